@@ -297,6 +297,15 @@ _NORMAL_DEPENDENCIES = {
         _COMMON_CONDITION: {
             "hashbrown": Label("@crates_vendor//:hashbrown-0.17.1"),
             "log": Label("@crates_vendor//:log-0.4.29"),
+            "serde": Label("@crates_vendor//:serde-1.0.229"),
+            "serde_json": Label("@crates_vendor//:serde_json-1.0.154"),
+        },
+    },
+    "macros": {
+        _COMMON_CONDITION: {
+            "proc-macro2": Label("@crates_vendor//:proc-macro2-1.0.106"),
+            "quote": Label("@crates_vendor//:quote-1.0.45"),
+            "syn": Label("@crates_vendor//:syn-2.0.119"),
         },
     },
 }
@@ -306,56 +315,91 @@ _NORMAL_ALIASES = {
         _COMMON_CONDITION: {
         },
     },
+    "macros": {
+        _COMMON_CONDITION: {
+        },
+    },
 }
 
 _NORMAL_DEV_DEPENDENCIES = {
     "": {
+        _COMMON_CONDITION: {
+            "serde": Label("@crates_vendor//:serde-1.0.229"),
+        },
+    },
+    "macros": {
+        _COMMON_CONDITION: {
+            "serde": Label("@crates_vendor//:serde-1.0.229"),
+            "trybuild": Label("@crates_vendor//:trybuild-1.0.116"),
+        },
     },
 }
 
 _NORMAL_DEV_ALIASES = {
     "": {
+        _COMMON_CONDITION: {
+        },
+    },
+    "macros": {
+        _COMMON_CONDITION: {
+        },
     },
 }
 
 _PROC_MACRO_DEPENDENCIES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _PROC_MACRO_ALIASES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _PROC_MACRO_DEV_DEPENDENCIES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _PROC_MACRO_DEV_ALIASES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _BUILD_DEPENDENCIES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _BUILD_ALIASES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _BUILD_PROC_MACRO_DEPENDENCIES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _BUILD_PROC_MACRO_ALIASES = {
     "": {
     },
+    "macros": {
+    },
 }
 
 _CONDITIONS = {
@@ -444,6 +488,16 @@ def crate_repositories():
         build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.foldhash-0.2.0.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "crates_vendor__glob-0.3.4",
+        sha256 = "e4eba85ea1d0a966a983acd07deee566e67395d2d96b6fb39e62b5a833f1eb0b",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/glob/0.3.4/download"],
+        strip_prefix = "glob-0.3.4",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.glob-0.3.4.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "crates_vendor__hashbrown-0.17.1",
@@ -454,6 +508,26 @@ def crate_repositories():
         build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.hashbrown-0.17.1.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "crates_vendor__indexmap-2.14.2",
+        sha256 = "cc4e190f5d26ca7051642629da2c52fc03bde85a03197c99408dcd291734c855",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/indexmap/2.14.2/download"],
+        strip_prefix = "indexmap-2.14.2",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.indexmap-2.14.2.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__itoa-1.0.18",
+        sha256 = "8f42a60cbdf9a97f5d2305f08a87dc4e09308d1276d28c869c684d7777685682",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/itoa/1.0.18/download"],
+        strip_prefix = "itoa-1.0.18",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.itoa-1.0.18.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "crates_vendor__log-0.4.29",
@@ -464,7 +538,243 @@ def crate_repositories():
         build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.log-0.4.29.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "crates_vendor__memchr-2.8.3",
+        sha256 = "cf8baf1c55e62ffcace7a9f06f4bd9cd3f0c4beb022d3b367256b91b87513d98",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/memchr/2.8.3/download"],
+        strip_prefix = "memchr-2.8.3",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.memchr-2.8.3.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__proc-macro2-1.0.106",
+        sha256 = "8fd00f0bb2e90d81d1044c2b32617f68fcb9fa3bb7640c23e9c748e53fb30934",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/proc-macro2/1.0.106/download"],
+        strip_prefix = "proc-macro2-1.0.106",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.proc-macro2-1.0.106.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__quote-1.0.45",
+        sha256 = "41f2619966050689382d2b44f664f4bc593e129785a36d6ee376ddf37259b924",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/quote/1.0.45/download"],
+        strip_prefix = "quote-1.0.45",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.quote-1.0.45.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__serde-1.0.229",
+        sha256 = "4148590afebada386688f18773da617792bf2ef03ffc1e4cbd2b1d45b023e0ba",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/serde/1.0.229/download"],
+        strip_prefix = "serde-1.0.229",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.serde-1.0.229.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__serde_core-1.0.229",
+        sha256 = "67dca2c9c51e58a4791a4b1ed58308b39c64224d349a935ab5039aa360942a48",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/serde_core/1.0.229/download"],
+        strip_prefix = "serde_core-1.0.229",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.serde_core-1.0.229.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__serde_derive-1.0.229",
+        sha256 = "e7a5d71263a5a7d47b41f6b3f06ba276f10cc18b0931f1799f710578e2309348",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/serde_derive/1.0.229/download"],
+        strip_prefix = "serde_derive-1.0.229",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.serde_derive-1.0.229.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__serde_json-1.0.154",
+        sha256 = "e7e9cc8b1b85264074fbcc02a88680c4096b1e47df8f739dceb03bf482f04bd6",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/serde_json/1.0.154/download"],
+        strip_prefix = "serde_json-1.0.154",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.serde_json-1.0.154.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__serde_spanned-1.1.2",
+        sha256 = "4e7523beb55eece201a2356bee0bbca0d1ab466c14c07703b2e0ee6d42cb0c2c",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/serde_spanned/1.1.2/download"],
+        strip_prefix = "serde_spanned-1.1.2",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.serde_spanned-1.1.2.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__syn-2.0.119",
+        sha256 = "872831b642d1a07999a962a351ed35b955ea2cfc8f3862091e2a240a84f17297",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/syn/2.0.119/download"],
+        strip_prefix = "syn-2.0.119",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.syn-2.0.119.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__syn-3.0.8",
+        sha256 = "01016da373cd8f7ef12624f796309f5c31ba8d646dd08856c02cd741d823c622",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/syn/3.0.8/download"],
+        strip_prefix = "syn-3.0.8",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.syn-3.0.8.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__target-triple-1.0.1",
+        sha256 = "c3a6bfce3d99adfa72d24750a61f782f3036a81e7f86d8841ee1326deaebd171",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/target-triple/1.0.1/download"],
+        strip_prefix = "target-triple-1.0.1",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.target-triple-1.0.1.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__termcolor-1.4.1",
+        sha256 = "06794f8f6c5c898b3275aebefa6b8a1cb24cd2c6c79397ab15774837a0bc5755",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/termcolor/1.4.1/download"],
+        strip_prefix = "termcolor-1.4.1",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.termcolor-1.4.1.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__toml-1.1.8-spec-1.1.0",
+        sha256 = "20489e00e4d8741d6be680764cc12e270655e375a20d1011e844a9c3379e678d",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/toml/1.1.8+spec-1.1.0/download"],
+        strip_prefix = "toml-1.1.8+spec-1.1.0",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.toml-1.1.8+spec-1.1.0.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__toml_datetime-1.1.2-spec-1.1.0",
+        sha256 = "2b86d767906c6c42421dcba507eb9d203e779497710a47782a224bb871653053",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/toml_datetime/1.1.2+spec-1.1.0/download"],
+        strip_prefix = "toml_datetime-1.1.2+spec-1.1.0",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.toml_datetime-1.1.2+spec-1.1.0.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__toml_parser-1.1.5-spec-1.1.0",
+        sha256 = "baa693a8032d7e1cada7d0041e96126df243179ff061456783ac7f12bda4744c",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/toml_parser/1.1.5+spec-1.1.0/download"],
+        strip_prefix = "toml_parser-1.1.5+spec-1.1.0",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.toml_parser-1.1.5+spec-1.1.0.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__toml_writer-1.1.3-spec-1.1.0",
+        sha256 = "06bdbd8cfc056b8d2e2e85f29b56a3bdbecb527cef81eb39e3e7b98af4652770",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/toml_writer/1.1.3+spec-1.1.0/download"],
+        strip_prefix = "toml_writer-1.1.3+spec-1.1.0",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.toml_writer-1.1.3+spec-1.1.0.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__trybuild-1.0.116",
+        sha256 = "47c635f0191bd3a2941013e5062667100969f8c4e9cd787c14f977265d73616e",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/trybuild/1.0.116/download"],
+        strip_prefix = "trybuild-1.0.116",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.trybuild-1.0.116.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__unicode-ident-1.0.24",
+        sha256 = "e6e4313cd5fcd3dad5cafa179702e2b244f760991f45397d14d4ebf38247da75",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/unicode-ident/1.0.24/download"],
+        strip_prefix = "unicode-ident-1.0.24",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.unicode-ident-1.0.24.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__winapi-util-0.1.11",
+        sha256 = "c2a7b1c03c876122aa43f3020e6c3c3ee5c05081c9a00739faf7503aeba10d22",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/winapi-util/0.1.11/download"],
+        strip_prefix = "winapi-util-0.1.11",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.winapi-util-0.1.11.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__windows-link-0.2.1",
+        sha256 = "f0805222e57f7521d6a62e36fa9163bc891acd422f971defe97d64e70d0a4fe5",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/windows-link/0.2.1/download"],
+        strip_prefix = "windows-link-0.2.1",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.windows-link-0.2.1.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__windows-sys-0.61.2",
+        sha256 = "ae137229bcbd6cdf0f7b80a31df61766145077ddf49416a728b02cb3921ff3fc",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/windows-sys/0.61.2/download"],
+        strip_prefix = "windows-sys-0.61.2",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.windows-sys-0.61.2.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__winnow-1.0.4",
+        sha256 = "23b97319f7b8343df12cc98938e5c3eb436064524c8d2b4e30a1d3a36eecdf81",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/winnow/1.0.4/download"],
+        strip_prefix = "winnow-1.0.4",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.winnow-1.0.4.bazel"),
+    )
+
+    maybe(
+        http_archive,
+        name = "crates_vendor__zmij-1.0.23",
+        sha256 = "29666d0abbfad1e3dc4dcf6144730dd3a3ab225bbbdac83319345b1b44ccfc1b",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/zmij/1.0.23/download"],
+        strip_prefix = "zmij-1.0.23",
+        build_file = Label("@proxy_wasm_rust_sdk//bazel/cargo/remote:BUILD.zmij-1.0.23.bazel"),
+    )
+
     return [
         struct(repo = "crates_vendor__hashbrown-0.17.1", is_dev_dep = False),
         struct(repo = "crates_vendor__log-0.4.29", is_dev_dep = False),
+        struct(repo = "crates_vendor__proc-macro2-1.0.106", is_dev_dep = False),
+        struct(repo = "crates_vendor__quote-1.0.45", is_dev_dep = False),
+        struct(repo = "crates_vendor__serde-1.0.229", is_dev_dep = False),
+        struct(repo = "crates_vendor__serde_json-1.0.154", is_dev_dep = False),
+        struct(repo = "crates_vendor__syn-2.0.119", is_dev_dep = False),
+        struct(repo = "crates_vendor__trybuild-1.0.116", is_dev_dep = True),
     ]
@@ -15,7 +15,11 @@
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-env-changed=RUSTFLAGS");
+    println!("cargo:rustc-check-cfg=cfg(fuzzing)");
     println!("cargo:rustc-check-cfg=cfg(nightly)");
+    println!(
+        "cargo:rustc-check-cfg=cfg(proxy_abi, values(\"0.1.0\", \"0.2.0\", \"0.2.1\", \"vnext\"))"
+    );
     println!("cargo:rustc-check-cfg=cfg(wasi_exec_model_reactor)");
 
     if let Some(toolchain) = std::env::var_os("RUSTUP_TOOLCHAIN") {
@@ -24,6 +28,19 @@ fn main() {
         }
     }
 
+    // The oldest ABI version wins when several are enabled, which keeps the
+    // features additive (e.g. with `--all-features`).
+    let abi = if std::env::var_os("CARGO_FEATURE_ABI_0_1_0").is_some() {
+        "0.1.0"
+    } else if std::env::var_os("CARGO_FEATURE_ABI_0_2_0").is_some() {
+        "0.2.0"
+    } else if std::env::var_os("CARGO_FEATURE_ABI_VNEXT").is_some() {
+        "vnext"
+    } else {
+        "0.2.1"
+    };
+    println!("cargo:rustc-cfg=proxy_abi=\"{abi}\"");
+
     if let Some(target_os) = std::env::var_os("CARGO_CFG_TARGET_OS") {
         if target_os != "wasi" {
             return;
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use proxy_wasm::envoy;
+use proxy_wasm::protobuf::Value;
 use proxy_wasm::traits::*;
 use proxy_wasm::types::*;
 
@@ -27,24 +29,23 @@ impl Context for MetadataHttp {}
 impl HttpContext for MetadataHttp {
     fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
         // Read data set by the lua filter
-        match self.get_property(vec![
-            "metadata",
-            "filter_metadata",
-            "envoy.filters.http.lua",
-            "uppercased-custom-metadata",
-        ]) {
-            Some(metadata) => match String::from_utf8(metadata) {
-                Ok(data) => {
-                    self.send_http_response(
-                        200,
-                        vec![("Powered-By", "proxy-wasm"), ("uppercased-metadata", &data)],
-                        Some(format!("Custom response with Envoy metadata: {data:?}\n").as_bytes()),
-                    );
-                    Action::Pause
-                }
-                _ => Action::Continue,
-            },
-            _ => Action::Continue,
+        let metadata = envoy::get_dynamic_metadata("envoy.filters.http.lua");
+        let Ok(Some(metadata)) = metadata else {
+            return Action::Continue;
+        };
+        match metadata
+            .get("uppercased-custom-metadata")
+            .and_then(Value::as_str)
+        {
+            Some(data) => {
+                self.send_http_response(
+                    200,
+                    vec![("Powered-By", "proxy-wasm"), ("uppercased-metadata", data)],
+                    Some(format!("Custom response with Envoy metadata: {data:?}\n").as_bytes()),
+                );
+                Action::Pause
+            }
+            None => Action::Continue,
         }
     }
 }
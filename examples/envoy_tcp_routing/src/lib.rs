@@ -25,15 +25,9 @@
 use std::net::{IpAddr, SocketAddr};
 
 use log::{info, warn};
-use prost::Message;
+use proxy_wasm::envoy::{self, LifeSpan};
 use proxy_wasm::traits::*;
 use proxy_wasm::types::*;
-use set_envoy_filter_state::{LifeSpan, SetEnvoyFilterStateArguments};
-
-// Include the generated protobuf code
-pub mod set_envoy_filter_state {
-    include!("generated/envoy.source.extensions.common.wasm.rs");
-}
 
 proxy_wasm::main! {{
     proxy_wasm::set_log_level(LogLevel::Info);
@@ -91,19 +85,9 @@ impl StreamContext for TcpRoutingStream {
         };
 
         let cluster = select_cluster(last_byte);
-        let args = SetEnvoyFilterStateArguments {
-            path: "envoy.tcp_proxy.cluster".to_string(),
-            value: cluster.to_string(),
-            span: LifeSpan::FilterChain as i32,
-        };
-
-        let mut buf = Vec::new();
-        if let Err(err) = args.encode(&mut buf) {
-            warn!("[TCP WASM] Failed to encode filter state: {}", err);
-            return Action::Continue;
-        }
-
-        if let Err(err) = self.call_foreign_function("set_envoy_filter_state", Some(&buf)) {
+        if let Err(err) =
+            envoy::set_filter_state("envoy.tcp_proxy.cluster", cluster, LifeSpan::FilterChain)
+        {
             warn!("[TCP WASM] Failed to set Envoy filter state: {:?}", err);
             return Action::Continue;
         }
@@ -12,52 +12,46 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use proxy_wasm::config::ConfiguredRoot;
 use proxy_wasm::traits::*;
 use proxy_wasm::types::*;
+use serde::Deserialize;
+use std::rc::Rc;
 
 proxy_wasm::main! {{
     proxy_wasm::set_log_level(LogLevel::Trace);
     proxy_wasm::set_root_context(|_| -> Box<dyn RootContext> {
-        Box::new(HttpConfigHeaderRoot {
-            header_content: String::new(),
-        })
+        Box::new(
+            ConfiguredRoot::<HttpConfig>::new()
+                .with_validator(|config| match config.header_name.is_empty() {
+                    true => Err("header_name must not be empty".to_string()),
+                    false => Ok(()),
+                })
+                .with_http_context(|_, config| Box::new(HttpConfigHeader { config })),
+        )
     });
 }}
 
-struct HttpConfigHeader {
+#[derive(Deserialize)]
+struct HttpConfig {
+    #[serde(default = "default_header_name")]
+    header_name: String,
     header_content: String,
 }
 
-impl Context for HttpConfigHeader {}
-
-impl HttpContext for HttpConfigHeader {
-    fn on_http_response_headers(&mut self, _: usize, _: bool) -> Action {
-        self.add_http_response_header("custom-header", self.header_content.as_str());
-        Action::Continue
-    }
+fn default_header_name() -> String {
+    "custom-header".to_string()
 }
 
-struct HttpConfigHeaderRoot {
-    header_content: String,
+struct HttpConfigHeader {
+    config: Rc<HttpConfig>,
 }
 
-impl Context for HttpConfigHeaderRoot {}
-
-impl RootContext for HttpConfigHeaderRoot {
-    fn on_configure(&mut self, _: usize) -> bool {
-        if let Some(config_bytes) = self.get_plugin_configuration() {
-            self.header_content = String::from_utf8(config_bytes).unwrap()
-        }
-        true
-    }
-
-    fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
-        Some(Box::new(HttpConfigHeader {
-            header_content: self.header_content.clone(),
-        }))
-    }
+impl Context for HttpConfigHeader {}
 
-    fn get_type(&self) -> Option<ContextType> {
-        Some(ContextType::HttpContext)
+impl HttpContext for HttpConfigHeader {
+    fn on_http_response_headers(&mut self, _: usize, _: bool) -> Action {
+        self.add_http_response_header(&self.config.header_name, &self.config.header_content);
+        Action::Continue
     }
 }
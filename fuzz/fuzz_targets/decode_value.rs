@@ -0,0 +1,28 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use proxy_wasm::protobuf::{decode_list_value, decode_struct, decode_value, encode_value};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_struct(data);
+    let _ = decode_list_value(data);
+    if let Ok(value) = decode_value(data) {
+        // Compare encodings, since NaN numbers aren't equal to themselves.
+        let encoded = encode_value(&value);
+        assert_eq!(encode_value(&decode_value(&encoded).unwrap()), encoded);
+    }
+});
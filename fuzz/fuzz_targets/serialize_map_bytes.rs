@@ -0,0 +1,27 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use proxy_wasm::hostcalls::{deserialize_map_bytes, serialize_map_bytes};
+
+fuzz_target!(|map: Vec<(String, Vec<u8>)>| {
+    let borrowed: Vec<(&str, &[u8])> = map
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_slice()))
+        .collect();
+    let serialized = serialize_map_bytes(&borrowed);
+    assert_eq!(deserialize_map_bytes(&serialized).unwrap(), map);
+});
@@ -0,0 +1,261 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Procedural macros for `proxy-wasm`, re-exported by it with the `macros` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Error, Expr, Fields, Ident, ItemStruct, Lit, Meta, Path, Token, Type};
+
+/// Registers the annotated struct as the plugin's [`HttpContext`].
+///
+/// Generates the `proxy_wasm::main!` entrypoint, a root context that creates
+/// the struct for each HTTP stream, and an empty `Context` implementation.
+///
+/// ```ignore
+/// #[proxy_wasm::http_filter(config = Config, log_level = Info)]
+/// struct Filter {
+///     config: Rc<Config>,
+///     context_id: u32,
+///     seen: bool,
+/// }
+///
+/// impl HttpContext for Filter { ... }
+/// ```
+///
+/// Arguments:
+///
+/// - `config = Type`: deserializes the plugin configuration into `Type` with
+///   [`ConfiguredRoot`] (which needs the `json` or `yaml` feature), and passes
+///   it to the `config: Rc<Type>` field.
+/// - `log_level = Level`: sets the log level on start.
+/// - `impl_context = false`: skips the `Context` implementation, so that the
+///   struct can implement its callbacks.
+///
+/// A field named `context_id` is set to the ID of the stream, and all other
+/// fields to their default values.
+///
+/// The attribute must be used in the crate root, like `proxy_wasm::main!`, and
+/// at most once per plugin, together with [`stream_filter`].
+///
+/// [`HttpContext`]: https://docs.rs/proxy-wasm/latest/proxy_wasm/traits/trait.HttpContext.html
+/// [`ConfiguredRoot`]: https://docs.rs/proxy-wasm/latest/proxy_wasm/config/struct.ConfiguredRoot.html
+#[proc_macro_attribute]
+pub fn http_filter(args: TokenStream, input: TokenStream) -> TokenStream {
+    expand(Kind::Http, args.into(), input.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Registers the annotated struct as the plugin's [`StreamContext`].
+///
+/// Takes the same arguments as [`http_filter`].
+///
+/// [`StreamContext`]: https://docs.rs/proxy-wasm/latest/proxy_wasm/traits/trait.StreamContext.html
+#[proc_macro_attribute]
+pub fn stream_filter(args: TokenStream, input: TokenStream) -> TokenStream {
+    expand(Kind::Stream, args.into(), input.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Copy, Clone)]
+enum Kind {
+    Http,
+    Stream,
+}
+
+struct Args {
+    config: Option<Type>,
+    log_level: Option<Ident>,
+    impl_context: bool,
+}
+
+fn parse_args(args: TokenStream2) -> syn::Result<Args> {
+    let mut parsed = Args {
+        config: None,
+        log_level: None,
+        impl_context: true,
+    };
+    let metas = Punctuated::<Meta, Token![,]>::parse_terminated.parse2(args)?;
+    for meta in metas {
+        let name_value = match meta {
+            Meta::NameValue(name_value) => name_value,
+            meta => return Err(Error::new_spanned(meta, "expected `name = value`")),
+        };
+        let path = &name_value.path;
+        let value = &name_value.value;
+        if path.is_ident("config") {
+            parsed.config = Some(syn::parse2(quote!(#value))?);
+        } else if path.is_ident("log_level") {
+            parsed.log_level = Some(match value {
+                Expr::Path(expr) if expr.path.get_ident().is_some() => {
+                    expr.path.get_ident().unwrap().clone()
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        value,
+                        "expected a log level, e.g. `Info`",
+                    ));
+                }
+            });
+        } else if path.is_ident("impl_context") {
+            parsed.impl_context = match value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Bool(lit) => lit.value,
+                    _ => return Err(Error::new_spanned(value, "expected `true` or `false`")),
+                },
+                _ => return Err(Error::new_spanned(value, "expected `true` or `false`")),
+            };
+        } else {
+            return Err(Error::new_spanned(
+                path,
+                "expected `config`, `log_level` or `impl_context`",
+            ));
+        }
+    }
+    Ok(parsed)
+}
+
+// Initializes each field of the struct, see `http_filter`.
+fn constructor(item: &ItemStruct, has_config: bool) -> syn::Result<TokenStream2> {
+    let name = &item.ident;
+    let fields = match &item.fields {
+        Fields::Named(fields) => &fields.named,
+        Fields::Unit => return Ok(quote!(#name)),
+        Fields::Unnamed(_) => {
+            return Err(Error::new_spanned(
+                &item.fields,
+                "filters must have named fields or be unit structs",
+            ));
+        }
+    };
+    let fields = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        if ident == "config" && has_config {
+            quote!(#ident: config)
+        } else if ident == "context_id" {
+            quote!(#ident: context_id)
+        } else {
+            quote!(#ident: ::core::default::Default::default())
+        }
+    });
+    Ok(quote!(#name { #(#fields),* }))
+}
+
+fn expand(kind: Kind, args: TokenStream2, input: TokenStream2) -> syn::Result<TokenStream2> {
+    let args = parse_args(args)?;
+    let item: ItemStruct = syn::parse2(input)?;
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &item.generics,
+            "filters can't have generic parameters",
+        ));
+    }
+    let name = &item.ident;
+    let new_context = constructor(&item, args.config.is_some())?;
+
+    let (context_trait, context_type, create_context, with_context): (Path, _, _, _) = match kind {
+        Kind::Http => (
+            syn::parse_quote!(::proxy_wasm::traits::HttpContext),
+            quote!(HttpContext),
+            quote!(create_http_context),
+            quote!(with_http_context),
+        ),
+        Kind::Stream => (
+            syn::parse_quote!(::proxy_wasm::traits::StreamContext),
+            quote!(StreamContext),
+            quote!(create_stream_context),
+            quote!(with_stream_context),
+        ),
+    };
+
+    let impl_context = args.impl_context.then(|| {
+        quote! {
+            impl ::proxy_wasm::traits::Context for #name {}
+        }
+    });
+
+    let set_log_level = args.log_level.map(|level| {
+        quote! {
+            ::proxy_wasm::set_log_level(::proxy_wasm::types::LogLevel::#level);
+        }
+    });
+
+    // Defined by each filter, so that registering more than one filter in the
+    // same plugin fails to compile with an error that names the problem.
+    let module = Ident::new(
+        "only_one_http_filter_or_stream_filter_per_plugin",
+        Span::call_site(),
+    );
+    let root = Ident::new("Root", Span::call_site());
+    let (root_item, new_root) = match &args.config {
+        Some(config) => (
+            quote! {
+                pub(super) type #root = ::proxy_wasm::config::ConfiguredRoot<#config>;
+            },
+            quote! {
+                #module::#root::new().#with_context(|context_id, config| {
+                    let _ = (context_id, &config);
+                    ::std::boxed::Box::new(#new_context)
+                })
+            },
+        ),
+        None => (
+            quote! {
+                pub(super) struct #root;
+
+                impl ::proxy_wasm::traits::Context for #root {}
+
+                impl ::proxy_wasm::traits::RootContext for #root {
+                    fn #create_context(
+                        &self,
+                        context_id: u32,
+                    ) -> ::core::option::Option<::std::boxed::Box<dyn #context_trait>> {
+                        let _ = context_id;
+                        ::core::option::Option::Some(::std::boxed::Box::new(#new_context))
+                    }
+
+                    fn get_type(&self) -> ::core::option::Option<::proxy_wasm::types::ContextType> {
+                        ::core::option::Option::Some(::proxy_wasm::types::ContextType::#context_type)
+                    }
+                }
+            },
+            quote!(#module::#root),
+        ),
+    };
+
+    Ok(quote! {
+        #[doc(hidden)]
+        mod #module {
+            use super::*;
+
+            #root_item
+        }
+
+        #item
+
+        #impl_context
+
+        ::proxy_wasm::main! {{
+            #set_log_level
+            ::proxy_wasm::set_root_context(|_| -> ::std::boxed::Box<dyn ::proxy_wasm::traits::RootContext> {
+                ::std::boxed::Box::new(#new_root)
+            });
+        }}
+    })
+}
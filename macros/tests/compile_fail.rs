@@ -1,4 +1,4 @@
-// Copyright 2020 Google LLC
+// Copyright 2026 Google LLC
 //
 // Licensed under the Apache License, Version 2.0 (the "License");
 // you may not use this file except in compliance with the License.
@@ -12,14 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fs;
-
-fn main() {
-    println!("cargo:rerun-if-changed=src/set_envoy_filter_state.proto");
-    let out_dir = "src/generated";
-    fs::create_dir_all(out_dir).unwrap();
-    prost_build::Config::new()
-        .out_dir(out_dir)
-        .compile_protos(&["src/set_envoy_filter_state.proto"], &["src/"])
-        .unwrap();
+#[test]
+fn test_compile_fail() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
 }
@@ -0,0 +1,72 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use proxy_wasm::testing;
+use proxy_wasm::traits::*;
+use proxy_wasm::types::*;
+use serde::Deserialize;
+use std::rc::Rc;
+
+#[derive(Deserialize)]
+struct Config {
+    header: String,
+}
+
+#[proxy_wasm::http_filter(config = Config, log_level = Info)]
+struct Filter {
+    config: Rc<Config>,
+    context_id: u32,
+    calls: u32,
+}
+
+impl HttpContext for Filter {
+    fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+        self.calls += 1;
+        let value = format!("{}/{}", self.context_id, self.calls);
+        self.set_http_request_header(&self.config.header, Some(&value));
+        Action::Continue
+    }
+}
+
+// Exported by `proxy_wasm`, and called by the host.
+unsafe extern "C" {
+    fn proxy_on_context_create(context_id: u32, root_context_id: u32);
+    fn proxy_on_vm_start(context_id: u32, vm_configuration_size: usize) -> bool;
+    fn proxy_on_configure(context_id: u32, plugin_configuration_size: usize) -> bool;
+    fn proxy_on_request_headers(context_id: u32, num_headers: usize, end_of_stream: bool)
+    -> Action;
+}
+
+#[test]
+fn test_http_filter() {
+    testing::reset();
+    _initialize();
+    unsafe {
+        proxy_on_context_create(1, 0);
+        assert!(proxy_on_vm_start(1, 0));
+        testing::set_buffer(BufferType::PluginConfiguration, Some(b"{}"));
+        assert!(!proxy_on_configure(1, 2));
+        testing::set_buffer(
+            BufferType::PluginConfiguration,
+            Some(b"{\"header\": \"x-filter\"}"),
+        );
+        assert!(proxy_on_configure(1, 22));
+        proxy_on_context_create(2, 1);
+        assert_eq!(proxy_on_request_headers(2, 0, true), Action::Continue);
+    }
+    assert_eq!(
+        testing::get_map_value(MapType::HttpRequestHeaders, "x-filter"),
+        Some("2/1".to_string())
+    );
+}
@@ -0,0 +1,50 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use proxy_wasm::testing;
+use proxy_wasm::traits::*;
+use proxy_wasm::types::*;
+
+#[proxy_wasm::stream_filter(impl_context = false)]
+struct Filter;
+
+impl Context for Filter {}
+
+impl StreamContext for Filter {
+    fn on_new_connection(&mut self) -> Action {
+        self.set_property(vec!["filter"], Some(b"seen"));
+        Action::Pause
+    }
+}
+
+// Exported by `proxy_wasm`, and called by the host.
+unsafe extern "C" {
+    fn proxy_on_context_create(context_id: u32, root_context_id: u32);
+    fn proxy_on_new_connection(context_id: u32) -> Action;
+}
+
+#[test]
+fn test_stream_filter() {
+    testing::reset();
+    _initialize();
+    unsafe {
+        proxy_on_context_create(1, 0);
+        proxy_on_context_create(2, 1);
+        assert_eq!(proxy_on_new_connection(2), Action::Pause);
+    }
+    assert_eq!(
+        proxy_wasm::hostcalls::get_property(vec!["filter"]),
+        Ok(Some(b"seen".to_vec()))
+    );
+}
@@ -1,4 +1,4 @@
-// Copyright 2020 Google LLC
+// Copyright 2026 Google LLC
 //
 // Licensed under the Apache License, Version 2.0 (the "License");
 // you may not use this file except in compliance with the License.
@@ -12,20 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-syntax = "proto3";
+#[proxy_wasm::http_filter]
+struct HttpFilter;
 
-package envoy.source.extensions.common.wasm;
+impl proxy_wasm::traits::HttpContext for HttpFilter {}
 
-enum LifeSpan {
-  FilterChain = 0;
-  DownstreamRequest = 1;
-  DownstreamConnection = 2;
-}
+#[proxy_wasm::stream_filter]
+struct StreamFilter;
 
-// Argument expected by set_envoy_filter_state in envoy
-// https://github.com/envoyproxy/envoy/blob/d741713c376d1e024236519fb59406c05702ad77/source/extensions/common/wasm/foreign.cc#L116
-message SetEnvoyFilterStateArguments {
-  string path = 1;
-  string value = 2;
-  LifeSpan span = 3;
-}
+impl proxy_wasm::traits::StreamContext for StreamFilter {}
+
+fn main() {}
@@ -0,0 +1,73 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Values defined by the Proxy-Wasm ABI v0.2.1.
+
+pub const STATUS_OK: i32 = 0;
+pub const STATUS_NOT_FOUND: i32 = 1;
+pub const STATUS_BAD_ARGUMENT: i32 = 2;
+pub const STATUS_PARSE_FAILURE: i32 = 4;
+pub const STATUS_EMPTY: i32 = 7;
+pub const STATUS_CAS_MISMATCH: i32 = 8;
+
+pub const ACTION_CONTINUE: i32 = 0;
+pub const ACTION_PAUSE: i32 = 1;
+
+pub const STREAM_HTTP_REQUEST: u32 = 0;
+pub const STREAM_HTTP_RESPONSE: u32 = 1;
+
+pub const BUFFER_HTTP_REQUEST_BODY: u32 = 0;
+pub const BUFFER_HTTP_RESPONSE_BODY: u32 = 1;
+pub const BUFFER_HTTP_CALL_RESPONSE_BODY: u32 = 4;
+pub const BUFFER_VM_CONFIGURATION: u32 = 6;
+pub const BUFFER_PLUGIN_CONFIGURATION: u32 = 7;
+
+pub const MAP_HTTP_REQUEST_HEADERS: u32 = 0;
+pub const MAP_HTTP_REQUEST_TRAILERS: u32 = 1;
+pub const MAP_HTTP_RESPONSE_HEADERS: u32 = 2;
+pub const MAP_HTTP_RESPONSE_TRAILERS: u32 = 3;
+pub const MAP_HTTP_CALL_RESPONSE_HEADERS: u32 = 6;
+pub const MAP_HTTP_CALL_RESPONSE_TRAILERS: u32 = 7;
+
+pub const METRIC_COUNTER: u32 = 0;
+
+pub fn log_level_name(level: i32) -> &'static str {
+    match level {
+        0 => "trace",
+        1 => "debug",
+        2 => "info",
+        3 => "warn",
+        4 => "error",
+        5 => "critical",
+        _ => "unknown",
+    }
+}
+
+pub fn action_name(action: i32) -> String {
+    match action {
+        ACTION_CONTINUE => "Continue".to_string(),
+        ACTION_PAUSE => "Pause".to_string(),
+        _ => format!("Unknown({action})"),
+    }
+}
+
+pub fn stream_name(stream_type: u32) -> &'static str {
+    match stream_type {
+        STREAM_HTTP_REQUEST => "request",
+        STREAM_HTTP_RESPONSE => "response",
+        2 => "downstream",
+        3 => "upstream",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,883 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::abi::*;
+use anyhow::{Result, anyhow, bail};
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+use wasmtime::{Caller, Extern, Linker};
+use wasmtime_wasi::preview1::WasiP1Ctx;
+
+pub type Map = Vec<(String, Vec<u8>)>;
+
+/// Something the plugin asked the host to do, reported after each callback.
+#[derive(Debug)]
+pub enum Event {
+    Log {
+        level: i32,
+        message: String,
+    },
+    HttpCall {
+        token_id: u32,
+        upstream: String,
+        headers: Map,
+        body: Vec<u8>,
+        timeout: u32,
+    },
+    GrpcCall {
+        token_id: u32,
+        upstream: String,
+        service: String,
+        method: String,
+    },
+    LocalResponse {
+        status_code: u32,
+        details: String,
+        headers: Map,
+        body: Vec<u8>,
+    },
+    SetTickPeriod(u32),
+    ContinueStream(u32),
+    CloseStream(u32),
+    SetProperty {
+        path: Vec<String>,
+        value: Vec<u8>,
+    },
+    Done,
+}
+
+pub struct Metric {
+    pub name: String,
+    pub metric_type: u32,
+    pub value: u64,
+}
+
+struct Queue {
+    vm_id: String,
+    name: String,
+    items: VecDeque<Vec<u8>>,
+}
+
+pub struct Host {
+    wasi: WasiP1Ctx,
+    pub buffers: HashMap<u32, Vec<u8>>,
+    pub maps: HashMap<u32, Map>,
+    pub properties: HashMap<Vec<u8>, Vec<u8>>,
+    pub metrics: Vec<Metric>,
+    pub events: Vec<Event>,
+    shared_data: HashMap<String, (Vec<u8>, u32)>,
+    queues: Vec<Queue>,
+    vm_id: String,
+    next_token_id: u32,
+}
+
+impl Host {
+    pub fn new(wasi: WasiP1Ctx, vm_id: &str) -> Host {
+        Host {
+            wasi,
+            buffers: HashMap::new(),
+            maps: HashMap::new(),
+            properties: HashMap::new(),
+            metrics: Vec::new(),
+            events: Vec::new(),
+            shared_data: HashMap::new(),
+            queues: Vec::new(),
+            vm_id: vm_id.to_string(),
+            next_token_id: 1,
+        }
+    }
+
+    pub fn set_map(&mut self, map_type: u32, map: &[(String, String)]) {
+        let map = map
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.as_bytes().to_vec()))
+            .collect();
+        self.maps.insert(map_type, map);
+    }
+
+    fn next_token_id(&mut self) -> u32 {
+        let token_id = self.next_token_id;
+        self.next_token_id += 1;
+        token_id
+    }
+}
+
+pub fn serialize_map(map: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(map.len() as u32).to_le_bytes());
+    for (name, value) in map {
+        bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    }
+    for (name, value) in map {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(value);
+        bytes.push(0);
+    }
+    bytes
+}
+
+pub fn deserialize_map(bytes: &[u8]) -> Result<Map> {
+    fn read_u32(bytes: &[u8], offset: usize) -> Result<usize> {
+        let size = bytes
+            .get(offset..offset + 4)
+            .ok_or_else(|| anyhow!("truncated map"))?;
+        Ok(u32::from_le_bytes(size.try_into()?) as usize)
+    }
+    fn read_str(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8]> {
+        bytes
+            .get(offset..offset + size)
+            .ok_or_else(|| anyhow!("truncated map"))
+    }
+
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let count = read_u32(bytes, 0)?;
+    let mut map = Vec::with_capacity(count);
+    let mut p = 4 + count * 8;
+    for n in 0..count {
+        let name_size = read_u32(bytes, 4 + n * 8)?;
+        let value_size = read_u32(bytes, 8 + n * 8)?;
+        let name = String::from_utf8(read_str(bytes, p, name_size)?.to_vec())?;
+        p += name_size + 1;
+        let value = read_str(bytes, p, value_size)?.to_vec();
+        p += value_size + 1;
+        map.push((name, value));
+    }
+    Ok(map)
+}
+
+fn read(caller: &mut Caller<'_, Host>, ptr: i32, size: i32) -> Result<Vec<u8>> {
+    let memory = match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => memory,
+        _ => bail!("plugin doesn't export memory"),
+    };
+    let mut bytes = vec![0; size as u32 as usize];
+    memory.read(&caller, ptr as u32 as usize, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_string(caller: &mut Caller<'_, Host>, ptr: i32, size: i32) -> Result<String> {
+    Ok(String::from_utf8_lossy(&read(caller, ptr, size)?).into_owned())
+}
+
+fn write(caller: &mut Caller<'_, Host>, ptr: i32, bytes: &[u8]) -> Result<()> {
+    let memory = match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => memory,
+        _ => bail!("plugin doesn't export memory"),
+    };
+    memory.write(caller, ptr as u32 as usize, bytes)?;
+    Ok(())
+}
+
+fn write_u32(caller: &mut Caller<'_, Host>, ptr: i32, value: u32) -> Result<()> {
+    write(caller, ptr, &value.to_le_bytes())
+}
+
+// Copies `bytes` into memory allocated by the plugin, and returns its
+// location through `return_data` and `return_size`.
+fn write_bytes(
+    caller: &mut Caller<'_, Host>,
+    bytes: &[u8],
+    return_data: i32,
+    return_size: i32,
+) -> Result<()> {
+    let mut ptr = 0;
+    if !bytes.is_empty() {
+        let allocate = ["proxy_on_memory_allocate", "malloc"]
+            .iter()
+            .find_map(|name| caller.get_export(name).and_then(Extern::into_func))
+            .ok_or_else(|| anyhow!("plugin doesn't export proxy_on_memory_allocate"))?;
+        ptr = allocate
+            .typed::<i32, i32>(&caller)?
+            .call(&mut *caller, bytes.len() as i32)?;
+        if ptr == 0 {
+            bail!("plugin failed to allocate {} bytes", bytes.len());
+        }
+        write(caller, ptr, bytes)?;
+    }
+    write_u32(caller, return_data, ptr as u32)?;
+    write_u32(caller, return_size, bytes.len() as u32)
+}
+
+pub fn add_to_linker(linker: &mut Linker<Host>) -> Result<()> {
+    wasmtime_wasi::preview1::add_to_linker_sync(linker, |host: &mut Host| &mut host.wasi)?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_log",
+        |mut caller: Caller<'_, Host>, level: i32, data: i32, size: i32| -> Result<i32> {
+            let message = read_string(&mut caller, data, size)?;
+            caller.data_mut().events.push(Event::Log { level, message });
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_log_level",
+        |mut caller: Caller<'_, Host>, return_level: i32| -> Result<i32> {
+            write_u32(&mut caller, return_level, 0)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_current_time_nanoseconds",
+        |mut caller: Caller<'_, Host>, return_time: i32| -> Result<i32> {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+            write(&mut caller, return_time, &now.to_le_bytes())?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_tick_period_milliseconds",
+        |mut caller: Caller<'_, Host>, period: i32| -> i32 {
+            let event = Event::SetTickPeriod(period as u32);
+            caller.data_mut().events.push(event);
+            STATUS_OK
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_buffer_bytes",
+        |mut caller: Caller<'_, Host>,
+         buffer_type: i32,
+         start: i32,
+         max_size: i32,
+         return_data: i32,
+         return_size: i32|
+         -> Result<i32> {
+            let Some(buffer) = caller.data().buffers.get(&(buffer_type as u32)) else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            let start = start as u32 as usize;
+            if start > buffer.len() {
+                return Ok(STATUS_BAD_ARGUMENT);
+            }
+            let end = buffer
+                .len()
+                .min(start.saturating_add(max_size as u32 as usize));
+            let bytes = buffer[start..end].to_vec();
+            write_bytes(&mut caller, &bytes, return_data, return_size)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_buffer_bytes",
+        |mut caller: Caller<'_, Host>,
+         buffer_type: i32,
+         start: i32,
+         size: i32,
+         data: i32,
+         data_size: i32|
+         -> Result<i32> {
+            let bytes = read(&mut caller, data, data_size)?;
+            let buffer = caller
+                .data_mut()
+                .buffers
+                .entry(buffer_type as u32)
+                .or_default();
+            let start = start as u32 as usize;
+            if start > buffer.len() {
+                return Ok(STATUS_BAD_ARGUMENT);
+            }
+            let end = buffer.len().min(start.saturating_add(size as u32 as usize));
+            buffer.splice(start..end, bytes);
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_header_map_pairs",
+        |mut caller: Caller<'_, Host>,
+         map_type: i32,
+         return_data: i32,
+         return_size: i32|
+         -> Result<i32> {
+            let Some(map) = caller.data().maps.get(&(map_type as u32)) else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            let bytes = serialize_map(map);
+            write_bytes(&mut caller, &bytes, return_data, return_size)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_header_map_pairs",
+        |mut caller: Caller<'_, Host>, map_type: i32, data: i32, size: i32| -> Result<i32> {
+            let bytes = read(&mut caller, data, size)?;
+            let Ok(map) = deserialize_map(&bytes) else {
+                return Ok(STATUS_PARSE_FAILURE);
+            };
+            let map = map
+                .into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value))
+                .collect();
+            caller.data_mut().maps.insert(map_type as u32, map);
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map_type: i32,
+         key_data: i32,
+         key_size: i32,
+         return_data: i32,
+         return_size: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_data, key_size)?;
+            let value = caller
+                .data()
+                .maps
+                .get(&(map_type as u32))
+                .and_then(|map| map.iter().find(|(name, _)| name.eq_ignore_ascii_case(&key)))
+                .map(|(_, value)| value.clone());
+            let Some(value) = value else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            write_bytes(&mut caller, &value, return_data, return_size)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_remove_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map_type: i32,
+         key_data: i32,
+         key_size: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_data, key_size)?;
+            let map = caller.data_mut().maps.entry(map_type as u32).or_default();
+            map.retain(|(name, _)| !name.eq_ignore_ascii_case(&key));
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_replace_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map_type: i32,
+         key_data: i32,
+         key_size: i32,
+         value_data: i32,
+         value_size: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_data, key_size)?.to_lowercase();
+            let value = read(&mut caller, value_data, value_size)?;
+            let map = caller.data_mut().maps.entry(map_type as u32).or_default();
+            let mut found = false;
+            map.retain_mut(|(name, current)| {
+                if !name.eq_ignore_ascii_case(&key) {
+                    return true;
+                }
+                if found {
+                    return false;
+                }
+                found = true;
+                *current = value.clone();
+                true
+            });
+            if !found {
+                map.push((key, value));
+            }
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_add_header_map_value",
+        |mut caller: Caller<'_, Host>,
+         map_type: i32,
+         key_data: i32,
+         key_size: i32,
+         value_data: i32,
+         value_size: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_data, key_size)?.to_lowercase();
+            let value = read(&mut caller, value_data, value_size)?;
+            let map = caller.data_mut().maps.entry(map_type as u32).or_default();
+            map.push((key, value));
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_property",
+        |mut caller: Caller<'_, Host>,
+         path_data: i32,
+         path_size: i32,
+         return_data: i32,
+         return_size: i32|
+         -> Result<i32> {
+            let path = read(&mut caller, path_data, path_size)?;
+            let Some(value) = caller.data().properties.get(&path).cloned() else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            write_bytes(&mut caller, &value, return_data, return_size)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_property",
+        |mut caller: Caller<'_, Host>,
+         path_data: i32,
+         path_size: i32,
+         value_data: i32,
+         value_size: i32|
+         -> Result<i32> {
+            let path = read(&mut caller, path_data, path_size)?;
+            let value = read(&mut caller, value_data, value_size)?;
+            let host = caller.data_mut();
+            host.events.push(Event::SetProperty {
+                path: path
+                    .split(|b| *b == 0)
+                    .map(|part| String::from_utf8_lossy(part).into_owned())
+                    .collect(),
+                value: value.clone(),
+            });
+            host.properties.insert(path, value);
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_shared_data",
+        |mut caller: Caller<'_, Host>,
+         key_data: i32,
+         key_size: i32,
+         return_data: i32,
+         return_size: i32,
+         return_cas: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_data, key_size)?;
+            let Some((value, cas)) = caller.data().shared_data.get(&key).cloned() else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            write_bytes(&mut caller, &value, return_data, return_size)?;
+            write_u32(&mut caller, return_cas, cas)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_shared_data",
+        |mut caller: Caller<'_, Host>,
+         key_data: i32,
+         key_size: i32,
+         value_data: i32,
+         value_size: i32,
+         cas: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_data, key_size)?;
+            let value = read(&mut caller, value_data, value_size)?;
+            let shared_data = &mut caller.data_mut().shared_data;
+            let current_cas = shared_data.get(&key).map_or(0, |(_, cas)| *cas);
+            if cas != 0 && cas as u32 != current_cas {
+                return Ok(STATUS_CAS_MISMATCH);
+            }
+            shared_data.insert(key, (value, current_cas + 1));
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_register_shared_queue",
+        |mut caller: Caller<'_, Host>,
+         name_data: i32,
+         name_size: i32,
+         return_id: i32|
+         -> Result<i32> {
+            let name = read_string(&mut caller, name_data, name_size)?;
+            let host = caller.data_mut();
+            let vm_id = host.vm_id.clone();
+            let index = match host
+                .queues
+                .iter()
+                .position(|queue| queue.vm_id == vm_id && queue.name == name)
+            {
+                Some(index) => index,
+                None => {
+                    host.queues.push(Queue {
+                        vm_id,
+                        name,
+                        items: VecDeque::new(),
+                    });
+                    host.queues.len() - 1
+                }
+            };
+            write_u32(&mut caller, return_id, index as u32 + 1)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_resolve_shared_queue",
+        |mut caller: Caller<'_, Host>,
+         vm_id_data: i32,
+         vm_id_size: i32,
+         name_data: i32,
+         name_size: i32,
+         return_id: i32|
+         -> Result<i32> {
+            let vm_id = read_string(&mut caller, vm_id_data, vm_id_size)?;
+            let name = read_string(&mut caller, name_data, name_size)?;
+            let Some(index) = caller
+                .data()
+                .queues
+                .iter()
+                .position(|queue| queue.vm_id == vm_id && queue.name == name)
+            else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            write_u32(&mut caller, return_id, index as u32 + 1)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_dequeue_shared_queue",
+        |mut caller: Caller<'_, Host>,
+         queue_id: i32,
+         return_data: i32,
+         return_size: i32|
+         -> Result<i32> {
+            let index = (queue_id as u32 as usize).wrapping_sub(1);
+            let Some(queue) = caller.data_mut().queues.get_mut(index) else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            let Some(item) = queue.items.pop_front() else {
+                return Ok(STATUS_EMPTY);
+            };
+            write_bytes(&mut caller, &item, return_data, return_size)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_enqueue_shared_queue",
+        |mut caller: Caller<'_, Host>, queue_id: i32, data: i32, size: i32| -> Result<i32> {
+            let item = read(&mut caller, data, size)?;
+            let index = (queue_id as u32 as usize).wrapping_sub(1);
+            let Some(queue) = caller.data_mut().queues.get_mut(index) else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            queue.items.push_back(item);
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_continue_stream",
+        |mut caller: Caller<'_, Host>, stream_type: i32| -> i32 {
+            let event = Event::ContinueStream(stream_type as u32);
+            caller.data_mut().events.push(event);
+            STATUS_OK
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_close_stream",
+        |mut caller: Caller<'_, Host>, stream_type: i32| -> i32 {
+            let event = Event::CloseStream(stream_type as u32);
+            caller.data_mut().events.push(event);
+            STATUS_OK
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_send_local_response",
+        |mut caller: Caller<'_, Host>,
+         status_code: i32,
+         details_data: i32,
+         details_size: i32,
+         body_data: i32,
+         body_size: i32,
+         headers_data: i32,
+         headers_size: i32,
+         _grpc_status: i32|
+         -> Result<i32> {
+            let details = read_string(&mut caller, details_data, details_size)?;
+            let body = read(&mut caller, body_data, body_size)?;
+            let Ok(headers) = deserialize_map(&read(&mut caller, headers_data, headers_size)?)
+            else {
+                return Ok(STATUS_PARSE_FAILURE);
+            };
+            caller.data_mut().events.push(Event::LocalResponse {
+                status_code: status_code as u32,
+                details,
+                headers,
+                body,
+            });
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_http_call",
+        |mut caller: Caller<'_, Host>,
+         upstream_data: i32,
+         upstream_size: i32,
+         headers_data: i32,
+         headers_size: i32,
+         body_data: i32,
+         body_size: i32,
+         _trailers_data: i32,
+         _trailers_size: i32,
+         timeout: i32,
+         return_token: i32|
+         -> Result<i32> {
+            let upstream = read_string(&mut caller, upstream_data, upstream_size)?;
+            let Ok(headers) = deserialize_map(&read(&mut caller, headers_data, headers_size)?)
+            else {
+                return Ok(STATUS_PARSE_FAILURE);
+            };
+            let body = read(&mut caller, body_data, body_size)?;
+            let host = caller.data_mut();
+            let token_id = host.next_token_id();
+            host.events.push(Event::HttpCall {
+                token_id,
+                upstream,
+                headers,
+                body,
+                timeout: timeout as u32,
+            });
+            write_u32(&mut caller, return_token, token_id)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_grpc_call",
+        |mut caller: Caller<'_, Host>,
+         upstream_data: i32,
+         upstream_size: i32,
+         service_data: i32,
+         service_size: i32,
+         method_data: i32,
+         method_size: i32,
+         _metadata_data: i32,
+         _metadata_size: i32,
+         _message_data: i32,
+         _message_size: i32,
+         _timeout: i32,
+         return_token: i32|
+         -> Result<i32> {
+            let upstream = read_string(&mut caller, upstream_data, upstream_size)?;
+            let service = read_string(&mut caller, service_data, service_size)?;
+            let method = read_string(&mut caller, method_data, method_size)?;
+            let host = caller.data_mut();
+            let token_id = host.next_token_id();
+            host.events.push(Event::GrpcCall {
+                token_id,
+                upstream,
+                service,
+                method,
+            });
+            write_u32(&mut caller, return_token, token_id)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_grpc_stream",
+        |mut caller: Caller<'_, Host>,
+         upstream_data: i32,
+         upstream_size: i32,
+         service_data: i32,
+         service_size: i32,
+         method_data: i32,
+         method_size: i32,
+         _metadata_data: i32,
+         _metadata_size: i32,
+         return_token: i32|
+         -> Result<i32> {
+            let upstream = read_string(&mut caller, upstream_data, upstream_size)?;
+            let service = read_string(&mut caller, service_data, service_size)?;
+            let method = read_string(&mut caller, method_data, method_size)?;
+            let host = caller.data_mut();
+            let token_id = host.next_token_id();
+            host.events.push(Event::GrpcCall {
+                token_id,
+                upstream,
+                service,
+                method,
+            });
+            write_u32(&mut caller, return_token, token_id)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_grpc_send",
+        |_: Caller<'_, Host>, _token: i32, _data: i32, _size: i32, _end_stream: i32| -> i32 {
+            STATUS_OK
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_grpc_cancel",
+        |_: Caller<'_, Host>, _token: i32| -> i32 { STATUS_OK },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_grpc_close",
+        |_: Caller<'_, Host>, _token: i32| -> i32 { STATUS_OK },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_status",
+        |mut caller: Caller<'_, Host>,
+         return_code: i32,
+         return_data: i32,
+         return_size: i32|
+         -> Result<i32> {
+            write_u32(&mut caller, return_code, 0)?;
+            write_bytes(&mut caller, &[], return_data, return_size)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_set_effective_context",
+        |_: Caller<'_, Host>, _context_id: i32| -> i32 { STATUS_OK },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_call_foreign_function",
+        |_: Caller<'_, Host>,
+         _name_data: i32,
+         _name_size: i32,
+         _arguments_data: i32,
+         _arguments_size: i32,
+         _return_data: i32,
+         _return_size: i32|
+         -> i32 { STATUS_NOT_FOUND },
+    )?;
+
+    linker.func_wrap("env", "proxy_done", |mut caller: Caller<'_, Host>| -> i32 {
+        caller.data_mut().events.push(Event::Done);
+        STATUS_OK
+    })?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_define_metric",
+        |mut caller: Caller<'_, Host>,
+         metric_type: i32,
+         name_data: i32,
+         name_size: i32,
+         return_id: i32|
+         -> Result<i32> {
+            let name = read_string(&mut caller, name_data, name_size)?;
+            let metrics = &mut caller.data_mut().metrics;
+            let index = match metrics.iter().position(|metric| metric.name == name) {
+                Some(index) => index,
+                None => {
+                    metrics.push(Metric {
+                        name,
+                        metric_type: metric_type as u32,
+                        value: 0,
+                    });
+                    metrics.len() - 1
+                }
+            };
+            write_u32(&mut caller, return_id, index as u32 + 1)?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_get_metric",
+        |mut caller: Caller<'_, Host>, metric_id: i32, return_value: i32| -> Result<i32> {
+            let index = (metric_id as u32 as usize).wrapping_sub(1);
+            let Some(value) = caller.data().metrics.get(index).map(|metric| metric.value) else {
+                return Ok(STATUS_NOT_FOUND);
+            };
+            write(&mut caller, return_value, &value.to_le_bytes())?;
+            Ok(STATUS_OK)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_record_metric",
+        |mut caller: Caller<'_, Host>, metric_id: i32, value: i64| -> i32 {
+            let index = (metric_id as u32 as usize).wrapping_sub(1);
+            let Some(metric) = caller.data_mut().metrics.get_mut(index) else {
+                return STATUS_NOT_FOUND;
+            };
+            metric.value = value as u64;
+            STATUS_OK
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "proxy_increment_metric",
+        |mut caller: Caller<'_, Host>, metric_id: i32, offset: i64| -> i32 {
+            let index = (metric_id as u32 as usize).wrapping_sub(1);
+            let Some(metric) = caller.data_mut().metrics.get_mut(index) else {
+                return STATUS_NOT_FOUND;
+            };
+            if metric.metric_type == METRIC_COUNTER && offset < 0 {
+                return STATUS_BAD_ARGUMENT;
+            }
+            metric.value = metric.value.wrapping_add_signed(offset);
+            STATUS_OK
+        },
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod abi;
+mod host;
+mod plugin;
+mod replay;
+mod scenario;
+
+use anyhow::{Context, Result, bail};
+use plugin::Plugin;
+use scenario::Scenario;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+Usage: proxy-wasm-run [OPTIONS] <PLUGIN> [SCENARIO]
+
+Loads a Proxy-Wasm plugin and replays the HTTP exchanges described in
+SCENARIO (YAML, or JSON with a .json extension) against it.
+
+Options:
+  --vm-config <FILE>      VM configuration passed to on_vm_start
+  --plugin-config <FILE>  Plugin configuration passed to on_configure
+  --vm-id <ID>            VM ID (default: empty)
+  -h, --help              Print this help";
+
+#[derive(Default)]
+struct Args {
+    plugin: PathBuf,
+    scenario: Option<PathBuf>,
+    vm_configuration: Option<PathBuf>,
+    plugin_configuration: Option<PathBuf>,
+    vm_id: String,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = Args::default();
+    let mut positional = Vec::new();
+    let mut iter = std::env::args_os().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .with_context(|| format!("missing value for {name}"))
+        };
+        match arg.to_str() {
+            Some("-h" | "--help") => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            Some("--vm-config") => args.vm_configuration = Some(value("--vm-config")?.into()),
+            Some("--plugin-config") => {
+                args.plugin_configuration = Some(value("--plugin-config")?.into())
+            }
+            Some("--vm-id") => {
+                args.vm_id = value("--vm-id")?
+                    .into_string()
+                    .map_err(|_| anyhow::anyhow!("invalid --vm-id"))?
+            }
+            Some(option) if option.starts_with('-') => bail!("unknown option: {option}"),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    let mut positional = positional.into_iter();
+    args.plugin = positional.next().context("missing plugin")?;
+    args.scenario = positional.next();
+    if positional.next().is_some() {
+        bail!("too many arguments");
+    }
+    Ok(args)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn run(args: Args) -> Result<()> {
+    let scenario = match &args.scenario {
+        Some(path) => Scenario::load(path)?,
+        None => Scenario::default(),
+    };
+    let wasm = read(&args.plugin)?;
+    let mut plugin = Plugin::new(&wasm, &args.vm_id)
+        .with_context(|| format!("failed to load {}", args.plugin.display()))?;
+    if let Some(path) = &args.vm_configuration {
+        let configuration = read(path)?;
+        plugin
+            .host_mut()
+            .buffers
+            .insert(abi::BUFFER_VM_CONFIGURATION, configuration);
+    }
+    if let Some(path) = &args.plugin_configuration {
+        let configuration = read(path)?;
+        plugin
+            .host_mut()
+            .buffers
+            .insert(abi::BUFFER_PLUGIN_CONFIGURATION, configuration);
+    }
+    replay::run(&mut plugin, &scenario, &mut std::io::stdout().lock())
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+    if let Err(err) = result {
+        eprintln!("error: {err:#}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::host::{self, Host};
+use anyhow::Result;
+use wasmtime::{Engine, Instance, Linker, Module, Store, WasmParams, WasmResults};
+use wasmtime_wasi::WasiCtxBuilder;
+
+/// Instance of a compiled plugin, with a single root context.
+pub struct Plugin {
+    store: Store<Host>,
+    instance: Instance,
+    root_context_id: u32,
+    next_context_id: u32,
+}
+
+impl Plugin {
+    /// Instantiates the plugin from either a binary or text Wasm module.
+    pub fn new(wasm: &[u8], vm_id: &str) -> Result<Plugin> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm)?;
+        let mut linker = Linker::new(&engine);
+        host::add_to_linker(&mut linker)?;
+        linker.define_unknown_imports_as_traps(&module)?;
+
+        // Plugins get stdio, but no filesystem, environment or network access.
+        let wasi = WasiCtxBuilder::new()
+            .inherit_stdout()
+            .inherit_stderr()
+            .build_p1();
+        let mut store = Store::new(&engine, Host::new(wasi, vm_id));
+        let instance = linker.instantiate(&mut store, &module)?;
+
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            initialize.call(&mut store, ())?;
+        }
+        if let Ok(main) = instance.get_typed_func::<(i32, i32), i32>(&mut store, "main") {
+            main.call(&mut store, (0, 0))?;
+        }
+
+        Ok(Plugin {
+            store,
+            instance,
+            root_context_id: 1,
+            next_context_id: 2,
+        })
+    }
+
+    pub fn host(&self) -> &Host {
+        self.store.data()
+    }
+
+    pub fn host_mut(&mut self) -> &mut Host {
+        self.store.data_mut()
+    }
+
+    pub fn root_context_id(&self) -> u32 {
+        self.root_context_id
+    }
+
+    /// Calls a function exported by the plugin.
+    pub fn call<P: WasmParams, R: WasmResults>(&mut self, name: &str, params: P) -> Result<R> {
+        let func = self
+            .instance
+            .get_typed_func::<P, R>(&mut self.store, name)?;
+        func.call(&mut self.store, params)
+    }
+
+    pub fn create_root_context(&mut self) -> Result<()> {
+        let root_context_id = self.root_context_id as i32;
+        self.call::<(i32, i32), ()>("proxy_on_context_create", (root_context_id, 0))
+    }
+
+    pub fn create_context(&mut self) -> Result<u32> {
+        let context_id = self.next_context_id;
+        self.next_context_id += 1;
+        self.call::<(i32, i32), ()>(
+            "proxy_on_context_create",
+            (context_id as i32, self.root_context_id as i32),
+        )?;
+        Ok(context_id)
+    }
+
+    pub fn buffer_size(&self, buffer_type: u32) -> i32 {
+        self.host()
+            .buffers
+            .get(&buffer_type)
+            .map_or(0, |buffer| buffer.len() as i32)
+    }
+}
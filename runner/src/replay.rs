@@ -0,0 +1,554 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::abi::*;
+use crate::host::{Event, Map};
+use crate::plugin::Plugin;
+use crate::scenario::{Exchange, Message, Scenario};
+use anyhow::{Result, bail};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+
+const HTTP_MAPS: [(u32, &str); 4] = [
+    (MAP_HTTP_REQUEST_HEADERS, "request header"),
+    (MAP_HTTP_REQUEST_TRAILERS, "request trailer"),
+    (MAP_HTTP_RESPONSE_HEADERS, "response header"),
+    (MAP_HTTP_RESPONSE_TRAILERS, "response trailer"),
+];
+
+const HTTP_BODIES: [(u32, &str); 2] = [
+    (BUFFER_HTTP_REQUEST_BODY, "request body"),
+    (BUFFER_HTTP_RESPONSE_BODY, "response body"),
+];
+
+/// Host state before a callback, used to report what the plugin changed.
+struct Snapshot {
+    maps: HashMap<u32, Map>,
+    buffers: HashMap<u32, Vec<u8>>,
+    metrics: HashMap<String, u64>,
+}
+
+impl Snapshot {
+    fn take(plugin: &Plugin) -> Snapshot {
+        let host = plugin.host();
+        Snapshot {
+            maps: host.maps.clone(),
+            buffers: host.buffers.clone(),
+            metrics: host
+                .metrics
+                .iter()
+                .map(|metric| (metric.name.clone(), metric.value))
+                .collect(),
+        }
+    }
+}
+
+/// Replays `scenario` against `plugin`, writing a report of every callback
+/// and its side effects to `out`.
+pub fn run(plugin: &mut Plugin, scenario: &Scenario, out: &mut dyn Write) -> Result<()> {
+    for property in &scenario.properties {
+        let path = property.path.join("\0").into_bytes();
+        let value = property.value.clone().into_bytes();
+        plugin.host_mut().properties.insert(path, value);
+    }
+
+    let mut replay = Replay {
+        plugin,
+        out,
+        pending_http_calls: VecDeque::new(),
+        resumed: [false; 2],
+        ended: false,
+    };
+    replay.start(scenario.ticks)?;
+    for (index, exchange) in scenario.exchanges.iter().enumerate() {
+        replay.exchange(index + 1, exchange)?;
+    }
+    Ok(())
+}
+
+struct Replay<'a> {
+    plugin: &'a mut Plugin,
+    out: &'a mut dyn Write,
+    pending_http_calls: VecDeque<u32>,
+    resumed: [bool; 2],
+    ended: bool,
+}
+
+impl Replay<'_> {
+    fn start(&mut self, ticks: u32) -> Result<()> {
+        self.plugin.create_root_context()?;
+        let root_context_id = self.plugin.root_context_id() as i32;
+
+        let size = self.plugin.buffer_size(BUFFER_VM_CONFIGURATION);
+        let started = self.step("vm start", describe_bool, |plugin| {
+            plugin.call::<(i32, i32), i32>("proxy_on_vm_start", (root_context_id, size))
+        })?;
+        if started == 0 {
+            bail!("plugin failed to start");
+        }
+
+        let size = self.plugin.buffer_size(BUFFER_PLUGIN_CONFIGURATION);
+        let configured = self.step("configure", describe_bool, |plugin| {
+            plugin.call::<(i32, i32), i32>("proxy_on_configure", (root_context_id, size))
+        })?;
+        if configured == 0 {
+            bail!("plugin rejected its configuration");
+        }
+
+        for _ in 0..ticks {
+            self.step("tick", describe_none, |plugin| {
+                plugin.call::<i32, ()>("proxy_on_tick", root_context_id)
+            })?;
+        }
+        Ok(())
+    }
+
+    fn exchange(&mut self, index: usize, exchange: &Exchange) -> Result<()> {
+        match &exchange.name {
+            Some(name) => writeln!(self.out, "\n== exchange {index}: {name}")?,
+            None => writeln!(self.out, "\n== exchange {index}")?,
+        }
+
+        let context_id = self.plugin.create_context()? as i32;
+        let mut responses = exchange.http_call_responses.iter().collect();
+        self.ended = false;
+
+        let request = &exchange.request;
+        let flowing = self.message(context_id, STREAM_HTTP_REQUEST, request, &mut responses)?;
+        if let (true, Some(response)) = (flowing, &exchange.response) {
+            self.message(context_id, STREAM_HTTP_RESPONSE, response, &mut responses)?;
+        }
+
+        self.step("done", describe_bool, |plugin| {
+            plugin.call::<i32, i32>("proxy_on_done", context_id)
+        })?;
+        self.step("log", describe_none, |plugin| {
+            plugin.call::<i32, ()>("proxy_on_log", context_id)
+        })?;
+        self.step("delete", describe_none, |plugin| {
+            plugin.call::<i32, ()>("proxy_on_delete", context_id)
+        })?;
+        self.pending_http_calls.clear();
+        Ok(())
+    }
+
+    // Delivers the headers, body and trailers of a request or response,
+    // returning whether the stream is still flowing afterwards.
+    fn message(
+        &mut self,
+        context_id: i32,
+        stream_type: u32,
+        message: &Message,
+        responses: &mut VecDeque<&Message>,
+    ) -> Result<bool> {
+        let (headers_type, body_type, trailers_type, name) = match stream_type {
+            STREAM_HTTP_REQUEST => (
+                MAP_HTTP_REQUEST_HEADERS,
+                BUFFER_HTTP_REQUEST_BODY,
+                MAP_HTTP_REQUEST_TRAILERS,
+                "request",
+            ),
+            _ => (
+                MAP_HTTP_RESPONSE_HEADERS,
+                BUFFER_HTTP_RESPONSE_BODY,
+                MAP_HTTP_RESPONSE_TRAILERS,
+                "response",
+            ),
+        };
+        let has_trailers = !message.trailers.is_empty();
+
+        let end_of_stream = message.body.is_none() && !has_trailers;
+        let num_headers = message.headers.len() as i32;
+        self.plugin
+            .host_mut()
+            .set_map(headers_type, &message.headers);
+        self.resumed = [false; 2];
+        let action = self.step(&format!("{name} headers"), describe_action, |plugin| {
+            plugin.call::<(i32, i32, i32), i32>(
+                &format!("proxy_on_{name}_headers"),
+                (context_id, num_headers, end_of_stream as i32),
+            )
+        })?;
+        if !self.proceed(context_id, stream_type, action, end_of_stream, responses)? {
+            return Ok(false);
+        }
+
+        if let Some(body) = &message.body {
+            let body_size = body.len() as i32;
+            let host = self.plugin.host_mut();
+            host.buffers.insert(body_type, body.clone().into_bytes());
+            self.resumed = [false; 2];
+            let action = self.step(&format!("{name} body"), describe_action, |plugin| {
+                plugin.call::<(i32, i32, i32), i32>(
+                    &format!("proxy_on_{name}_body"),
+                    (context_id, body_size, !has_trailers as i32),
+                )
+            })?;
+            if !self.proceed(context_id, stream_type, action, !has_trailers, responses)? {
+                return Ok(false);
+            }
+        }
+
+        if has_trailers {
+            let num_trailers = message.trailers.len() as i32;
+            let host = self.plugin.host_mut();
+            host.set_map(trailers_type, &message.trailers);
+            self.resumed = [false; 2];
+            let action = self.step(&format!("{name} trailers"), describe_action, |plugin| {
+                plugin.call::<(i32, i32), i32>(
+                    &format!("proxy_on_{name}_trailers"),
+                    (context_id, num_trailers),
+                )
+            })?;
+            if !self.proceed(context_id, stream_type, action, true, responses)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Delivers responses to HTTP calls made by the plugin, then decides
+    // whether the stream can continue. As in Envoy, pausing a callback that
+    // isn't the last one for the message doesn't stop later callbacks, and
+    // the stream only stalls if the last one is paused and never resumed.
+    fn proceed(
+        &mut self,
+        context_id: i32,
+        stream_type: u32,
+        action: i32,
+        last: bool,
+        responses: &mut VecDeque<&Message>,
+    ) -> Result<bool> {
+        while let Some(token_id) = self.pending_http_calls.pop_front() {
+            let Some(response) = responses.pop_front() else {
+                writeln!(
+                    self.out,
+                    "   no response scripted for http call #{token_id}"
+                )?;
+                continue;
+            };
+            let num_headers = response.headers.len() as i32;
+            let body = response.body.clone().unwrap_or_default().into_bytes();
+            let body_size = body.len() as i32;
+            let num_trailers = response.trailers.len() as i32;
+            let host = self.plugin.host_mut();
+            host.set_map(MAP_HTTP_CALL_RESPONSE_HEADERS, &response.headers);
+            host.buffers.insert(BUFFER_HTTP_CALL_RESPONSE_BODY, body);
+            host.set_map(MAP_HTTP_CALL_RESPONSE_TRAILERS, &response.trailers);
+            self.step(
+                &format!("http call #{token_id} response"),
+                describe_none,
+                |plugin| {
+                    plugin.call::<(i32, i32, i32, i32, i32), ()>(
+                        "proxy_on_http_call_response",
+                        (
+                            context_id,
+                            token_id as i32,
+                            num_headers,
+                            body_size,
+                            num_trailers,
+                        ),
+                    )
+                },
+            )?;
+        }
+
+        if self.ended {
+            return Ok(false);
+        }
+        if action == ACTION_PAUSE && last && !self.resumed[stream_type as usize] {
+            writeln!(
+                self.out,
+                "   {} paused and never resumed, skipping the rest of the exchange",
+                stream_name(stream_type)
+            )?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn step<R>(
+        &mut self,
+        label: &str,
+        describe: fn(&R) -> Option<String>,
+        callback: impl FnOnce(&mut Plugin) -> Result<R>,
+    ) -> Result<R> {
+        let before = Snapshot::take(self.plugin);
+        let result = callback(self.plugin)?;
+        match describe(&result) {
+            Some(result) => writeln!(self.out, "{label}: {result}")?,
+            None => writeln!(self.out, "{label}")?,
+        }
+        self.report(&before)?;
+        Ok(result)
+    }
+
+    fn report(&mut self, before: &Snapshot) -> Result<()> {
+        let host = self.plugin.host();
+        for (map_type, name) in HTTP_MAPS {
+            let empty = Vec::new();
+            let old = before.maps.get(&map_type).unwrap_or(&empty);
+            let new = host.maps.get(&map_type).unwrap_or(&empty);
+            for (key, value) in difference(old, new) {
+                writeln!(self.out, "   - {name} {key}: {}", lossy(value))?;
+            }
+            for (key, value) in difference(new, old) {
+                writeln!(self.out, "   + {name} {key}: {}", lossy(value))?;
+            }
+        }
+        for (buffer_type, name) in HTTP_BODIES {
+            let new = host.buffers.get(&buffer_type);
+            if before.buffers.get(&buffer_type) != new {
+                let new = new.map(|body| lossy(body)).unwrap_or_default();
+                writeln!(self.out, "   ~ {name}: {new:?}")?;
+            }
+        }
+
+        let events = std::mem::take(&mut self.plugin.host_mut().events);
+        for event in events {
+            match event {
+                Event::Log { level, message } => {
+                    writeln!(self.out, "   log [{}] {message}", log_level_name(level))?;
+                }
+                Event::HttpCall {
+                    token_id,
+                    upstream,
+                    headers,
+                    body,
+                    timeout,
+                } => {
+                    writeln!(
+                        self.out,
+                        "   http call #{token_id} to {upstream:?} (timeout {timeout}ms)"
+                    )?;
+                    for (key, value) in &headers {
+                        writeln!(self.out, "     {key}: {}", lossy(value))?;
+                    }
+                    if !body.is_empty() {
+                        writeln!(self.out, "     body: {:?}", lossy(&body))?;
+                    }
+                    self.pending_http_calls.push_back(token_id);
+                }
+                Event::GrpcCall {
+                    token_id,
+                    upstream,
+                    service,
+                    method,
+                } => {
+                    writeln!(
+                        self.out,
+                        "   grpc call #{token_id} to {upstream:?}: {service}/{method} (not answered)"
+                    )?;
+                }
+                Event::LocalResponse {
+                    status_code,
+                    details,
+                    headers,
+                    body,
+                } => {
+                    match details.is_empty() {
+                        true => writeln!(self.out, "   local response {status_code}")?,
+                        false => writeln!(self.out, "   local response {status_code} ({details})")?,
+                    }
+                    for (key, value) in &headers {
+                        writeln!(self.out, "     {key}: {}", lossy(value))?;
+                    }
+                    if !body.is_empty() {
+                        writeln!(self.out, "     body: {:?}", lossy(&body))?;
+                    }
+                    self.ended = true;
+                }
+                Event::SetTickPeriod(period) => {
+                    writeln!(self.out, "   tick period = {period}ms")?;
+                }
+                Event::ContinueStream(stream_type) => {
+                    writeln!(self.out, "   resume {}", stream_name(stream_type))?;
+                    if let Some(resumed) = self.resumed.get_mut(stream_type as usize) {
+                        *resumed = true;
+                    }
+                }
+                Event::CloseStream(stream_type) => {
+                    writeln!(self.out, "   close {}", stream_name(stream_type))?;
+                    self.ended = true;
+                }
+                Event::SetProperty { path, value } => {
+                    writeln!(
+                        self.out,
+                        "   property {} = {:?}",
+                        path.join("."),
+                        lossy(&value)
+                    )?;
+                }
+                Event::Done => writeln!(self.out, "   done")?,
+            }
+        }
+
+        for metric in &self.plugin.host().metrics {
+            match before.metrics.get(&metric.name) {
+                None => writeln!(self.out, "   metric {} = {}", metric.name, metric.value)?,
+                Some(value) if *value != metric.value => writeln!(
+                    self.out,
+                    "   metric {}: {value} -> {}",
+                    metric.name, metric.value
+                )?,
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe_action(action: &i32) -> Option<String> {
+    Some(action_name(*action))
+}
+
+fn describe_bool(result: &i32) -> Option<String> {
+    Some((*result != 0).to_string())
+}
+
+fn describe_none<R>(_: &R) -> Option<String> {
+    None
+}
+
+// Entries of `a` that aren't in `b`, counting duplicates.
+fn difference<'a>(a: &'a Map, b: &Map) -> Vec<&'a (String, Vec<u8>)> {
+    let mut remaining: Vec<_> = b.iter().collect();
+    a.iter()
+        .filter(
+            |entry| match remaining.iter().position(|other| other == entry) {
+                Some(index) => {
+                    remaining.swap_remove(index);
+                    false
+                }
+                None => true,
+            },
+        )
+        .collect()
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PLUGIN: &str = r#"
+    (module
+      (import "env" "proxy_log" (func $log (param i32 i32 i32) (result i32)))
+      (import "env" "proxy_add_header_map_value"
+        (func $add_header (param i32 i32 i32 i32 i32) (result i32)))
+      (import "env" "proxy_get_header_map_value"
+        (func $get_header (param i32 i32 i32 i32 i32) (result i32)))
+      (import "env" "proxy_http_call"
+        (func $http_call (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+      (import "env" "proxy_continue_stream" (func $continue (param i32) (result i32)))
+      (memory (export "memory") 1)
+      (global $heap (mut i32) (i32.const 1024))
+      (data (i32.const 0) "x-wat")
+      (data (i32.const 8) "hello")
+      (data (i32.const 16) "auth")
+      (data (i32.const 24) "\00\00\00\00")
+      (data (i32.const 32) ":status")
+      (data (i32.const 40) "x-status")
+      (func (export "proxy_on_memory_allocate") (param $size i32) (result i32)
+        (global.get $heap)
+        (global.set $heap (i32.add (global.get $heap) (local.get $size))))
+      (func (export "proxy_on_context_create") (param i32 i32))
+      (func (export "proxy_on_vm_start") (param i32 i32) (result i32) (i32.const 1))
+      (func (export "proxy_on_configure") (param i32 i32) (result i32) (i32.const 1))
+      (func (export "proxy_on_request_headers") (param i32 i32 i32) (result i32)
+        (drop (call $log (i32.const 2) (i32.const 8) (i32.const 5)))
+        (drop (call $add_header
+          (i32.const 0) (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 4)))
+        (drop (call $http_call
+          (i32.const 16) (i32.const 4) (i32.const 24) (i32.const 4) (i32.const 0)
+          (i32.const 0) (i32.const 24) (i32.const 4) (i32.const 1000) (i32.const 72)))
+        (i32.const 1))
+      (func (export "proxy_on_http_call_response") (param i32 i32 i32 i32 i32)
+        (drop (call $get_header
+          (i32.const 6) (i32.const 32) (i32.const 7) (i32.const 64) (i32.const 68)))
+        (drop (call $add_header
+          (i32.const 0) (i32.const 40) (i32.const 8)
+          (i32.load (i32.const 64)) (i32.load (i32.const 68))))
+        (drop (call $continue (i32.const 0))))
+      (func (export "proxy_on_response_headers") (param i32 i32 i32) (result i32)
+        (i32.const 0))
+      (func (export "proxy_on_done") (param i32) (result i32) (i32.const 1))
+      (func (export "proxy_on_log") (param i32))
+      (func (export "proxy_on_delete") (param i32)))
+    "#;
+
+    fn replay(scenario: &str) -> String {
+        let scenario: Scenario = serde_yaml::from_str(scenario).unwrap();
+        let mut plugin = Plugin::new(PLUGIN.as_bytes(), "").unwrap();
+        let mut out = Vec::new();
+        run(&mut plugin, &scenario, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_http_call() {
+        let output = replay(
+            r#"
+            exchanges:
+              - name: auth
+                request:
+                  headers: [[":path", "/"]]
+                http_call_responses:
+                  - headers: [[":status", "200"]]
+                response:
+                  headers: [[":status", "200"]]
+            "#,
+        );
+        assert_eq!(
+            output,
+            r#"vm start: true
+configure: true
+
+== exchange 1: auth
+request headers: Pause
+   + request header x-wat: auth
+   log [info] hello
+   http call #1 to "auth" (timeout 1000ms)
+http call #1 response
+   + request header x-status: 200
+   resume request
+response headers: Continue
+done: true
+log
+delete
+"#
+        );
+    }
+
+    #[test]
+    fn test_unanswered_http_call() {
+        let output = replay(
+            r#"
+            exchanges:
+              - request:
+                  headers: [[":path", "/"]]
+                response:
+                  headers: [[":status", "200"]]
+            "#,
+        );
+        assert!(output.contains(
+            "   no response scripted for http call #1\n   \
+             request paused and never resumed, skipping the rest of the exchange\n"
+        ));
+        assert!(!output.contains("response headers"));
+    }
+}
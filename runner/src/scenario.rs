@@ -0,0 +1,89 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// HTTP exchanges to replay against the plugin, loaded from YAML or JSON.
+///
+/// ```yaml
+/// properties:
+///   - path: [plugin_name]
+///     value: my_plugin
+/// exchanges:
+///   - name: authorized request
+///     request:
+///       headers: [[":method", "GET"], [":path", "/"]]
+///     http_call_responses:
+///       - headers: [[":status", "200"]]
+///     response:
+///       headers: [[":status", "200"]]
+///       body: Hello, World!
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub properties: Vec<Property>,
+    /// Number of `on_tick` callbacks to deliver after configuring the plugin.
+    #[serde(default)]
+    pub ticks: u32,
+    #[serde(default)]
+    pub exchanges: Vec<Exchange>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Property {
+    pub path: Vec<String>,
+    pub value: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exchange {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub request: Message,
+    #[serde(default)]
+    pub response: Option<Message>,
+    /// Responses delivered, in order, to HTTP calls dispatched by the plugin
+    /// while processing this exchange.
+    #[serde(default)]
+    pub http_call_responses: Vec<Message>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Message {
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub trailers: Vec<(String, String)>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Scenario> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let scenario = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+            _ => serde_yaml::from_str(&contents).map_err(anyhow::Error::from),
+        };
+        scenario.with_context(|| format!("failed to parse {}", path.display()))
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming access to HTTP bodies and other host buffers.
+//!
+//! A [`BodyReader`] remembers how much of the buffer was consumed, so that it
+//! can be kept in the stream context and only fetches the new bytes of each
+//! body chunk. A [`BodyWriter`] batches modifications into at most two
+//! `set_buffer` calls.
+
+use crate::hostcalls;
+use crate::types::{BufferType, Bytes, HostcallError, Status};
+use std::io;
+
+const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Reads a buffer incrementally, across callbacks.
+///
+/// Reads return EOF once all the bytes buffered by the host were consumed,
+/// and continue from there once the host buffers more of them. Since the host
+/// drops the bytes that it already forwarded, call [`reset`](Self::reset)
+/// after returning [`Action::Continue`] from a body callback.
+///
+/// Each chunk is copied into a single buffer that's reused across reads, and
+/// the memory allocated by the host for it is freed right away.
+///
+/// [`Action::Continue`]: crate::types::Action::Continue
+pub struct BodyReader {
+    buffer_type: BufferType,
+    chunk_size: usize,
+    // Offset in the host buffer of `chunk[0]`.
+    offset: usize,
+    chunk: Bytes,
+    position: usize,
+}
+
+impl BodyReader {
+    pub fn new(buffer_type: BufferType) -> BodyReader {
+        BodyReader {
+            buffer_type,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            offset: 0,
+            chunk: Bytes::new(),
+            position: 0,
+        }
+    }
+
+    pub fn http_request() -> BodyReader {
+        BodyReader::new(BufferType::HttpRequestBody)
+    }
+
+    pub fn http_response() -> BodyReader {
+        BodyReader::new(BufferType::HttpResponseBody)
+    }
+
+    /// Sets the maximum number of bytes fetched from the host at once.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> BodyReader {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    pub fn buffer_type(&self) -> BufferType {
+        self.buffer_type
+    }
+
+    /// Returns the offset in the host buffer of the next byte to read.
+    pub fn consumed(&self) -> usize {
+        self.offset + self.position
+    }
+
+    /// Starts reading again from the beginning of the host buffer.
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.chunk.clear();
+        self.position = 0;
+    }
+
+    fn fetch(&mut self) -> Result<(), Status> {
+        let start = self.consumed();
+        hostcalls::get_buffer_into(self.buffer_type, start, self.chunk_size, &mut self.chunk)?;
+        self.offset = start;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl io::BufRead for BodyReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.chunk.len() {
+            self.fetch().map_err(|status| {
+                io::Error::other(HostcallError::new(
+                    status,
+                    "get_buffer",
+                    &[&self.buffer_type, &self.consumed(), &self.chunk_size],
+                ))
+            })?;
+        }
+        Ok(&self.chunk[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.chunk.len());
+    }
+}
+
+impl io::Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = io::BufRead::fill_buf(self)?;
+        let size = available.len().min(buf.len());
+        buf[..size].copy_from_slice(&available[..size]);
+        io::BufRead::consume(self, size);
+        Ok(size)
+    }
+}
+
+/// Modifies a buffer with at most two `set_buffer` calls, one to prepend and
+/// one to append bytes, or a single one to replace it.
+///
+/// Writes through [`io::Write`] are appended. Nothing is sent to the host
+/// until [`commit`](Self::commit) is called.
+pub struct BodyWriter {
+    buffer_type: BufferType,
+    size: usize,
+    // In reverse order, since each one is prepended to the previous ones.
+    prepended: Vec<Bytes>,
+    replacement: Option<Bytes>,
+    appended: Bytes,
+}
+
+impl BodyWriter {
+    /// Creates a writer for a buffer of `size` bytes, e.g. the `body_size`
+    /// passed to the body callback.
+    pub fn new(buffer_type: BufferType, size: usize) -> BodyWriter {
+        BodyWriter {
+            buffer_type,
+            size,
+            prepended: Vec::new(),
+            replacement: None,
+            appended: Bytes::new(),
+        }
+    }
+
+    pub fn http_request(body_size: usize) -> BodyWriter {
+        BodyWriter::new(BufferType::HttpRequestBody, body_size)
+    }
+
+    pub fn http_response(body_size: usize) -> BodyWriter {
+        BodyWriter::new(BufferType::HttpResponseBody, body_size)
+    }
+
+    pub fn buffer_type(&self) -> BufferType {
+        self.buffer_type
+    }
+
+    /// Returns the size of the buffer once the pending changes are committed.
+    pub fn size(&self) -> usize {
+        let prepended = self.prepended.iter().map(Vec::len).sum::<usize>();
+        let body = match &self.replacement {
+            Some(replacement) => replacement.len(),
+            None => self.size,
+        };
+        prepended + body + self.appended.len()
+    }
+
+    pub fn prepend(&mut self, bytes: &[u8]) {
+        self.prepended.push(bytes.to_vec());
+    }
+
+    pub fn append(&mut self, bytes: &[u8]) {
+        self.appended.extend_from_slice(bytes);
+    }
+
+    /// Replaces the whole buffer, including the bytes prepended and appended so far.
+    pub fn replace(&mut self, bytes: &[u8]) {
+        self.prepended.clear();
+        self.replacement = Some(bytes.to_vec());
+        self.appended.clear();
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !self.prepended.is_empty() || self.replacement.is_some() || !self.appended.is_empty()
+    }
+
+    /// Sends the pending changes to the host.
+    pub fn commit(&mut self) -> Result<(), Status> {
+        let size = self.size();
+        let mut prepended = Bytes::new();
+        for bytes in self.prepended.drain(..).rev() {
+            prepended.extend(bytes);
+        }
+        match self.replacement.take() {
+            Some(replacement) => {
+                let mut body = prepended;
+                body.extend(replacement);
+                body.append(&mut self.appended);
+                hostcalls::set_buffer(self.buffer_type, 0, self.size, &body)?;
+            }
+            None => {
+                if !prepended.is_empty() {
+                    hostcalls::set_buffer(self.buffer_type, 0, 0, &prepended)?;
+                }
+                if !self.appended.is_empty() {
+                    let end = prepended.len() + self.size;
+                    hostcalls::set_buffer(self.buffer_type, end, 0, &self.appended)?;
+                    self.appended.clear();
+                }
+            }
+        }
+        self.size = size;
+        Ok(())
+    }
+}
+
+impl io::Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.append(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::testing;
+    use crate::traits::*;
+    use crate::types::*;
+    use std::io::{BufRead, Read, Write};
+
+    #[test]
+    fn test_reader() {
+        testing::reset();
+        testing::set_buffer(BufferType::HttpRequestBody, Some(b"hello"));
+        let mut reader = BodyReader::http_request().with_chunk_size(2);
+        assert_eq!(reader.fill_buf().unwrap(), b"he");
+        let chunk = reader.chunk.as_ptr();
+        reader.consume(2);
+        assert_eq!(reader.fill_buf().unwrap(), b"ll");
+        // The buffer is reused for each chunk.
+        assert_eq!(reader.chunk.as_ptr(), chunk);
+        reader.reset();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "hello");
+        assert_eq!(reader.consumed(), 5);
+
+        testing::set_buffer(BufferType::HttpRequestBody, Some(b"hello\nworld\n"));
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(lines, vec!["", "world"]);
+    }
+
+    #[test]
+    fn test_writer() {
+        testing::reset();
+        testing::set_buffer(BufferType::HttpRequestBody, Some(b"body"));
+        let mut writer = BodyWriter::http_request(4);
+        writer.prepend(b"<");
+        writer.prepend(b"<<");
+        writer.append(b">");
+        write!(writer, ">>").unwrap();
+        assert_eq!(writer.size(), 10);
+        writer.commit().unwrap();
+        assert_eq!(
+            testing::get_buffer(BufferType::HttpRequestBody),
+            Some(b"<<<body>>>".to_vec())
+        );
+
+        writer.append(b"ignored");
+        writer.replace(b"new");
+        writer.append(b" body");
+        assert!(writer.is_modified());
+        writer.commit().unwrap();
+        assert!(!writer.is_modified());
+        assert_eq!(
+            testing::get_buffer(BufferType::HttpRequestBody),
+            Some(b"new body".to_vec())
+        );
+    }
+
+    struct Root;
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(Redactor {
+                reader: BodyReader::http_request().with_chunk_size(4),
+                line: String::new(),
+                lines: Vec::new(),
+            }))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    // Buffers the body, and replaces the lines that contain a secret.
+    struct Redactor {
+        reader: BodyReader,
+        line: String,
+        lines: Vec<String>,
+    }
+
+    impl Context for Redactor {}
+
+    impl HttpContext for Redactor {
+        fn on_http_request_body(&mut self, body_size: usize, end_of_stream: bool) -> Action {
+            // Incomplete lines are kept, and completed with the next chunk.
+            while self.reader.read_line(&mut self.line).unwrap() > 0 {
+                if self.line.ends_with('\n') {
+                    self.lines.push(std::mem::take(&mut self.line));
+                }
+            }
+            if !end_of_stream {
+                return Action::Pause;
+            }
+            let mut writer = BodyWriter::http_request(body_size);
+            let lines = self.lines.iter().map(|line| {
+                if line.contains("secret") {
+                    "[redacted]\n"
+                } else {
+                    line
+                }
+            });
+            writer.replace(lines.collect::<String>().as_bytes());
+            writer.commit().unwrap();
+            Action::Continue
+        }
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Root));
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], false);
+        stream.request_body(b"first\nthe sec", false);
+        stream.request_body(b"ret is\nlast\n", true);
+        assert_eq!(
+            testing::get_buffer(BufferType::HttpRequestBody),
+            Some(b"first\n[redacted]\nlast\n".to_vec())
+        );
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Buffering of complete HTTP bodies, up to a maximum size.
+
+#[cfg(feature = "compression")]
+use crate::compression::Encoding;
+use crate::hostcalls;
+use crate::traits::*;
+use crate::types::*;
+
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// HTTP context that receives complete bodies, see [`BufferedHttpContext`].
+pub trait FullBodyHttpContext: HttpContext {
+    /// Called with the whole request body, which can be replaced with
+    /// `set_http_request_body(0, body.len(), ...)`.
+    ///
+    /// Not called for requests without a body.
+    fn on_full_request_body(&mut self, _body: &[u8]) -> Action {
+        Action::Continue
+    }
+
+    /// Called with the whole response body, which can be replaced with
+    /// `set_http_response_body(0, body.len(), ...)`.
+    ///
+    /// Not called for responses without a body.
+    fn on_full_response_body(&mut self, _body: &[u8]) -> Action {
+        Action::Continue
+    }
+}
+
+/// Wraps an HTTP context, and pauses each body until it's complete to pass it
+/// to [`FullBodyHttpContext`].
+///
+/// Requests with a larger body than the maximum are answered with
+/// `413 Payload Too Large`, and responses with `500 Internal Server Error`, as
+/// soon as their `content-length` header or their buffered body exceeds it.
+/// All other callbacks are forwarded to the wrapped context.
+pub struct BufferedHttpContext<C> {
+    inner: C,
+    request: Body,
+    response: Body,
+}
+
+impl<C: FullBodyHttpContext> BufferedHttpContext<C> {
+    pub fn new(inner: C) -> BufferedHttpContext<C> {
+        BufferedHttpContext {
+            inner,
+            request: Body::new(
+                BufferType::HttpRequestBody,
+                MapType::HttpRequestHeaders,
+                (413, "Payload Too Large"),
+                (400, "Bad Request"),
+            ),
+            response: Body::new(
+                BufferType::HttpResponseBody,
+                MapType::HttpResponseHeaders,
+                (500, "Internal Server Error"),
+                (502, "Bad Gateway"),
+            ),
+        }
+    }
+
+    /// Sets the maximum size of both request and response bodies.
+    pub fn with_max_body_size(self, size: usize) -> BufferedHttpContext<C> {
+        self.with_max_request_body_size(size)
+            .with_max_response_body_size(size)
+    }
+
+    pub fn with_max_request_body_size(mut self, size: usize) -> BufferedHttpContext<C> {
+        self.request.max_size = size;
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, size: usize) -> BufferedHttpContext<C> {
+        self.response.max_size = size;
+        self
+    }
+
+    /// Decodes bodies with a supported `content-encoding` before passing them to
+    /// the wrapped context, and encodes them again if they were modified.
+    ///
+    /// The maximum size applies to both the encoded and the decoded body, and
+    /// bodies that can't be decoded are answered with `400 Bad Request` for
+    /// requests and `502 Bad Gateway` for responses. The `content-length` header
+    /// of encoded bodies is removed, since their size can change.
+    #[cfg(feature = "compression")]
+    pub fn with_decompression(mut self) -> BufferedHttpContext<C> {
+        self.request.decompress = true;
+        self.response.decompress = true;
+        self
+    }
+
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+// Buffering state of the request or the response body.
+struct Body {
+    buffer_type: BufferType,
+    headers: MapType,
+    too_large: (u32, &'static str),
+    #[cfg_attr(not(feature = "compression"), allow(dead_code))]
+    invalid: (u32, &'static str),
+    max_size: usize,
+    // Size of the body paused so far, which is complete once the trailers arrive.
+    paused_size: Option<usize>,
+    #[cfg(feature = "compression")]
+    decompress: bool,
+    #[cfg(feature = "compression")]
+    encoding: Option<Encoding>,
+}
+
+impl Body {
+    fn new(
+        buffer_type: BufferType,
+        headers: MapType,
+        too_large: (u32, &'static str),
+        invalid: (u32, &'static str),
+    ) -> Body {
+        Body {
+            buffer_type,
+            headers,
+            too_large,
+            invalid,
+            max_size: DEFAULT_MAX_BODY_SIZE,
+            paused_size: None,
+            #[cfg(feature = "compression")]
+            decompress: false,
+            #[cfg(feature = "compression")]
+            encoding: None,
+        }
+    }
+
+    fn on_headers(&mut self, f: impl FnOnce() -> Action) -> Action {
+        let content_length = hostcalls::get_map_value(self.headers, "content-length")
+            .unwrap()
+            .and_then(|length| length.parse::<usize>().ok());
+        if content_length.is_some_and(|length| length > self.max_size) {
+            return reject(self.too_large);
+        }
+        #[cfg(feature = "compression")]
+        if self.decompress {
+            self.encoding = hostcalls::get_map_value(self.headers, "content-encoding")
+                .unwrap()
+                .and_then(|value| Encoding::from_header(&value));
+            if self.encoding.is_some() && content_length.is_some() {
+                hostcalls::set_map_value(self.headers, "content-length", None).unwrap();
+            }
+        }
+        f()
+    }
+
+    fn on_body(
+        &mut self,
+        body_size: usize,
+        end_of_stream: bool,
+        f: impl FnOnce(&[u8]) -> Action,
+    ) -> Action {
+        if body_size > self.max_size {
+            return reject(self.too_large);
+        }
+        if !end_of_stream {
+            self.paused_size = Some(body_size);
+            return Action::Pause;
+        }
+        self.paused_size = None;
+        self.complete(body_size, f)
+    }
+
+    fn on_trailers(&mut self, f: impl FnOnce(&[u8]) -> Action) -> Action {
+        match self.paused_size.take() {
+            Some(body_size) => self.complete(body_size, f),
+            None => Action::Continue,
+        }
+    }
+
+    fn complete(&self, body_size: usize, f: impl FnOnce(&[u8]) -> Action) -> Action {
+        let body = get_body(self.buffer_type, body_size);
+        #[cfg(feature = "compression")]
+        if let Some(encoding) = self.encoding {
+            return self.complete_encoded(encoding, &body, f);
+        }
+        f(&body)
+    }
+
+    #[cfg(feature = "compression")]
+    fn complete_encoded(
+        &self,
+        encoding: Encoding,
+        body: &[u8],
+        f: impl FnOnce(&[u8]) -> Action,
+    ) -> Action {
+        let decoded = match encoding.decode(body, self.max_size) {
+            Ok(Some(decoded)) => decoded,
+            Ok(None) => return reject(self.too_large),
+            Err(_) => return reject(self.invalid),
+        };
+        // The decoded body is also put in the buffer, so that the wrapped context
+        // can modify it with the usual setters.
+        hostcalls::set_buffer(self.buffer_type, 0, body.len(), &decoded).unwrap();
+        let action = f(&decoded);
+        let modified = get_body(self.buffer_type, usize::MAX);
+        let encoded = if modified == decoded {
+            body.to_vec()
+        } else {
+            encoding.encode(&modified)
+        };
+        hostcalls::set_buffer(self.buffer_type, 0, modified.len(), &encoded).unwrap();
+        action
+    }
+}
+
+fn get_body(buffer_type: BufferType, body_size: usize) -> Bytes {
+    hostcalls::get_buffer(buffer_type, 0, body_size)
+        .unwrap()
+        .unwrap_or_default()
+}
+
+fn reject((status_code, body): (u32, &str)) -> Action {
+    hostcalls::send_http_response(status_code, vec![], Some(body.as_bytes())).unwrap();
+    Action::Pause
+}
+
+// Pauses if either the full body or the trailers callback did.
+fn combine(body_action: Action, trailers_action: Action) -> Action {
+    if trailers_action == Action::Continue {
+        body_action
+    } else {
+        trailers_action
+    }
+}
+
+impl<C: FullBodyHttpContext> Context for BufferedHttpContext<C> {
+    fn on_http_call_response(
+        &mut self,
+        token_id: u32,
+        num_headers: usize,
+        body_size: usize,
+        num_trailers: usize,
+    ) {
+        self.inner
+            .on_http_call_response(token_id, num_headers, body_size, num_trailers)
+    }
+
+    fn on_grpc_call_response(&mut self, token_id: u32, status_code: u32, response_size: usize) {
+        self.inner
+            .on_grpc_call_response(token_id, status_code, response_size)
+    }
+
+    fn on_grpc_stream_initial_metadata(&mut self, token_id: u32, num_elements: u32) {
+        self.inner
+            .on_grpc_stream_initial_metadata(token_id, num_elements)
+    }
+
+    fn on_grpc_stream_message(&mut self, token_id: u32, message_size: usize) {
+        self.inner.on_grpc_stream_message(token_id, message_size)
+    }
+
+    fn on_grpc_stream_trailing_metadata(&mut self, token_id: u32, num_elements: u32) {
+        self.inner
+            .on_grpc_stream_trailing_metadata(token_id, num_elements)
+    }
+
+    fn on_grpc_stream_close(&mut self, token_id: u32, status_code: u32) {
+        self.inner.on_grpc_stream_close(token_id, status_code)
+    }
+
+    fn on_foreign_function(&mut self, function_id: u32, arguments_size: usize) {
+        self.inner.on_foreign_function(function_id, arguments_size)
+    }
+
+    fn on_done(&mut self) -> bool {
+        self.inner.on_done()
+    }
+}
+
+impl<C: FullBodyHttpContext> HttpContext for BufferedHttpContext<C> {
+    fn on_http_request_headers(&mut self, num_headers: usize, end_of_stream: bool) -> Action {
+        let inner = &mut self.inner;
+        self.request
+            .on_headers(|| inner.on_http_request_headers(num_headers, end_of_stream))
+    }
+
+    fn on_http_request_body(&mut self, body_size: usize, end_of_stream: bool) -> Action {
+        let inner = &mut self.inner;
+        self.request.on_body(body_size, end_of_stream, |body| {
+            inner.on_full_request_body(body)
+        })
+    }
+
+    fn on_http_request_trailers(&mut self, num_trailers: usize) -> Action {
+        let inner = &mut self.inner;
+        let action = self
+            .request
+            .on_trailers(|body| inner.on_full_request_body(body));
+        combine(action, inner.on_http_request_trailers(num_trailers))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn on_http_request_metadata(&mut self, num_elements: usize) -> Action {
+        self.inner.on_http_request_metadata(num_elements)
+    }
+
+    fn on_http_response_headers(&mut self, num_headers: usize, end_of_stream: bool) -> Action {
+        let inner = &mut self.inner;
+        self.response
+            .on_headers(|| inner.on_http_response_headers(num_headers, end_of_stream))
+    }
+
+    fn on_http_response_body(&mut self, body_size: usize, end_of_stream: bool) -> Action {
+        let inner = &mut self.inner;
+        self.response.on_body(body_size, end_of_stream, |body| {
+            inner.on_full_response_body(body)
+        })
+    }
+
+    fn on_http_response_trailers(&mut self, num_trailers: usize) -> Action {
+        let inner = &mut self.inner;
+        let action = self
+            .response
+            .on_trailers(|body| inner.on_full_response_body(body));
+        combine(action, inner.on_http_response_trailers(num_trailers))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn on_http_response_metadata(&mut self, num_elements: usize) -> Action {
+        self.inner.on_http_response_metadata(num_elements)
+    }
+
+    fn on_log(&mut self) {
+        self.inner.on_log()
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::testing;
+
+    // Configures the context of each stream.
+    struct Root(fn(BufferedHttpContext<Uppercase>) -> BufferedHttpContext<Uppercase>);
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            let context = BufferedHttpContext::new(Uppercase).with_max_body_size(8);
+            Some(Box::new(self.0(context)))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct Uppercase;
+
+    impl Context for Uppercase {}
+
+    impl HttpContext for Uppercase {}
+
+    impl FullBodyHttpContext for Uppercase {
+        fn on_full_request_body(&mut self, body: &[u8]) -> Action {
+            self.set_http_request_body(0, body.len(), &body.to_ascii_uppercase());
+            Action::Continue
+        }
+    }
+
+    #[test]
+    fn test_buffered_body() {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Root(|context| context)));
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], false);
+        stream.request_body(b"abc", false);
+        assert_eq!(stream.last_action(), Some(Action::Pause));
+        stream.request_body(b"def", true);
+        assert_eq!(stream.last_action(), Some(Action::Continue));
+        assert_eq!(
+            testing::get_buffer(BufferType::HttpRequestBody),
+            Some(b"ABCDEF".to_vec())
+        );
+
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], false);
+        stream.request_body(b"abc", false);
+        stream.request_trailers(vec![("grpc-status", "0")]);
+        assert_eq!(
+            testing::get_buffer(BufferType::HttpRequestBody),
+            Some(b"ABC".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_too_large() {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Root(|context| context)));
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], false);
+        stream.request_body(b"abcde", false);
+        stream.request_body(b"fghij", false);
+        assert_eq!(testing::take_local_response().unwrap().status_code, 413);
+
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![("content-length", "9")], false);
+        assert_eq!(stream.last_action(), Some(Action::Pause));
+        assert_eq!(testing::take_local_response().unwrap().status_code, 413);
+
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], true);
+        stream.response_headers(vec![], false);
+        stream.response_body(b"abcdefghij", true);
+        assert_eq!(testing::take_local_response().unwrap().status_code, 500);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_decompression() {
+        let mut harness = Harness::new().with_root_context(|_| {
+            // Encoded bodies are larger than 8 bytes.
+            Box::new(Root(|context| {
+                context.with_max_request_body_size(64).with_decompression()
+            }))
+        });
+        harness.start();
+        let gzip = |body: &[u8]| Encoding::Gzip.encode(body);
+        let headers = vec![("content-encoding", "gzip"), ("content-length", "23")];
+
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(headers.clone(), false);
+        assert_eq!(
+            testing::get_map_value(MapType::HttpRequestHeaders, "content-length"),
+            None
+        );
+        let body = gzip(b"abc");
+        stream.request_body(&body[..10], false);
+        stream.request_body(&body[10..], true);
+        let body = testing::get_buffer(BufferType::HttpRequestBody).unwrap();
+        assert_eq!(Encoding::Gzip.decode(&body, 8), Ok(Some(b"ABC".to_vec())));
+
+        // Bodies that aren't modified are passed through as is.
+        let body = gzip(b"ABC");
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(headers.clone(), false);
+        stream.request_body(&body, true);
+        assert_eq!(testing::get_buffer(BufferType::HttpRequestBody), Some(body));
+
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(headers.clone(), false);
+        stream.request_body(&gzip(&[b'a'; 100]), true);
+        assert_eq!(testing::take_local_response().unwrap().status_code, 413);
+
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(headers, false);
+        stream.request_body(b"abc", true);
+        assert_eq!(testing::take_local_response().unwrap().status_code, 400);
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content codings of HTTP bodies.
+//!
+//! Enabled by the `compression` feature. The codecs are implemented in Rust,
+//! so they don't need a C toolchain for `wasm32` targets.
+
+use crate::types::{Bytes, Status};
+use std::io::Read;
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW_SIZE: u32 = 22;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+impl Encoding {
+    /// Parses a `content-encoding` header.
+    ///
+    /// Returns `None` for `identity`, and for unsupported or multiple codings.
+    pub fn from_header(value: &str) -> Option<Encoding> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("gzip") || value.eq_ignore_ascii_case("x-gzip") {
+            Some(Encoding::Gzip)
+        } else if value.eq_ignore_ascii_case("deflate") {
+            Some(Encoding::Deflate)
+        } else if value.eq_ignore_ascii_case("br") {
+            Some(Encoding::Brotli)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    pub fn encode(self, data: &[u8]) -> Bytes {
+        let compression = flate2::Compression::default();
+        let mut encoded = Vec::new();
+        let result = match self {
+            Encoding::Gzip => {
+                flate2::read::GzEncoder::new(data, compression).read_to_end(&mut encoded)
+            }
+            Encoding::Deflate => {
+                flate2::read::ZlibEncoder::new(data, compression).read_to_end(&mut encoded)
+            }
+            Encoding::Brotli => brotli::CompressorReader::new(
+                data,
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW_SIZE,
+            )
+            .read_to_end(&mut encoded),
+        };
+        // Reading from a slice can't fail.
+        result.unwrap();
+        encoded
+    }
+
+    /// Decodes `data`, or returns `Ok(None)` if it's larger than `max_size` once
+    /// decoded, which stops decompression bombs early.
+    pub fn decode(self, data: &[u8], max_size: usize) -> Result<Option<Bytes>, Status> {
+        let decode = |decoder: &mut dyn Read| {
+            let mut decoded = Vec::new();
+            decoder
+                .take(max_size as u64 + 1)
+                .read_to_end(&mut decoded)
+                .map_err(|_| Status::ParseFailure)?;
+            Ok(Some(decoded).filter(|decoded| decoded.len() <= max_size))
+        };
+        match self {
+            Encoding::Gzip => decode(&mut flate2::read::MultiGzDecoder::new(data)),
+            // Some servers send raw deflate streams, without the zlib wrapper.
+            Encoding::Deflate => decode(&mut flate2::read::ZlibDecoder::new(data))
+                .or_else(|_| decode(&mut flate2::read::DeflateDecoder::new(data))),
+            Encoding::Brotli => decode(&mut brotli::Decompressor::new(data, BROTLI_BUFFER_SIZE)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = b"{\"hello\": \"world\"}".repeat(100);
+        for encoding in [Encoding::Gzip, Encoding::Deflate, Encoding::Brotli] {
+            let encoded = encoding.encode(&data);
+            assert!(encoded.len() < data.len());
+            assert_eq!(Encoding::from_header(encoding.as_str()), Some(encoding));
+            assert_eq!(
+                encoding.decode(&encoded, data.len()),
+                Ok(Some(data.clone()))
+            );
+            assert_eq!(encoding.decode(&encoded, data.len() - 1), Ok(None));
+            assert_eq!(encoding.decode(b"plain", 100), Err(Status::ParseFailure));
+        }
+
+        let mut raw = Vec::new();
+        flate2::read::DeflateEncoder::new(&data[..], flate2::Compression::fast())
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(Encoding::Deflate.decode(&raw, data.len()), Ok(Some(data)));
+        assert_eq!(Encoding::from_header(" GZIP "), Some(Encoding::Gzip));
+        assert_eq!(Encoding::from_header("identity"), None);
+        assert_eq!(Encoding::from_header("gzip, br"), None);
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deserialization of the plugin and VM configuration with `serde`.
+//!
+//! Enabled by the `json` and `yaml` features.
+
+use crate::hostcalls;
+use crate::traits::*;
+use crate::types::*;
+use log::error;
+use serde::de::value::{Error as ValueError, MapDeserializer};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl Default for Format {
+    #[cfg(feature = "json")]
+    fn default() -> Format {
+        Format::Json
+    }
+
+    #[cfg(not(feature = "json"))]
+    fn default() -> Format {
+        Format::Yaml
+    }
+}
+
+/// Error returned when the configuration can't be deserialized or is invalid.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ConfigError {
+    buffer_type: BufferType,
+    message: String,
+    location: Option<(usize, usize)>,
+}
+
+impl ConfigError {
+    pub fn new(buffer_type: BufferType, message: impl Into<String>) -> ConfigError {
+        ConfigError {
+            buffer_type,
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    // Parsers append the location to their messages, so it's moved to its own field.
+    fn located(
+        buffer_type: BufferType,
+        mut message: String,
+        location: Option<(usize, usize)>,
+    ) -> ConfigError {
+        if let Some((line, column)) = location {
+            let suffix = format!(" at line {line} column {column}");
+            if message.ends_with(&suffix) {
+                message.truncate(message.len() - suffix.len());
+            }
+        }
+        ConfigError {
+            buffer_type,
+            message,
+            location,
+        }
+    }
+
+    /// Either [`BufferType::PluginConfiguration`] or [`BufferType::VmConfiguration`].
+    pub fn buffer_type(&self) -> BufferType {
+        self.buffer_type
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Line and column of the error, if it was raised by the parser.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        self.location
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.buffer_type {
+            BufferType::VmConfiguration => "VM",
+            _ => "plugin",
+        };
+        write!(f, "invalid {name} configuration: {}", self.message)?;
+        if let Some((line, column)) = self.location {
+            write!(f, " (line {line}, column {column})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Deserializes the plugin configuration.
+///
+/// A missing configuration is deserialized as an empty map, so that it's
+/// accepted by types whose fields all have defaults.
+pub fn plugin_configuration<C: DeserializeOwned>(format: Format) -> Result<C, ConfigError> {
+    let buffer_type = BufferType::PluginConfiguration;
+    match read(buffer_type)? {
+        Some(bytes) => deserialize(buffer_type, format, &bytes),
+        None => C::deserialize(MapDeserializer::<_, ValueError>::new(std::iter::empty::<(
+            &str,
+            &str,
+        )>()))
+        .map_err(|error| ConfigError::new(buffer_type, error.to_string())),
+    }
+}
+
+/// Deserializes the VM configuration, or returns `Ok(None)` if it's missing.
+pub fn vm_configuration<V: DeserializeOwned>(format: Format) -> Result<Option<V>, ConfigError> {
+    let buffer_type = BufferType::VmConfiguration;
+    read(buffer_type)?
+        .map(|bytes| deserialize(buffer_type, format, &bytes))
+        .transpose()
+}
+
+fn read(buffer_type: BufferType) -> Result<Option<Bytes>, ConfigError> {
+    match hostcalls::get_buffer(buffer_type, 0, usize::MAX) {
+        Ok(bytes) => Ok(bytes.filter(|bytes| !bytes.is_empty())),
+        Err(status) => Err(ConfigError::new(buffer_type, format!("{status:?}"))),
+    }
+}
+
+fn deserialize<C: DeserializeOwned>(
+    buffer_type: BufferType,
+    format: Format,
+    bytes: &[u8],
+) -> Result<C, ConfigError> {
+    match format {
+        #[cfg(feature = "json")]
+        Format::Json => serde_json::from_slice(bytes).map_err(|error| {
+            let location = Some((error.line(), error.column())).filter(|&(line, _)| line > 0);
+            ConfigError::located(buffer_type, error.to_string(), location)
+        }),
+        #[cfg(feature = "yaml")]
+        Format::Yaml => serde_yaml::from_slice(bytes).map_err(|error| {
+            let location = error
+                .location()
+                .map(|location| (location.line(), location.column()));
+            ConfigError::located(buffer_type, error.to_string(), location)
+        }),
+    }
+}
+
+pub type NewConfiguredHttpContext<C> = fn(context_id: u32, config: Rc<C>) -> Box<dyn HttpContext>;
+pub type NewConfiguredStreamContext<C> =
+    fn(context_id: u32, config: Rc<C>) -> Box<dyn StreamContext>;
+pub type ConfigValidator<C> = fn(config: &C) -> Result<(), String>;
+
+/// Root context that deserializes the plugin configuration into `C` (and the VM
+/// configuration into `V`), and shares it with each new context.
+///
+/// Invalid configurations are logged and rejected, in which case the host keeps
+/// using the previous one, if any. By default, the VM configuration only needs
+/// to be well-formed.
+pub struct ConfiguredRoot<C, V = IgnoredAny> {
+    format: Format,
+    validator: Option<ConfigValidator<C>>,
+    new_http_context: Option<NewConfiguredHttpContext<C>>,
+    new_stream_context: Option<NewConfiguredStreamContext<C>>,
+    config: Option<Rc<C>>,
+    vm_config: Option<Rc<V>>,
+}
+
+impl<C, V> Default for ConfiguredRoot<C, V>
+where
+    C: DeserializeOwned + 'static,
+    V: DeserializeOwned + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, V> ConfiguredRoot<C, V>
+where
+    C: DeserializeOwned + 'static,
+    V: DeserializeOwned + 'static,
+{
+    pub fn new() -> Self {
+        ConfiguredRoot {
+            format: Format::default(),
+            validator: None,
+            new_http_context: None,
+            new_stream_context: None,
+            config: None,
+            vm_config: None,
+        }
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Checks the configuration after it's deserialized.
+    pub fn with_validator(mut self, validator: ConfigValidator<C>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn with_http_context(mut self, callback: NewConfiguredHttpContext<C>) -> Self {
+        self.new_http_context = Some(callback);
+        self
+    }
+
+    pub fn with_stream_context(mut self, callback: NewConfiguredStreamContext<C>) -> Self {
+        self.new_stream_context = Some(callback);
+        self
+    }
+
+    pub fn config(&self) -> Option<&Rc<C>> {
+        self.config.as_ref()
+    }
+
+    pub fn vm_config(&self) -> Option<&Rc<V>> {
+        self.vm_config.as_ref()
+    }
+
+    fn load(&self) -> Result<C, ConfigError> {
+        let config = plugin_configuration::<C>(self.format)?;
+        if let Some(validator) = self.validator {
+            validator(&config)
+                .map_err(|message| ConfigError::new(BufferType::PluginConfiguration, message))?;
+        }
+        Ok(config)
+    }
+}
+
+impl<C, V> Context for ConfiguredRoot<C, V> {}
+
+impl<C, V> RootContext for ConfiguredRoot<C, V>
+where
+    C: DeserializeOwned + 'static,
+    V: DeserializeOwned + 'static,
+{
+    fn on_vm_start(&mut self, _: usize) -> bool {
+        match vm_configuration::<V>(self.format) {
+            Ok(vm_config) => {
+                self.vm_config = vm_config.map(Rc::new);
+                true
+            }
+            Err(err) => {
+                error!("{err}");
+                false
+            }
+        }
+    }
+
+    fn on_configure(&mut self, _: usize) -> bool {
+        match self.load() {
+            Ok(config) => {
+                self.config = Some(Rc::new(config));
+                true
+            }
+            Err(err) => {
+                error!("{err}");
+                false
+            }
+        }
+    }
+
+    fn create_http_context(&self, context_id: u32) -> Option<Box<dyn HttpContext>> {
+        let config = self.config.clone()?;
+        self.new_http_context
+            .map(|callback| callback(context_id, config))
+    }
+
+    fn create_stream_context(&self, context_id: u32) -> Option<Box<dyn StreamContext>> {
+        let config = self.config.clone()?;
+        self.new_stream_context
+            .map(|callback| callback(context_id, config))
+    }
+
+    fn get_type(&self) -> Option<ContextType> {
+        match (self.new_http_context, self.new_stream_context) {
+            (Some(_), _) => Some(ContextType::HttpContext),
+            (None, Some(_)) => Some(ContextType::StreamContext),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::testing;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    struct Config {
+        header: String,
+        #[serde(default)]
+        limit: u32,
+    }
+
+    struct Filter(Rc<Config>);
+
+    impl Context for Filter {}
+
+    impl HttpContext for Filter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            self.set_http_request_header(&self.0.header, Some(&self.0.limit.to_string()));
+            Action::Continue
+        }
+    }
+
+    fn validate(config: &Config) -> Result<(), String> {
+        match config.limit {
+            0..=100 => Ok(()),
+            limit => Err(format!("limit {limit} is over 100")),
+        }
+    }
+
+    // JSON documents are also valid YAML.
+    #[test]
+    fn test_configured_root() {
+        let mut harness = Harness::new().with_root_context(|_| {
+            Box::new(
+                ConfiguredRoot::<Config>::new()
+                    .with_validator(validate)
+                    .with_http_context(|_, config| Box::new(Filter(config))),
+            )
+        });
+        testing::set_buffer(BufferType::PluginConfiguration, Some(b"{\"limit\": 500}"));
+        harness.create_root_context().vm_start().expect_result(true);
+        harness.configure().expect_result(false);
+        testing::set_buffer(
+            BufferType::PluginConfiguration,
+            Some(b"{\"header\": \"x-limit\", \"limit\": 500}"),
+        );
+        harness.configure().expect_result(false);
+        testing::set_buffer(
+            BufferType::PluginConfiguration,
+            Some(b"{\"header\": \"x-limit\", \"limit\": 50}"),
+        );
+        harness.configure().expect_result(true);
+        harness
+            .create_http_stream()
+            .request_headers(vec![], true)
+            .expect_action(Action::Continue);
+        assert_eq!(
+            testing::get_map_value(MapType::HttpRequestHeaders, "x-limit"),
+            Some("50".to_string())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        testing::reset();
+        let err = plugin_configuration::<Config>(Format::default()).unwrap_err();
+        assert_eq!(err.message(), "missing field `header`");
+        assert_eq!(err.location(), None);
+
+        #[cfg(feature = "json")]
+        {
+            testing::set_buffer(BufferType::PluginConfiguration, Some(b"{\"limit\": \"x\"}"));
+            let err = plugin_configuration::<Config>(Format::Json).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "invalid plugin configuration: invalid type: string \"x\", expected u32 (line 1, column 13)"
+            );
+        }
+
+        #[cfg(feature = "yaml")]
+        {
+            testing::set_buffer(BufferType::VmConfiguration, Some(b"header: a\nlimit: x"));
+            let err = vm_configuration::<Config>(Format::Yaml).unwrap_err();
+            assert_eq!(err.buffer_type(), BufferType::VmConfiguration);
+            assert_eq!(err.location(), Some((2, 8)));
+            assert_eq!(
+                err.to_string(),
+                "invalid VM configuration: limit: invalid type: string \"x\", expected u32 (line 2, column 8)"
+            );
+        }
+    }
+}
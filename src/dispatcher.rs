@@ -12,12 +12,38 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "async")]
+use crate::executor::Executor;
 use crate::hostcalls;
+use crate::timers::Timers;
 use crate::traits::*;
 use crate::types::*;
 use hashbrown::HashMap;
 use log::trace;
 use std::cell::{Cell, RefCell};
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::time::Duration;
+
+// Drains a shared queue registered with `shared_queue::QueueReceiver`.
+pub(crate) type QueueHandler = Box<dyn FnMut()>;
+
+// Receives the response of an HTTP or gRPC call, with the type of context that
+// dispatched it.
+pub(crate) enum CallHandler<T> {
+    Root(RootCallCallback<T>),
+    Stream(StreamCallCallback<T>),
+    Http(HttpContextCallCallback<T>),
+}
+
+type RootCallCallback<T> = Box<dyn FnOnce(&mut dyn RootContext, T)>;
+type StreamCallCallback<T> = Box<dyn FnOnce(&mut dyn StreamContext, T)>;
+type HttpContextCallCallback<T> = Box<dyn FnOnce(&mut dyn HttpContext, T)>;
+
+pub(crate) type HttpCallHandler = CallHandler<HttpCallResponse>;
+pub(crate) type GrpcCallHandler = CallHandler<GrpcCallResponse>;
 
 thread_local! {
 static DISPATCHER: Dispatcher = Dispatcher::new();
@@ -35,6 +61,14 @@ pub(crate) fn set_http_context(callback: NewHttpContext) {
     DISPATCHER.with(|dispatcher| dispatcher.set_http_context(callback));
 }
 
+pub(crate) fn set_error_policy(policy: ErrorPolicy) {
+    DISPATCHER.with(|dispatcher| dispatcher.set_error_policy(policy));
+}
+
+pub(crate) fn set_error_hook(hook: ErrorHook) {
+    DISPATCHER.with(|dispatcher| dispatcher.set_error_hook(hook));
+}
+
 pub(crate) fn register_callout(token_id: u32) {
     DISPATCHER.with(|dispatcher| dispatcher.register_callout(token_id));
 }
@@ -47,22 +81,146 @@ pub(crate) fn register_grpc_stream(token_id: u32) {
     DISPATCHER.with(|dispatcher| dispatcher.register_grpc_stream(token_id));
 }
 
+pub(crate) fn register_http_callback(token_id: u32, callback: HttpCallHandler) {
+    DISPATCHER.with(|dispatcher| dispatcher.register_http_callback(token_id, callback));
+}
+
+pub(crate) fn register_grpc_callback(token_id: u32, callback: GrpcCallHandler) {
+    DISPATCHER.with(|dispatcher| dispatcher.register_grpc_callback(token_id, callback));
+}
+
+pub(crate) fn register_grpc_stream_handler(token_id: u32, handler: Box<dyn GrpcStreamHandler>) {
+    DISPATCHER.with(|dispatcher| dispatcher.register_grpc_stream_handler(token_id, handler));
+}
+
+pub(crate) fn set_tick_period(period: Duration) -> Result<(), Status> {
+    DISPATCHER.with(|dispatcher| dispatcher.set_tick_period(period))
+}
+
+pub(crate) fn set_timer(
+    delay: Duration,
+    interval: Option<Duration>,
+    callback: TimerCallback,
+) -> Result<Timer, Status> {
+    DISPATCHER.with(|dispatcher| dispatcher.set_timer(delay, interval, callback))
+}
+
+pub(crate) fn cancel_timer(id: u32) {
+    DISPATCHER.with(|dispatcher| dispatcher.timers.cancel(id));
+}
+
+pub(crate) fn with_root<R: RootContext + 'static, T>(f: impl FnOnce(&mut R) -> T) -> Option<T> {
+    DISPATCHER.with(|dispatcher| dispatcher.with_root(f))
+}
+
+pub(crate) fn register_queue_handler(queue_id: u32, handler: QueueHandler) {
+    DISPATCHER.with(|dispatcher| dispatcher.register_queue_handler(queue_id, handler));
+}
+
+pub(crate) fn read_http_call_response(
+    num_headers: usize,
+    body_size: usize,
+    num_trailers: usize,
+) -> HttpCallResponse {
+    HttpCallResponse {
+        headers: match num_headers {
+            0 => Vec::new(),
+            _ => hostcalls::get_map_bytes(MapType::HttpCallResponseHeaders).unwrap_or_default(),
+        },
+        body: match body_size {
+            0 => None,
+            _ => hostcalls::get_buffer(BufferType::HttpCallResponseBody, 0, body_size)
+                .unwrap_or_default(),
+        },
+        trailers: match num_trailers {
+            0 => Vec::new(),
+            _ => hostcalls::get_map_bytes(MapType::HttpCallResponseTrailers).unwrap_or_default(),
+        },
+    }
+}
+
+pub(crate) fn read_grpc_call_response(status_code: u32, response_size: usize) -> GrpcCallResponse {
+    GrpcCallResponse {
+        status_code,
+        status_message: match status_code {
+            0 => None,
+            _ => hostcalls::get_grpc_status()
+                .map(|(_, message)| message)
+                .unwrap_or_default(),
+        },
+        message: match response_size {
+            0 => None,
+            _ => hostcalls::get_buffer(BufferType::GrpcReceiveBuffer, 0, response_size)
+                .unwrap_or_default(),
+        },
+    }
+}
+
+pub(crate) fn read_grpc_stream_message(message_size: usize) -> Bytes {
+    hostcalls::get_buffer(BufferType::GrpcReceiveBuffer, 0, message_size)
+        .unwrap_or_default()
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "async")]
+pub(crate) fn spawn(future: Pin<Box<dyn Future<Output = ()>>>) -> Action {
+    DISPATCHER.with(|dispatcher| dispatcher.spawn(future))
+}
+
+#[cfg(feature = "async")]
+pub(crate) fn with_executor<T>(f: impl FnOnce(&Executor) -> T) -> T {
+    DISPATCHER.with(|dispatcher| f(&dispatcher.executor))
+}
+
+// Doesn't panic while the dispatcher is being destroyed.
+#[cfg(feature = "async")]
+pub(crate) fn try_with_executor<T>(f: impl FnOnce(&Executor) -> T) -> Option<T> {
+    DISPATCHER
+        .try_with(|dispatcher| f(&dispatcher.executor))
+        .ok()
+}
+
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub(crate) fn with_dispatcher<T>(f: impl FnOnce(&Dispatcher) -> T) -> T {
+    DISPATCHER.with(f)
+}
+
 struct NoopRoot;
 
 impl Context for NoopRoot {}
 impl RootContext for NoopRoot {}
 
-struct Dispatcher {
+// Contexts that are borrowed while an error is reported are treated as absent.
+fn contains<T: ?Sized>(contexts: &RefCell<HashMap<u32, Box<T>>>, context_id: u32) -> bool {
+    contexts
+        .try_borrow()
+        .is_ok_and(|contexts| contexts.contains_key(&context_id))
+}
+
+pub(crate) struct Dispatcher {
     new_root: Cell<Option<NewRootContext>>,
     roots: RefCell<HashMap<u32, Box<dyn RootContext>>>,
     new_stream: Cell<Option<NewStreamContext>>,
     streams: RefCell<HashMap<u32, Box<dyn StreamContext>>>,
+    root_ids: RefCell<HashMap<u32, u32>>,
     new_http_stream: Cell<Option<NewHttpContext>>,
     http_streams: RefCell<HashMap<u32, Box<dyn HttpContext>>>,
     active_id: Cell<u32>,
     callouts: RefCell<HashMap<u32, u32>>,
     grpc_callouts: RefCell<HashMap<u32, u32>>,
     grpc_streams: RefCell<HashMap<u32, u32>>,
+    // Keyed by context and token ID, so that they're dropped with the context.
+    http_callbacks: RefCell<HashMap<(u32, u32), HttpCallHandler>>,
+    grpc_callbacks: RefCell<HashMap<(u32, u32), GrpcCallHandler>>,
+    grpc_stream_handlers: RefCell<HashMap<(u32, u32), Box<dyn GrpcStreamHandler>>>,
+    queue_handlers: RefCell<HashMap<u32, QueueHandler>>,
+    timers: Timers,
+    error_policy: Cell<ErrorPolicy>,
+    error_hook: Cell<Option<ErrorHook>>,
+    #[cfg(feature = "async")]
+    active_stream: Cell<Option<StreamType>>,
+    #[cfg(feature = "async")]
+    executor: Executor,
 }
 
 impl Dispatcher {
@@ -72,12 +230,24 @@ impl Dispatcher {
             roots: RefCell::new(HashMap::new()),
             new_stream: Cell::new(None),
             streams: RefCell::new(HashMap::new()),
+            root_ids: RefCell::new(HashMap::new()),
             new_http_stream: Cell::new(None),
             http_streams: RefCell::new(HashMap::new()),
             active_id: Cell::new(0),
             callouts: RefCell::new(HashMap::new()),
             grpc_callouts: RefCell::new(HashMap::new()),
             grpc_streams: RefCell::new(HashMap::new()),
+            http_callbacks: RefCell::new(HashMap::new()),
+            grpc_callbacks: RefCell::new(HashMap::new()),
+            grpc_stream_handlers: RefCell::new(HashMap::new()),
+            queue_handlers: RefCell::new(HashMap::new()),
+            timers: Timers::default(),
+            error_policy: Cell::new(ErrorPolicy::Abort),
+            error_hook: Cell::new(None),
+            #[cfg(feature = "async")]
+            active_stream: Cell::new(None),
+            #[cfg(feature = "async")]
+            executor: Executor::default(),
         }
     }
 
@@ -93,6 +263,88 @@ impl Dispatcher {
         self.new_http_stream.set(Some(callback));
     }
 
+    fn set_error_policy(&self, policy: ErrorPolicy) {
+        self.error_policy.set(policy);
+    }
+
+    fn set_error_hook(&self, hook: ErrorHook) {
+        self.error_hook.set(Some(hook));
+    }
+
+    fn fail<T>(&self, error: DispatchError, fallback: T) -> T {
+        if let Some(hook) = self.error_hook.get() {
+            hook(&error);
+        }
+        match self.error_policy.get() {
+            ErrorPolicy::Abort => panic!("{}", error),
+            ErrorPolicy::Ignore => {
+                hostcalls::log(LogLevel::Error, &error.to_string()).ok();
+            }
+            ErrorPolicy::ResetStream => {
+                hostcalls::log(LogLevel::Error, &error.to_string()).ok();
+                if let Some(context_id) = error.context_id() {
+                    self.reset_stream(context_id);
+                }
+            }
+        }
+        fallback
+    }
+
+    fn reset_stream(&self, context_id: u32) {
+        if contains(&self.roots, context_id)
+            || hostcalls::set_effective_context(context_id).is_err()
+        {
+            return;
+        }
+        // ABI v0.1.0 can't reset streams, so HTTP streams are answered locally instead.
+        #[cfg(proxy_abi = "0.1.0")]
+        if !contains(&self.streams, context_id) {
+            hostcalls::send_http_response(500, vec![], None).ok();
+        }
+        // Contexts unknown to the plugin are reset as HTTP streams if the host allows it.
+        #[cfg(not(proxy_abi = "0.1.0"))]
+        if contains(&self.streams, context_id)
+            || hostcalls::reset_http_request()
+                .and_then(|_| hostcalls::reset_http_response())
+                .is_err()
+        {
+            hostcalls::close_downstream().ok();
+            hostcalls::close_upstream().ok();
+        }
+    }
+
+    // Sets the context, and the stream that's processed, of the current callback.
+    fn enter(&self, context_id: u32, stream: Option<StreamType>) {
+        self.active_id.set(context_id);
+        #[cfg(feature = "async")]
+        self.active_stream.set(stream);
+        #[cfg(not(feature = "async"))]
+        let _ = stream;
+    }
+
+    #[cfg(feature = "async")]
+    fn spawn(&self, future: Pin<Box<dyn Future<Output = ()>>>) -> Action {
+        self.executor
+            .spawn(self.active_id.get(), self.active_stream.get(), future)
+    }
+
+    #[cfg(feature = "async")]
+    fn run_tasks(&self) {
+        self.executor.run(|context_id| {
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
+        });
+    }
+
+    fn set_effective_context(&self, context_id: u32) {
+        if let Err(status) = hostcalls::set_effective_context(context_id) {
+            self.fail(
+                DispatchError::EffectiveContextFailed { context_id, status },
+                (),
+            )
+        }
+    }
+
     fn register_callout(&self, token_id: u32) {
         if self
             .callouts
@@ -100,7 +352,13 @@ impl Dispatcher {
             .insert(token_id, self.active_id.get())
             .is_some()
         {
-            panic!("duplicate token_id")
+            self.fail(
+                DispatchError::DuplicateTokenId {
+                    token_id,
+                    context_id: self.active_id.get(),
+                },
+                (),
+            )
         }
     }
 
@@ -111,7 +369,13 @@ impl Dispatcher {
             .insert(token_id, self.active_id.get())
             .is_some()
         {
-            panic!("duplicate token_id")
+            self.fail(
+                DispatchError::DuplicateTokenId {
+                    token_id,
+                    context_id: self.active_id.get(),
+                },
+                (),
+            )
         }
     }
 
@@ -122,8 +386,135 @@ impl Dispatcher {
             .insert(token_id, self.active_id.get())
             .is_some()
         {
-            panic!("duplicate token_id")
+            self.fail(
+                DispatchError::DuplicateTokenId {
+                    token_id,
+                    context_id: self.active_id.get(),
+                },
+                (),
+            )
+        }
+    }
+
+    fn register_http_callback(&self, token_id: u32, callback: HttpCallHandler) {
+        self.http_callbacks
+            .borrow_mut()
+            .insert((self.active_id.get(), token_id), callback);
+    }
+
+    fn register_grpc_callback(&self, token_id: u32, callback: GrpcCallHandler) {
+        self.grpc_callbacks
+            .borrow_mut()
+            .insert((self.active_id.get(), token_id), callback);
+    }
+
+    fn register_grpc_stream_handler(&self, token_id: u32, handler: Box<dyn GrpcStreamHandler>) {
+        self.grpc_stream_handlers
+            .borrow_mut()
+            .insert((self.active_id.get(), token_id), handler);
+    }
+
+    // Tick periods and timers apply to the active root context.
+    fn set_tick_period(&self, period: Duration) -> Result<(), Status> {
+        self.timers.set_tick_period(self.active_id.get(), period)
+    }
+
+    fn set_timer(
+        &self,
+        delay: Duration,
+        interval: Option<Duration>,
+        callback: TimerCallback,
+    ) -> Result<Timer, Status> {
+        self.timers
+            .add(self.active_id.get(), delay, interval, callback)
+    }
+
+    fn register_queue_handler(&self, queue_id: u32, handler: QueueHandler) {
+        self.queue_handlers.borrow_mut().insert(queue_id, handler);
+    }
+
+    // Calls `f` with the root context of the active stream, unless it's already
+    // borrowed (e.g. while it's creating the stream) or isn't an `R`.
+    fn with_root<R: RootContext + 'static, T>(&self, f: impl FnOnce(&mut R) -> T) -> Option<T> {
+        let root_id = *self.root_ids.borrow().get(&self.active_id.get())?;
+        let mut roots = self.roots.try_borrow_mut().ok()?;
+        let root = roots.get_mut(&root_id)?;
+        root.as_mut().as_any().downcast_mut::<R>().map(f)
+    }
+
+    // Calls `f` with the context, after making it the active and effective one.
+    fn with_context(&self, context_id: u32, f: impl FnOnce(&mut dyn Context)) {
+        if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
+            f(http_stream.as_context())
+        } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
+            f(stream.as_context())
+        } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
+            f(root.as_context())
+        }
+    }
+
+    // Returns whether the gRPC stream has a handler.
+    fn with_grpc_stream_handler(
+        &self,
+        token_id: u32,
+        context_id: u32,
+        f: impl FnOnce(&mut dyn GrpcStreamHandler, &mut dyn Context),
+    ) -> bool {
+        // The handler is taken out while it runs, since it can open other streams.
+        let handler = self
+            .grpc_stream_handlers
+            .borrow_mut()
+            .remove(&(context_id, token_id));
+        match handler {
+            Some(mut handler) => {
+                self.with_context(context_id, |context| f(handler.as_mut(), context));
+                self.grpc_stream_handlers
+                    .borrow_mut()
+                    .insert((context_id, token_id), handler);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Calls the callback with the context that dispatched the call, if it has
+    // the same type as when it was registered.
+    fn call_callback<T>(
+        &self,
+        context_id: u32,
+        callback: CallHandler<T>,
+        response: impl FnOnce() -> T,
+    ) {
+        match callback {
+            CallHandler::Http(callback) => {
+                if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
+                    return callback(http_stream.as_mut(), response());
+                }
+            }
+            CallHandler::Stream(callback) => {
+                if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
+                    return callback(stream.as_mut(), response());
+                }
+            }
+            CallHandler::Root(callback) => {
+                if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
+                    return callback(root.as_mut(), response());
+                }
+            }
         }
+        self.fail(DispatchError::InvalidContextId(context_id), ())
     }
 
     fn create_root_context(&self, context_id: u32) {
@@ -137,7 +528,7 @@ impl Dispatcher {
             .insert(context_id, new_context)
             .is_some()
         {
-            panic!("duplicate context_id")
+            self.fail(DispatchError::DuplicateContextId(context_id), ())
         }
     }
 
@@ -147,18 +538,37 @@ impl Dispatcher {
                 Some(f) => f(context_id, root_context_id),
                 None => match root_context.create_stream_context(context_id) {
                     Some(stream_context) => stream_context,
-                    None => panic!("create_stream_context returned None"),
+                    None => {
+                        return self.fail(
+                            DispatchError::ContextNotCreated {
+                                context_id,
+                                root_context_id,
+                            },
+                            (),
+                        );
+                    }
                 },
             },
-            None => panic!("invalid root_context_id"),
+            None => {
+                return self.fail(
+                    DispatchError::InvalidRootContextId {
+                        context_id,
+                        root_context_id,
+                    },
+                    (),
+                );
+            }
         };
+        self.root_ids
+            .borrow_mut()
+            .insert(context_id, root_context_id);
         if self
             .streams
             .borrow_mut()
             .insert(context_id, new_context)
             .is_some()
         {
-            panic!("duplicate context_id")
+            self.fail(DispatchError::DuplicateContextId(context_id), ())
         }
     }
 
@@ -168,22 +578,41 @@ impl Dispatcher {
                 Some(f) => f(context_id, root_context_id),
                 None => match root_context.create_http_context(context_id) {
                     Some(stream_context) => stream_context,
-                    None => panic!("create_http_context returned None"),
+                    None => {
+                        return self.fail(
+                            DispatchError::ContextNotCreated {
+                                context_id,
+                                root_context_id,
+                            },
+                            (),
+                        );
+                    }
                 },
             },
-            None => panic!("invalid root_context_id"),
+            None => {
+                return self.fail(
+                    DispatchError::InvalidRootContextId {
+                        context_id,
+                        root_context_id,
+                    },
+                    (),
+                );
+            }
         };
+        self.root_ids
+            .borrow_mut()
+            .insert(context_id, root_context_id);
         if self
             .http_streams
             .borrow_mut()
             .insert(context_id, new_context)
             .is_some()
         {
-            panic!("duplicate context_id")
+            self.fail(DispatchError::DuplicateContextId(context_id), ())
         }
     }
 
-    fn on_create_context(&self, context_id: u32, root_context_id: u32) {
+    pub(crate) fn on_create_context(&self, context_id: u32, root_context_id: u32) {
         if root_context_id == 0 {
             self.create_root_context(context_id);
         } else if self.new_http_stream.get().is_some() {
@@ -198,236 +627,364 @@ impl Dispatcher {
                 Some(ContextType::StreamContext) => {
                     self.create_stream_context(context_id, root_context_id)
                 }
-                None => panic!("missing ContextType on root_context"),
+                None => self.fail(
+                    DispatchError::MissingContextType {
+                        context_id,
+                        root_context_id,
+                    },
+                    (),
+                ),
             }
         } else {
-            panic!("invalid root_context_id and missing constructors");
+            self.fail(
+                DispatchError::InvalidRootContextId {
+                    context_id,
+                    root_context_id,
+                },
+                (),
+            )
         }
     }
 
-    fn on_done(&self, context_id: u32) -> bool {
+    pub(crate) fn on_done(&self, context_id: u32) -> bool {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             http_stream.on_done()
         } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             stream.on_done()
         } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             root.on_done()
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), true)
         }
     }
 
-    fn on_log(&self, context_id: u32) {
+    pub(crate) fn on_log(&self, context_id: u32) {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             http_stream.on_log()
         } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             stream.on_log()
         } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             root.on_log()
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), ())
         }
     }
 
-    fn on_delete(&self, context_id: u32) {
+    pub(crate) fn on_delete(&self, context_id: u32) {
+        #[cfg(feature = "async")]
+        self.executor.cancel(context_id);
+        self.timers.remove(context_id);
+        self.root_ids.borrow_mut().remove(&context_id);
+        self.http_callbacks
+            .borrow_mut()
+            .retain(|&(id, _), _| id != context_id);
+        self.grpc_callbacks
+            .borrow_mut()
+            .retain(|&(id, _), _| id != context_id);
+        self.grpc_stream_handlers
+            .borrow_mut()
+            .retain(|&(id, _), _| id != context_id);
         if !(self.http_streams.borrow_mut().remove(&context_id).is_some()
             || self.streams.borrow_mut().remove(&context_id).is_some()
             || self.roots.borrow_mut().remove(&context_id).is_some())
         {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), ())
         }
     }
 
-    fn on_vm_start(&self, context_id: u32, vm_configuration_size: usize) -> bool {
+    pub(crate) fn on_vm_start(&self, context_id: u32, vm_configuration_size: usize) -> bool {
         if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             root.on_vm_start(vm_configuration_size)
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), false)
         }
     }
 
-    fn on_configure(&self, context_id: u32, plugin_configuration_size: usize) -> bool {
+    pub(crate) fn on_configure(&self, context_id: u32, plugin_configuration_size: usize) -> bool {
         if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             root.on_configure(plugin_configuration_size)
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), false)
         }
     }
 
-    fn on_tick(&self, context_id: u32) {
+    pub(crate) fn on_tick(&self, context_id: u32) {
         if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            root.on_tick()
+            self.enter(context_id, None);
+            if self.timers.on_tick(context_id, root.as_mut()) {
+                root.on_tick()
+            }
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), ())
+        }
+        #[cfg(feature = "async")]
+        {
+            self.executor.on_tick();
+            self.run_tasks();
         }
     }
 
-    fn on_queue_ready(&self, context_id: u32, queue_id: u32) {
+    pub(crate) fn on_queue_ready(&self, context_id: u32, queue_id: u32) {
+        // Pending dequeues take their items first, and the handler or the root
+        // context still gets to drain the rest.
+        #[cfg(feature = "async")]
+        if self.executor.on_queue_ready(queue_id) {
+            self.run_tasks();
+        }
+
+        // The handler is taken out while it runs, since it can register other queues.
+        let handler = self.queue_handlers.borrow_mut().remove(&queue_id);
+        if let Some(mut handler) = handler {
+            self.with_context(context_id, |_| handler());
+            self.queue_handlers
+                .borrow_mut()
+                .entry(queue_id)
+                .or_insert(handler);
+            return;
+        }
         if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             root.on_queue_ready(queue_id)
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), ())
         }
     }
 
-    fn on_new_connection(&self, context_id: u32) -> Action {
+    pub(crate) fn on_new_connection(&self, context_id: u32) -> Action {
         if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::Downstream));
             stream.on_new_connection()
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_downstream_data(&self, context_id: u32, data_size: usize, end_of_stream: bool) -> Action {
+    pub(crate) fn on_downstream_data(
+        &self,
+        context_id: u32,
+        data_size: usize,
+        end_of_stream: bool,
+    ) -> Action {
         if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::Downstream));
             stream.on_downstream_data(data_size, end_of_stream)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_downstream_close(&self, context_id: u32, peer_type: PeerType) {
+    pub(crate) fn on_downstream_close(&self, context_id: u32, peer_type: PeerType) {
         if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             stream.on_downstream_close(peer_type)
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), ())
         }
     }
 
-    fn on_upstream_data(&self, context_id: u32, data_size: usize, end_of_stream: bool) -> Action {
+    pub(crate) fn on_upstream_data(
+        &self,
+        context_id: u32,
+        data_size: usize,
+        end_of_stream: bool,
+    ) -> Action {
         if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::Upstream));
             stream.on_upstream_data(data_size, end_of_stream)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_upstream_close(&self, context_id: u32, peer_type: PeerType) {
+    pub(crate) fn on_upstream_close(&self, context_id: u32, peer_type: PeerType) {
         if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, None);
             stream.on_upstream_close(peer_type)
         } else {
-            panic!("invalid context_id")
+            self.fail(DispatchError::InvalidContextId(context_id), ())
         }
     }
 
-    fn on_http_request_headers(
+    pub(crate) fn on_http_request_headers(
         &self,
         context_id: u32,
         num_headers: usize,
         end_of_stream: bool,
     ) -> Action {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::HttpRequest));
             http_stream.on_http_request_headers(num_headers, end_of_stream)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_http_request_body(
+    pub(crate) fn on_http_request_body(
         &self,
         context_id: u32,
         body_size: usize,
         end_of_stream: bool,
     ) -> Action {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::HttpRequest));
             http_stream.on_http_request_body(body_size, end_of_stream)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_http_request_trailers(&self, context_id: u32, num_trailers: usize) -> Action {
+    pub(crate) fn on_http_request_trailers(&self, context_id: u32, num_trailers: usize) -> Action {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::HttpRequest));
             http_stream.on_http_request_trailers(num_trailers)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
+        }
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    pub(crate) fn on_http_request_metadata(&self, context_id: u32, num_elements: usize) -> Action {
+        if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
+            self.enter(context_id, Some(StreamType::HttpRequest));
+            http_stream.on_http_request_metadata(num_elements)
+        } else {
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_http_response_headers(
+    pub(crate) fn on_http_response_headers(
         &self,
         context_id: u32,
         num_headers: usize,
         end_of_stream: bool,
     ) -> Action {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::HttpResponse));
             http_stream.on_http_response_headers(num_headers, end_of_stream)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_http_response_body(
+    pub(crate) fn on_http_response_body(
         &self,
         context_id: u32,
         body_size: usize,
         end_of_stream: bool,
     ) -> Action {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::HttpResponse));
             http_stream.on_http_response_body(body_size, end_of_stream)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_http_response_trailers(&self, context_id: u32, num_trailers: usize) -> Action {
+    pub(crate) fn on_http_response_trailers(&self, context_id: u32, num_trailers: usize) -> Action {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
+            self.enter(context_id, Some(StreamType::HttpResponse));
             http_stream.on_http_response_trailers(num_trailers)
         } else {
-            panic!("invalid context_id")
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
+        }
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    pub(crate) fn on_http_response_metadata(&self, context_id: u32, num_elements: usize) -> Action {
+        if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
+            self.enter(context_id, Some(StreamType::HttpResponse));
+            http_stream.on_http_response_metadata(num_elements)
+        } else {
+            self.fail(
+                DispatchError::InvalidContextId(context_id),
+                Action::Continue,
+            )
         }
     }
 
-    fn on_http_call_response(
+    pub(crate) fn on_http_call_response(
         &self,
         token_id: u32,
         num_headers: usize,
         body_size: usize,
         num_trailers: usize,
     ) {
-        let context_id = self
-            .callouts
+        let context_id = match self.callouts.borrow_mut().remove(&token_id) {
+            Some(id) => id,
+            None => return self.fail(DispatchError::InvalidTokenId(token_id), ()),
+        };
+
+        #[cfg(feature = "async")]
+        if self
+            .executor
+            .on_http_call_response(token_id, num_headers, body_size, num_trailers)
+        {
+            return self.run_tasks();
+        }
+
+        let callback = self
+            .http_callbacks
             .borrow_mut()
-            .remove(&token_id)
-            .expect("invalid token_id");
+            .remove(&(context_id, token_id));
+        if let Some(callback) = callback {
+            let response = || read_http_call_response(num_headers, body_size, num_trailers);
+            return self.call_callback(context_id, callback, response);
+        }
 
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             http_stream.on_http_call_response(token_id, num_headers, body_size, num_trailers)
         } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             stream.on_http_call_response(token_id, num_headers, body_size, num_trailers)
         } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             root.on_http_call_response(token_id, num_headers, body_size, num_trailers)
         }
     }
 
-    fn on_grpc_receive_initial_metadata(&self, token_id: u32, headers: u32) {
+    pub(crate) fn on_grpc_receive_initial_metadata(&self, token_id: u32, headers: u32) {
         let context_id = match self.grpc_streams.borrow_mut().get(&token_id) {
             Some(id) => *id,
             None => {
@@ -437,51 +994,86 @@ impl Dispatcher {
             }
         };
 
+        if self.with_grpc_stream_handler(token_id, context_id, |handler, context| {
+            let metadata = hostcalls::get_map_bytes(MapType::GrpcReceiveInitialMetadata);
+            handler.on_initial_metadata(context, token_id, metadata.unwrap_or_default())
+        }) {
+            return;
+        }
+
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             http_stream.on_grpc_stream_initial_metadata(token_id, headers);
         } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             stream.on_grpc_stream_initial_metadata(token_id, headers);
         } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             root.on_grpc_stream_initial_metadata(token_id, headers);
         }
     }
 
-    fn on_grpc_receive(&self, token_id: u32, response_size: usize) {
+    pub(crate) fn on_grpc_receive(&self, token_id: u32, response_size: usize) {
         let context_id = self.grpc_callouts.borrow_mut().remove(&token_id);
         if let Some(context_id) = context_id {
+            #[cfg(feature = "async")]
+            if self
+                .executor
+                .on_grpc_call_response(token_id, 0, response_size)
+            {
+                return self.run_tasks();
+            }
+            let callback = self
+                .grpc_callbacks
+                .borrow_mut()
+                .remove(&(context_id, token_id));
+            if let Some(callback) = callback {
+                let response = || read_grpc_call_response(0, response_size);
+                return self.call_callback(context_id, callback, response);
+            }
             if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-                self.active_id.set(context_id);
-                hostcalls::set_effective_context(context_id).unwrap();
+                self.enter(context_id, None);
+                self.set_effective_context(context_id);
                 http_stream.on_grpc_call_response(token_id, 0, response_size);
             } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-                self.active_id.set(context_id);
-                hostcalls::set_effective_context(context_id).unwrap();
+                self.enter(context_id, None);
+                self.set_effective_context(context_id);
                 stream.on_grpc_call_response(token_id, 0, response_size);
             } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-                self.active_id.set(context_id);
-                hostcalls::set_effective_context(context_id).unwrap();
+                self.enter(context_id, None);
+                self.set_effective_context(context_id);
                 root.on_grpc_call_response(token_id, 0, response_size);
             }
         } else {
             let context_id = self.grpc_streams.borrow().get(&token_id).cloned();
             if let Some(context_id) = context_id {
+                #[cfg(feature = "async")]
+                if self
+                    .executor
+                    .on_grpc_stream_message(token_id, response_size)
+                {
+                    return self.run_tasks();
+                }
+                if self.with_grpc_stream_handler(token_id, context_id, |handler, context| {
+                    let message = read_grpc_stream_message(response_size);
+                    handler.on_message(context, token_id, message)
+                }) {
+                    return;
+                }
                 if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-                    self.active_id.set(context_id);
-                    hostcalls::set_effective_context(context_id).unwrap();
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
                     http_stream.on_grpc_stream_message(token_id, response_size);
                 } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-                    self.active_id.set(context_id);
-                    hostcalls::set_effective_context(context_id).unwrap();
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
                     stream.on_grpc_stream_message(token_id, response_size);
                 } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-                    self.active_id.set(context_id);
-                    hostcalls::set_effective_context(context_id).unwrap();
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
                     root.on_grpc_stream_message(token_id, response_size);
                 }
             } else {
@@ -491,7 +1083,7 @@ impl Dispatcher {
         }
     }
 
-    fn on_grpc_receive_trailing_metadata(&self, token_id: u32, trailers: u32) {
+    pub(crate) fn on_grpc_receive_trailing_metadata(&self, token_id: u32, trailers: u32) {
         let context_id = match self.grpc_streams.borrow_mut().get(&token_id) {
             Some(id) => *id,
             None => {
@@ -501,51 +1093,86 @@ impl Dispatcher {
             }
         };
 
+        if self.with_grpc_stream_handler(token_id, context_id, |handler, context| {
+            let metadata = hostcalls::get_map_bytes(MapType::GrpcReceiveTrailingMetadata);
+            handler.on_trailing_metadata(context, token_id, metadata.unwrap_or_default())
+        }) {
+            return;
+        }
+
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             http_stream.on_grpc_stream_trailing_metadata(token_id, trailers);
         } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             stream.on_grpc_stream_trailing_metadata(token_id, trailers);
         } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             root.on_grpc_stream_trailing_metadata(token_id, trailers);
         }
     }
 
-    fn on_grpc_close(&self, token_id: u32, status_code: u32) {
+    pub(crate) fn on_grpc_close(&self, token_id: u32, status_code: u32) {
         let context_id = self.grpc_callouts.borrow_mut().remove(&token_id);
         if let Some(context_id) = context_id {
+            #[cfg(feature = "async")]
+            if self
+                .executor
+                .on_grpc_call_response(token_id, status_code, 0)
+            {
+                return self.run_tasks();
+            }
+            let callback = self
+                .grpc_callbacks
+                .borrow_mut()
+                .remove(&(context_id, token_id));
+            if let Some(callback) = callback {
+                let response = || read_grpc_call_response(status_code, 0);
+                return self.call_callback(context_id, callback, response);
+            }
             if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-                self.active_id.set(context_id);
-                hostcalls::set_effective_context(context_id).unwrap();
+                self.enter(context_id, None);
+                self.set_effective_context(context_id);
                 http_stream.on_grpc_call_response(token_id, status_code, 0);
             } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-                self.active_id.set(context_id);
-                hostcalls::set_effective_context(context_id).unwrap();
+                self.enter(context_id, None);
+                self.set_effective_context(context_id);
                 stream.on_grpc_call_response(token_id, status_code, 0);
             } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-                self.active_id.set(context_id);
-                hostcalls::set_effective_context(context_id).unwrap();
+                self.enter(context_id, None);
+                self.set_effective_context(context_id);
                 root.on_grpc_call_response(token_id, status_code, 0);
             }
         } else {
             let context_id = self.grpc_streams.borrow_mut().remove(&token_id);
             if let Some(context_id) = context_id {
+                #[cfg(feature = "async")]
+                if self.executor.on_grpc_stream_close(token_id) {
+                    return self.run_tasks();
+                }
+                let handler = self
+                    .grpc_stream_handlers
+                    .borrow_mut()
+                    .remove(&(context_id, token_id));
+                if let Some(mut handler) = handler {
+                    return self.with_context(context_id, |context| {
+                        handler.on_close(context, token_id, status_code)
+                    });
+                }
                 if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-                    self.active_id.set(context_id);
-                    hostcalls::set_effective_context(context_id).unwrap();
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
                     http_stream.on_grpc_stream_close(token_id, status_code)
                 } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-                    self.active_id.set(context_id);
-                    hostcalls::set_effective_context(context_id).unwrap();
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
                     stream.on_grpc_stream_close(token_id, status_code)
                 } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-                    self.active_id.set(context_id);
-                    hostcalls::set_effective_context(context_id).unwrap();
+                    self.enter(context_id, None);
+                    self.set_effective_context(context_id);
                     root.on_grpc_stream_close(token_id, status_code)
                 }
             } else {
@@ -555,18 +1182,23 @@ impl Dispatcher {
         }
     }
 
-    fn on_foreign_function(&self, context_id: u32, function_id: u32, arugments_size: usize) {
+    pub(crate) fn on_foreign_function(
+        &self,
+        context_id: u32,
+        function_id: u32,
+        arugments_size: usize,
+    ) {
         if let Some(http_stream) = self.http_streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             http_stream.on_foreign_function(function_id, arugments_size)
         } else if let Some(stream) = self.streams.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             stream.on_foreign_function(function_id, arugments_size)
         } else if let Some(root) = self.roots.borrow_mut().get_mut(&context_id) {
-            self.active_id.set(context_id);
-            hostcalls::set_effective_context(context_id).unwrap();
+            self.enter(context_id, None);
+            self.set_effective_context(context_id);
             root.on_foreign_function(function_id, arugments_size)
         }
     }
@@ -574,46 +1206,76 @@ impl Dispatcher {
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_context_create(context_id: u32, root_context_id: u32) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_context_create",
+        &[context_id as u64, root_context_id as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_create_context(context_id, root_context_id))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_done(context_id: u32) -> bool {
+    #[cfg(feature = "trace")]
+    crate::trace::callback("proxy_on_done", &[context_id as u64]);
     DISPATCHER.with(|dispatcher| dispatcher.on_done(context_id))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_log(context_id: u32) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback("proxy_on_log", &[context_id as u64]);
     DISPATCHER.with(|dispatcher| dispatcher.on_log(context_id))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_delete(context_id: u32) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback("proxy_on_delete", &[context_id as u64]);
     DISPATCHER.with(|dispatcher| dispatcher.on_delete(context_id))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_vm_start(context_id: u32, vm_configuration_size: usize) -> bool {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_vm_start",
+        &[context_id as u64, vm_configuration_size as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_vm_start(context_id, vm_configuration_size))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_configure(context_id: u32, plugin_configuration_size: usize) -> bool {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_configure",
+        &[context_id as u64, plugin_configuration_size as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_configure(context_id, plugin_configuration_size))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_tick(context_id: u32) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback("proxy_on_tick", &[context_id as u64]);
     DISPATCHER.with(|dispatcher| dispatcher.on_tick(context_id))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_queue_ready(context_id: u32, queue_id: u32) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_queue_ready",
+        &[context_id as u64, queue_id as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_queue_ready(context_id, queue_id))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_new_connection(context_id: u32) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback("proxy_on_new_connection", &[context_id as u64]);
     DISPATCHER.with(|dispatcher| dispatcher.on_new_connection(context_id))
 }
 
@@ -623,12 +1285,22 @@ pub extern "C" fn proxy_on_downstream_data(
     data_size: usize,
     end_of_stream: bool,
 ) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_downstream_data",
+        &[context_id as u64, data_size as u64, end_of_stream as u64],
+    );
     DISPATCHER
         .with(|dispatcher| dispatcher.on_downstream_data(context_id, data_size, end_of_stream))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_downstream_connection_close(context_id: u32, peer_type: PeerType) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_downstream_connection_close",
+        &[context_id as u64, peer_type as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_downstream_close(context_id, peer_type))
 }
 
@@ -638,66 +1310,155 @@ pub extern "C" fn proxy_on_upstream_data(
     data_size: usize,
     end_of_stream: bool,
 ) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_upstream_data",
+        &[context_id as u64, data_size as u64, end_of_stream as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_upstream_data(context_id, data_size, end_of_stream))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_upstream_connection_close(context_id: u32, peer_type: PeerType) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_upstream_connection_close",
+        &[context_id as u64, peer_type as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_upstream_close(context_id, peer_type))
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_request_headers(
     context_id: u32,
     num_headers: usize,
     end_of_stream: bool,
 ) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_request_headers",
+        &[context_id as u64, num_headers as u64, end_of_stream as u64],
+    );
     DISPATCHER.with(|dispatcher| {
         dispatcher.on_http_request_headers(context_id, num_headers, end_of_stream)
     })
 }
 
+// ABI v0.1.0 doesn't pass end_of_stream with headers.
+#[cfg(proxy_abi = "0.1.0")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_on_request_headers(context_id: u32, num_headers: usize) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_request_headers",
+        &[context_id as u64, num_headers as u64],
+    );
+    DISPATCHER.with(|dispatcher| dispatcher.on_http_request_headers(context_id, num_headers, false))
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_request_body(
     context_id: u32,
     body_size: usize,
     end_of_stream: bool,
 ) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_request_body",
+        &[context_id as u64, body_size as u64, end_of_stream as u64],
+    );
     DISPATCHER
         .with(|dispatcher| dispatcher.on_http_request_body(context_id, body_size, end_of_stream))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_request_trailers(context_id: u32, num_trailers: usize) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_request_trailers",
+        &[context_id as u64, num_trailers as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_http_request_trailers(context_id, num_trailers))
 }
 
+#[cfg(proxy_abi = "vnext")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_on_request_metadata(context_id: u32, num_elements: usize) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_request_metadata",
+        &[context_id as u64, num_elements as u64],
+    );
+    DISPATCHER.with(|dispatcher| dispatcher.on_http_request_metadata(context_id, num_elements))
+}
+
+#[cfg(not(proxy_abi = "0.1.0"))]
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_response_headers(
     context_id: u32,
     num_headers: usize,
     end_of_stream: bool,
 ) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_response_headers",
+        &[context_id as u64, num_headers as u64, end_of_stream as u64],
+    );
     DISPATCHER.with(|dispatcher| {
         dispatcher.on_http_response_headers(context_id, num_headers, end_of_stream)
     })
 }
 
+// ABI v0.1.0 doesn't pass end_of_stream with headers.
+#[cfg(proxy_abi = "0.1.0")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_on_response_headers(context_id: u32, num_headers: usize) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_response_headers",
+        &[context_id as u64, num_headers as u64],
+    );
+    DISPATCHER
+        .with(|dispatcher| dispatcher.on_http_response_headers(context_id, num_headers, false))
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_response_body(
     context_id: u32,
     body_size: usize,
     end_of_stream: bool,
 ) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_response_body",
+        &[context_id as u64, body_size as u64, end_of_stream as u64],
+    );
     DISPATCHER
         .with(|dispatcher| dispatcher.on_http_response_body(context_id, body_size, end_of_stream))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_response_trailers(context_id: u32, num_trailers: usize) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_response_trailers",
+        &[context_id as u64, num_trailers as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_http_response_trailers(context_id, num_trailers))
 }
 
+#[cfg(proxy_abi = "vnext")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_on_response_metadata(context_id: u32, num_elements: usize) -> Action {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_response_metadata",
+        &[context_id as u64, num_elements as u64],
+    );
+    DISPATCHER.with(|dispatcher| dispatcher.on_http_response_metadata(context_id, num_elements))
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_http_call_response(
     _context_id: u32,
@@ -706,6 +1467,17 @@ pub extern "C" fn proxy_on_http_call_response(
     body_size: usize,
     num_trailers: usize,
 ) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_http_call_response",
+        &[
+            _context_id as u64,
+            token_id as u64,
+            num_headers as u64,
+            body_size as u64,
+            num_trailers as u64,
+        ],
+    );
     DISPATCHER.with(|dispatcher| {
         dispatcher.on_http_call_response(token_id, num_headers, body_size, num_trailers)
     })
@@ -717,11 +1489,21 @@ pub extern "C" fn proxy_on_grpc_receive_initial_metadata(
     token_id: u32,
     headers: u32,
 ) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_grpc_receive_initial_metadata",
+        &[_context_id as u64, token_id as u64, headers as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_grpc_receive_initial_metadata(token_id, headers))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_grpc_receive(_context_id: u32, token_id: u32, response_size: usize) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_grpc_receive",
+        &[_context_id as u64, token_id as u64, response_size as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_grpc_receive(token_id, response_size))
 }
 
@@ -731,11 +1513,21 @@ pub extern "C" fn proxy_on_grpc_receive_trailing_metadata(
     token_id: u32,
     trailers: u32,
 ) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_grpc_receive_trailing_metadata",
+        &[_context_id as u64, token_id as u64, trailers as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_grpc_receive_trailing_metadata(token_id, trailers))
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_on_grpc_close(_context_id: u32, token_id: u32, status_code: u32) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_grpc_close",
+        &[_context_id as u64, token_id as u64, status_code as u64],
+    );
     DISPATCHER.with(|dispatcher| dispatcher.on_grpc_close(token_id, status_code))
 }
 
@@ -745,6 +1537,280 @@ pub extern "C" fn proxy_on_foreign_function(
     function_id: u32,
     arguments_size: usize,
 ) {
+    #[cfg(feature = "trace")]
+    crate::trace::callback(
+        "proxy_on_foreign_function",
+        &[context_id as u64, function_id as u64, arguments_size as u64],
+    );
     DISPATCHER
         .with(|dispatcher| dispatcher.on_foreign_function(context_id, function_id, arguments_size))
 }
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    thread_local! {
+        static ERRORS: RefCell<Vec<DispatchError>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(error: &DispatchError) {
+        ERRORS.with(|errors| errors.borrow_mut().push(*error));
+    }
+
+    #[test]
+    fn test_error_policy_ignore() {
+        testing::reset();
+        let dispatcher = Dispatcher::new();
+        dispatcher.set_error_policy(ErrorPolicy::Ignore);
+        dispatcher.set_error_hook(record);
+        dispatcher.on_create_context(1, 0);
+        dispatcher.on_create_context(2, 1);
+        assert_eq!(
+            dispatcher.on_http_request_headers(3, 0, true),
+            Action::Continue
+        );
+        dispatcher.on_http_call_response(4, 0, 0, 0);
+        assert_eq!(
+            ERRORS.with(|errors| errors.take()),
+            vec![
+                DispatchError::MissingContextType {
+                    context_id: 2,
+                    root_context_id: 1
+                },
+                DispatchError::InvalidContextId(3),
+                DispatchError::InvalidTokenId(4),
+            ]
+        );
+        assert_eq!(testing::take_logs().len(), 3);
+        assert!(testing::get_closed_streams().is_empty());
+    }
+
+    #[test]
+    fn test_error_policy_reset_stream() {
+        testing::reset();
+        let dispatcher = Dispatcher::new();
+        dispatcher.set_error_policy(ErrorPolicy::ResetStream);
+        dispatcher.on_http_request_headers(3, 0, true);
+        assert_eq!(testing::get_effective_context(), 3);
+        #[cfg(not(proxy_abi = "0.1.0"))]
+        assert_eq!(
+            testing::get_closed_streams(),
+            vec![StreamType::HttpRequest, StreamType::HttpResponse]
+        );
+        #[cfg(proxy_abi = "0.1.0")]
+        assert_eq!(testing::take_local_response().unwrap().status_code, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid context_id: 3")]
+    fn test_error_policy_abort() {
+        let dispatcher = Dispatcher::new();
+        dispatcher.on_http_request_headers(3, 0, true);
+    }
+
+    struct Root;
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(Filter))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct Filter;
+
+    impl Context for Filter {
+        fn on_http_call_response(&mut self, _: u32, _: usize, _: usize, _: usize) {
+            unreachable!()
+        }
+    }
+
+    impl HttpContext for Filter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            let pending = Rc::new(Cell::new(2));
+            for name in ["x-first", "x-second"] {
+                let pending = pending.clone();
+                let callback = Box::new(
+                    move |context: &mut dyn HttpContext, response: HttpCallResponse| {
+                        let body = String::from_utf8(response.body.unwrap()).unwrap();
+                        context.add_http_request_header(name, &body);
+                        pending.set(pending.get() - 1);
+                        if pending.get() == 0 {
+                            context.resume_http_request();
+                        }
+                    },
+                );
+                let timeout = Duration::from_secs(1);
+                self.dispatch_http_call_with_callback(
+                    name,
+                    vec![],
+                    None,
+                    vec![],
+                    timeout,
+                    callback,
+                )
+                .unwrap();
+            }
+            Action::Pause
+        }
+    }
+
+    #[test]
+    fn test_http_call_callbacks() {
+        let mut harness = testing::Harness::new().with_root_context(|_| Box::new(Root));
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], true);
+        for call in testing::take_http_calls().into_iter().rev() {
+            let body = call.upstream.as_bytes();
+            harness.http_call_response(call.token_id, vec![], Some(body), vec![]);
+        }
+        assert_eq!(
+            testing::get_map(MapType::HttpRequestHeaders),
+            vec![
+                ("x-second".to_string(), "x-second".to_string()),
+                ("x-first".to_string(), "x-first".to_string()),
+            ]
+        );
+        assert_eq!(
+            testing::get_resumed_streams(),
+            vec![StreamType::HttpRequest]
+        );
+    }
+
+    #[test]
+    fn test_http_call_callbacks_dropped_with_context() {
+        let mut harness = testing::Harness::new().with_root_context(|_| Box::new(Root));
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], true).finish();
+        with_dispatcher(|dispatcher| assert!(dispatcher.http_callbacks.borrow().is_empty()));
+        for call in testing::take_http_calls() {
+            harness.http_call_response(call.token_id, vec![], Some(b"late"), vec![]);
+        }
+        assert_eq!(testing::get_map(MapType::HttpRequestHeaders), vec![]);
+    }
+
+    struct GrpcRoot;
+
+    impl Context for GrpcRoot {}
+
+    impl RootContext for GrpcRoot {
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(GrpcFilter))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct GrpcFilter;
+
+    impl Context for GrpcFilter {
+        fn on_grpc_call_response(&mut self, _: u32, _: u32, _: usize) {
+            unreachable!()
+        }
+    }
+
+    impl HttpContext for GrpcFilter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            let callback = Box::new(
+                |context: &mut dyn HttpContext, response: GrpcCallResponse| {
+                    let message = String::from_utf8(response.message.unwrap()).unwrap();
+                    context.add_http_request_header("x-grpc", &message);
+                    context.resume_http_request();
+                },
+            );
+            let timeout = Duration::from_secs(1);
+            self.dispatch_grpc_call_with_callback(
+                "upstream",
+                "service",
+                "method",
+                vec![],
+                None,
+                timeout,
+                callback,
+            )
+            .unwrap();
+            Action::Pause
+        }
+    }
+
+    #[test]
+    fn test_grpc_call_callbacks() {
+        let mut harness = testing::Harness::new().with_root_context(|_| Box::new(GrpcRoot));
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream.request_headers(vec![], true);
+        let [call] = testing::take_grpc_calls().try_into().unwrap();
+        testing::set_buffer(BufferType::GrpcReceiveBuffer, Some(b"reply"));
+        with_dispatcher(|dispatcher| dispatcher.on_grpc_receive(call.token_id, 5));
+        assert_eq!(
+            testing::get_map(MapType::HttpRequestHeaders),
+            vec![("x-grpc".to_string(), "reply".to_string())]
+        );
+        assert_eq!(
+            testing::get_resumed_streams(),
+            vec![StreamType::HttpRequest]
+        );
+        with_dispatcher(|dispatcher| assert!(dispatcher.grpc_callbacks.borrow().is_empty()));
+    }
+
+    #[derive(Default)]
+    struct CountingRoot {
+        requests: u32,
+    }
+
+    impl Context for CountingRoot {}
+
+    impl RootContext for CountingRoot {
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(CountingFilter))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct CountingFilter;
+
+    impl Context for CountingFilter {}
+
+    impl HttpContext for CountingFilter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            assert!(self.with_root(|_: &mut Root| ()).is_none());
+            let requests = self.with_root(|root: &mut CountingRoot| {
+                root.requests += 1;
+                root.requests
+            });
+            self.set_http_request_header("x-requests", Some(&requests.unwrap().to_string()));
+            Action::Continue
+        }
+    }
+
+    #[test]
+    fn test_with_root() {
+        let mut harness =
+            testing::Harness::new().with_root_context(|_| Box::<CountingRoot>::default());
+        harness.start();
+        for requests in ["1", "2"] {
+            let mut stream = harness.create_http_stream();
+            stream.request_headers(vec![], true);
+            assert_eq!(
+                testing::get_map_value(MapType::HttpRequestHeaders, "x-requests"),
+                Some(requests.to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Envoy filter state and dynamic metadata.
+//!
+//! Everything here works with stock Envoy, except [`set_dynamic_metadata`]:
+//! Envoy doesn't register a foreign function for it, so it needs a custom host
+//! that provides `set_envoy_dynamic_metadata`.
+
+use crate::hostcalls;
+use crate::protobuf::{self, Value};
+use crate::types::{Bytes, Status};
+use std::collections::BTreeMap;
+
+/// How long Envoy keeps a filter state object, see `StreamInfo::FilterState::LifeSpan`.
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum LifeSpan {
+    FilterChain = 0,
+    DownstreamRequest = 1,
+    DownstreamConnection = 2,
+}
+
+/// Sets a filter state object, e.g. `envoy.tcp_proxy.cluster`.
+///
+/// Envoy only accepts keys that have a registered object factory.
+pub fn set_filter_state(key: &str, value: &str, span: LifeSpan) -> Result<(), Status> {
+    // Encoded as `envoy.source.extensions.common.wasm.SetEnvoyFilterStateArguments`.
+    let mut arguments = Vec::new();
+    protobuf::write_bytes(&mut arguments, 1, key.as_bytes());
+    protobuf::write_bytes(&mut arguments, 2, value.as_bytes());
+    protobuf::write_varint(&mut arguments, 3 << 3);
+    protobuf::write_varint(&mut arguments, span as u64);
+    hostcalls::call_foreign_function("set_envoy_filter_state", Some(&arguments)).map(|_| ())
+}
+
+/// Returns the serialized value of a filter state object.
+pub fn get_filter_state(key: &str) -> Result<Option<Bytes>, Status> {
+    hostcalls::get_property(vec!["filter_state", key])
+}
+
+/// Sets a key of the dynamic metadata in a namespace, e.g. `envoy.lb`.
+///
+/// Only works on a custom host that provides the `set_envoy_dynamic_metadata`
+/// foreign function, which merges an `envoy.config.core.v3.Metadata` message
+/// into the dynamic metadata of the stream. Stock Envoy doesn't, so this fails
+/// with [`Status::NotFound`] there.
+pub fn set_dynamic_metadata(namespace: &str, key: &str, value: Value) -> Result<(), Status> {
+    let fields = BTreeMap::from([(key.to_string(), value)]);
+    let arguments = protobuf::encode_struct_map(&[(namespace, &fields)]);
+    hostcalls::call_foreign_function("set_envoy_dynamic_metadata", Some(&arguments)).map(|_| ())
+}
+
+/// Returns the dynamic metadata in a namespace, as a [`Value::Object`].
+pub fn get_dynamic_metadata(namespace: &str) -> Result<Option<Value>, Status> {
+    let Some(metadata) = hostcalls::get_property(vec!["metadata"])? else {
+        return Ok(None);
+    };
+    Ok(protobuf::decode_struct_map(&metadata)?.remove(namespace))
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_filter_state() {
+        testing::reset();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = calls.clone();
+        testing::set_foreign_function(
+            "set_envoy_filter_state",
+            Box::new(move |arguments| {
+                recorded.borrow_mut().push(arguments.to_vec());
+                Ok(None)
+            }),
+        );
+        set_filter_state("envoy.tcp_proxy.cluster", "egress", LifeSpan::FilterChain).unwrap();
+        let mut expected = vec![0x0a, 0x17];
+        expected.extend(b"envoy.tcp_proxy.cluster");
+        expected.extend([0x12, 0x06]);
+        expected.extend(b"egress");
+        expected.extend([0x18, 0x00]);
+        assert_eq!(calls.take(), vec![expected]);
+
+        testing::set_property(vec!["filter_state", "key"], Some(b"value"));
+        assert_eq!(get_filter_state("key"), Ok(Some(b"value".to_vec())));
+    }
+
+    #[test]
+    fn test_set_dynamic_metadata_without_host_support() {
+        testing::reset();
+        assert_eq!(
+            set_dynamic_metadata("envoy.lb", "canary", true.into()),
+            Err(Status::NotFound)
+        );
+        assert_eq!(get_dynamic_metadata("envoy.lb"), Ok(None));
+    }
+
+    #[test]
+    fn test_dynamic_metadata() {
+        testing::reset();
+        testing::set_foreign_function(
+            "set_envoy_dynamic_metadata",
+            Box::new(|arguments| {
+                testing::set_property(vec!["metadata"], Some(arguments));
+                Ok(None)
+            }),
+        );
+        set_dynamic_metadata("envoy.lb", "canary", true.into()).unwrap();
+        let metadata = get_dynamic_metadata("envoy.lb").unwrap().unwrap();
+        assert_eq!(metadata.get("canary"), Some(&Value::Bool(true)));
+        assert_eq!(get_dynamic_metadata("envoy.filters.http.lua"), Ok(None));
+    }
+}
@@ -0,0 +1,772 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Single-threaded executor driven by host callbacks.
+//!
+//! Futures are polled from within the callbacks that complete them, with the
+//! effective context set to the context that spawned them:
+//!
+//! ```ignore
+//! fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+//!     executor::spawn(async {
+//!         let call = executor::http_call("auth", headers, None, vec![], timeout);
+//!         if let Ok(response) = call.await {
+//!             // ...
+//!         }
+//!     })
+//! }
+//! ```
+//!
+//! Sleeps are only checked on ticks, so their resolution is the tick period
+//! of the root context, which must be set for them to ever complete.
+
+use crate::dispatcher;
+use crate::hostcalls;
+use crate::types::*;
+use hashbrown::HashMap;
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::{Duration, SystemTime};
+
+/// Spawns a future on the current context.
+///
+/// When called from a stream callback, the stream is paused until the future
+/// completes, and the callback should return the resulting [`Action`].
+///
+/// With ABI v0.1.0, which can't resume TCP streams, only HTTP streams are paused.
+pub fn spawn(future: impl Future<Output = ()> + 'static) -> Action {
+    dispatcher::spawn(Box::pin(future))
+}
+
+/// Dispatches an HTTP call, and returns a future for its response.
+pub fn http_call(
+    upstream: &str,
+    headers: Vec<(&str, &str)>,
+    body: Option<&[u8]>,
+    trailers: Vec<(&str, &str)>,
+    timeout: Duration,
+) -> HttpCall {
+    let token = hostcalls::dispatch_http_call(upstream, headers, body, trailers, timeout);
+    if let Ok(token_id) = token {
+        dispatcher::with_executor(|executor| {
+            executor
+                .http_calls
+                .borrow_mut()
+                .insert(token_id, Slot::default())
+        });
+    }
+    HttpCall { token }
+}
+
+/// Dispatches a gRPC call, and returns a future for its response.
+pub fn grpc_call(
+    upstream_name: &str,
+    service_name: &str,
+    method_name: &str,
+    initial_metadata: Vec<(&str, &[u8])>,
+    message: Option<&[u8]>,
+    timeout: Duration,
+) -> GrpcCall {
+    let token = hostcalls::dispatch_grpc_call(
+        upstream_name,
+        service_name,
+        method_name,
+        initial_metadata,
+        message,
+        timeout,
+    );
+    if let Ok(token_id) = token {
+        dispatcher::with_executor(|executor| {
+            executor
+                .grpc_calls
+                .borrow_mut()
+                .insert(token_id, Slot::default())
+        });
+    }
+    GrpcCall { token }
+}
+
+/// Opens a gRPC stream, whose messages are received through [`GrpcStream::message`].
+pub fn open_grpc_stream(
+    cluster_name: &str,
+    service_name: &str,
+    method_name: &str,
+    initial_metadata: Vec<(&str, &[u8])>,
+) -> Result<GrpcStream, Status> {
+    let token_id =
+        hostcalls::open_grpc_stream(cluster_name, service_name, method_name, initial_metadata)?;
+    dispatcher::with_executor(|executor| {
+        executor
+            .grpc_streams
+            .borrow_mut()
+            .insert(token_id, StreamSlot::default())
+    });
+    Ok(GrpcStream { token_id })
+}
+
+/// Returns a future for the next item in a shared queue.
+///
+/// When the queue is ready, pending futures get their items before the
+/// queue's handler or [`RootContext::on_queue_ready`] is called.
+///
+/// [`RootContext::on_queue_ready`]: crate::traits::RootContext::on_queue_ready
+pub fn dequeue_shared_queue(queue_id: u32) -> Dequeue {
+    Dequeue {
+        queue_id,
+        waiter_id: None,
+    }
+}
+
+/// Returns a future that completes on the first tick after `duration` elapses.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: hostcalls::get_current_time().map(|now| now + duration),
+        waiter_id: None,
+    }
+}
+
+pub struct HttpCall {
+    token: Result<u32, Status>,
+}
+
+impl HttpCall {
+    pub fn token_id(&self) -> Result<u32, Status> {
+        self.token
+    }
+}
+
+impl Future for HttpCall {
+    type Output = Result<HttpCallResponse, Status>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        match self.token {
+            Ok(token_id) => dispatcher::with_executor(|executor| {
+                poll_slot(&executor.http_calls, token_id, cx).map(Ok)
+            }),
+            Err(status) => Poll::Ready(Err(status)),
+        }
+    }
+}
+
+impl Drop for HttpCall {
+    fn drop(&mut self) {
+        if let Ok(token_id) = self.token {
+            dispatcher::try_with_executor(|executor| {
+                executor.http_calls.borrow_mut().remove(&token_id)
+            });
+        }
+    }
+}
+
+pub struct GrpcCall {
+    token: Result<u32, Status>,
+}
+
+impl GrpcCall {
+    pub fn token_id(&self) -> Result<u32, Status> {
+        self.token
+    }
+}
+
+impl Future for GrpcCall {
+    type Output = Result<GrpcCallResponse, Status>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        match self.token {
+            Ok(token_id) => dispatcher::with_executor(|executor| {
+                poll_slot(&executor.grpc_calls, token_id, cx).map(Ok)
+            }),
+            Err(status) => Poll::Ready(Err(status)),
+        }
+    }
+}
+
+impl Drop for GrpcCall {
+    fn drop(&mut self) {
+        if let Ok(token_id) = self.token {
+            dispatcher::try_with_executor(|executor| {
+                executor.grpc_calls.borrow_mut().remove(&token_id)
+            });
+        }
+    }
+}
+
+pub struct GrpcStream {
+    token_id: u32,
+}
+
+impl GrpcStream {
+    pub fn token_id(&self) -> u32 {
+        self.token_id
+    }
+
+    pub fn send(&self, message: Option<&[u8]>, end_stream: bool) -> Result<(), Status> {
+        hostcalls::send_grpc_stream_message(self.token_id, message, end_stream)
+    }
+
+    pub fn close(&self) -> Result<(), Status> {
+        hostcalls::close_grpc_stream(self.token_id)
+    }
+
+    pub fn cancel(&self) -> Result<(), Status> {
+        hostcalls::cancel_grpc_stream(self.token_id)
+    }
+
+    /// Returns a future for the next message, or `None` once the stream is closed.
+    pub fn message(&mut self) -> GrpcStreamMessage<'_> {
+        GrpcStreamMessage { stream: self }
+    }
+}
+
+impl Drop for GrpcStream {
+    fn drop(&mut self) {
+        dispatcher::try_with_executor(|executor| {
+            executor.grpc_streams.borrow_mut().remove(&self.token_id)
+        });
+    }
+}
+
+pub struct GrpcStreamMessage<'a> {
+    stream: &'a mut GrpcStream,
+}
+
+impl Future for GrpcStreamMessage<'_> {
+    type Output = Option<Bytes>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        dispatcher::with_executor(|executor| {
+            let mut streams = executor.grpc_streams.borrow_mut();
+            let stream = match streams.get_mut(&self.stream.token_id) {
+                Some(stream) => stream,
+                None => return Poll::Ready(None),
+            };
+            match stream.messages.pop_front() {
+                Some(message) => Poll::Ready(Some(message)),
+                None if stream.closed => Poll::Ready(None),
+                None => {
+                    stream.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+pub struct Dequeue {
+    queue_id: u32,
+    waiter_id: Option<u32>,
+}
+
+impl Future for Dequeue {
+    type Output = Result<Bytes, Status>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match hostcalls::dequeue_shared_queue(this.queue_id) {
+            Ok(Some(value)) => Poll::Ready(Ok(value)),
+            Ok(None) => {
+                dispatcher::with_executor(|executor| {
+                    let waiter_id = *this.waiter_id.get_or_insert_with(|| executor.waiter_id());
+                    executor
+                        .queues
+                        .borrow_mut()
+                        .entry(this.queue_id)
+                        .or_default()
+                        .entry(waiter_id)
+                        .and_modify(|waker| waker.clone_from(cx.waker()))
+                        .or_insert_with(|| cx.waker().clone());
+                });
+                Poll::Pending
+            }
+            Err(status) => Poll::Ready(Err(status)),
+        }
+    }
+}
+
+impl Drop for Dequeue {
+    fn drop(&mut self) {
+        if let Some(waiter_id) = self.waiter_id {
+            dispatcher::try_with_executor(|executor| {
+                let mut queues = executor.queues.borrow_mut();
+                if let Some(wakers) = queues.get_mut(&self.queue_id) {
+                    wakers.remove(&waiter_id);
+                    if wakers.is_empty() {
+                        queues.remove(&self.queue_id);
+                    }
+                }
+            });
+        }
+    }
+}
+
+pub struct Sleep {
+    deadline: Result<SystemTime, Status>,
+    waiter_id: Option<u32>,
+}
+
+impl Future for Sleep {
+    type Output = Result<(), Status>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let deadline = this.deadline?;
+        if hostcalls::get_current_time()? >= deadline {
+            return Poll::Ready(Ok(()));
+        }
+        dispatcher::with_executor(|executor| {
+            let waiter_id = *this.waiter_id.get_or_insert_with(|| executor.waiter_id());
+            executor
+                .sleeps
+                .borrow_mut()
+                .entry(waiter_id)
+                .and_modify(|(_, waker)| waker.clone_from(cx.waker()))
+                .or_insert_with(|| (deadline, cx.waker().clone()));
+        });
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(waiter_id) = self.waiter_id {
+            dispatcher::try_with_executor(|executor| {
+                executor.sleeps.borrow_mut().remove(&waiter_id)
+            });
+        }
+    }
+}
+
+enum Slot<T> {
+    Waiting(Option<Waker>),
+    Done(T),
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Slot::Waiting(None)
+    }
+}
+
+fn poll_slot<T>(
+    slots: &RefCell<HashMap<u32, Slot<T>>>,
+    token_id: u32,
+    cx: &task::Context,
+) -> Poll<T> {
+    let mut slots = slots.borrow_mut();
+    match slots.remove(&token_id) {
+        Some(Slot::Done(value)) => Poll::Ready(value),
+        _ => {
+            slots.insert(token_id, Slot::Waiting(Some(cx.waker().clone())));
+            Poll::Pending
+        }
+    }
+}
+
+// Returns whether the token belongs to the executor.
+fn complete<T>(
+    slots: &RefCell<HashMap<u32, Slot<T>>>,
+    token_id: u32,
+    value: impl FnOnce() -> T,
+) -> bool {
+    let mut slots = slots.borrow_mut();
+    match slots.get_mut(&token_id) {
+        Some(slot) => {
+            if let Slot::Waiting(Some(waker)) = std::mem::replace(slot, Slot::Done(value())) {
+                waker.wake();
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+#[derive(Default)]
+struct StreamSlot {
+    messages: VecDeque<Bytes>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+struct Task {
+    context_id: u32,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    paused: Option<StreamType>,
+}
+
+#[derive(Default)]
+pub(crate) struct Executor {
+    next_task_id: Cell<u32>,
+    tasks: RefCell<HashMap<u32, Task>>,
+    ready: RefCell<VecDeque<u32>>,
+    http_calls: RefCell<HashMap<u32, Slot<HttpCallResponse>>>,
+    grpc_calls: RefCell<HashMap<u32, Slot<GrpcCallResponse>>>,
+    grpc_streams: RefCell<HashMap<u32, StreamSlot>>,
+    // Wakers of pending dequeues and sleeps, keyed by the ID of their future,
+    // so that polling a future again replaces its waker instead of adding one.
+    next_waiter_id: Cell<u32>,
+    queues: RefCell<HashMap<u32, HashMap<u32, Waker>>>,
+    sleeps: RefCell<HashMap<u32, (SystemTime, Waker)>>,
+}
+
+impl Executor {
+    pub(crate) fn spawn(
+        &self,
+        context_id: u32,
+        stream: Option<StreamType>,
+        future: Pin<Box<dyn Future<Output = ()>>>,
+    ) -> Action {
+        #[cfg(proxy_abi = "0.1.0")]
+        let stream = stream
+            .filter(|stream| matches!(stream, StreamType::HttpRequest | StreamType::HttpResponse));
+        let task_id = self.next_task_id.get().wrapping_add(1);
+        self.next_task_id.set(task_id);
+        let task = Task {
+            context_id,
+            future,
+            paused: None,
+        };
+        if self.poll(task_id, task) {
+            return Action::Continue;
+        }
+        if let Some(task) = self.tasks.borrow_mut().get_mut(&task_id) {
+            task.paused = stream;
+        }
+        match stream {
+            Some(_) => Action::Pause,
+            None => Action::Continue,
+        }
+    }
+
+    // Polls woken tasks, calling `enter` with the context of each one first.
+    pub(crate) fn run(&self, enter: impl Fn(u32)) {
+        loop {
+            let task_id = match self.ready.borrow_mut().pop_front() {
+                Some(task_id) => task_id,
+                None => break,
+            };
+            let task = match self.tasks.borrow_mut().remove(&task_id) {
+                Some(task) => task,
+                None => continue,
+            };
+            enter(task.context_id);
+            self.poll(task_id, task);
+        }
+    }
+
+    // Returns whether the task completed.
+    fn poll(&self, task_id: u32, mut task: Task) -> bool {
+        let waker = waker(task_id);
+        let mut cx = task::Context::from_waker(&waker);
+        match task.future.as_mut().poll(&mut cx) {
+            Poll::Ready(()) => {
+                let result = match task.paused {
+                    Some(StreamType::HttpRequest) => hostcalls::resume_http_request(),
+                    Some(StreamType::HttpResponse) => hostcalls::resume_http_response(),
+                    #[cfg(not(proxy_abi = "0.1.0"))]
+                    Some(StreamType::Downstream) => hostcalls::resume_downstream(),
+                    #[cfg(not(proxy_abi = "0.1.0"))]
+                    Some(StreamType::Upstream) => hostcalls::resume_upstream(),
+                    _ => Ok(()),
+                };
+                if let Err(status) = result {
+                    warn!("failed to resume {:?}: {:?}", task.paused, status);
+                }
+                true
+            }
+            Poll::Pending => {
+                self.tasks.borrow_mut().insert(task_id, task);
+                false
+            }
+        }
+    }
+
+    fn waiter_id(&self) -> u32 {
+        let waiter_id = self.next_waiter_id.get().wrapping_add(1);
+        self.next_waiter_id.set(waiter_id);
+        waiter_id
+    }
+
+    fn wake(&self, task_id: u32) {
+        self.ready.borrow_mut().push_back(task_id);
+    }
+
+    pub(crate) fn cancel(&self, context_id: u32) {
+        // Futures are dropped after releasing the tasks, since dropping them can touch the executor.
+        let cancelled = self
+            .tasks
+            .borrow_mut()
+            .extract_if(|_, task| task.context_id == context_id)
+            .collect::<Vec<_>>();
+        drop(cancelled);
+    }
+
+    pub(crate) fn on_http_call_response(
+        &self,
+        token_id: u32,
+        num_headers: usize,
+        body_size: usize,
+        num_trailers: usize,
+    ) -> bool {
+        complete(&self.http_calls, token_id, || {
+            dispatcher::read_http_call_response(num_headers, body_size, num_trailers)
+        })
+    }
+
+    pub(crate) fn on_grpc_call_response(
+        &self,
+        token_id: u32,
+        status_code: u32,
+        response_size: usize,
+    ) -> bool {
+        complete(&self.grpc_calls, token_id, || {
+            dispatcher::read_grpc_call_response(status_code, response_size)
+        })
+    }
+
+    pub(crate) fn on_grpc_stream_message(&self, token_id: u32, message_size: usize) -> bool {
+        let mut streams = self.grpc_streams.borrow_mut();
+        match streams.get_mut(&token_id) {
+            Some(stream) => {
+                let message = dispatcher::read_grpc_stream_message(message_size);
+                stream.messages.push_back(message);
+                if let Some(waker) = stream.waker.take() {
+                    waker.wake();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn on_grpc_stream_close(&self, token_id: u32) -> bool {
+        let mut streams = self.grpc_streams.borrow_mut();
+        match streams.get_mut(&token_id) {
+            Some(stream) => {
+                stream.closed = true;
+                if let Some(waker) = stream.waker.take() {
+                    waker.wake();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn on_queue_ready(&self, queue_id: u32) -> bool {
+        match self.queues.borrow_mut().remove(&queue_id) {
+            Some(wakers) => {
+                wakers.into_values().for_each(Waker::wake);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn on_tick(&self) {
+        if self.sleeps.borrow().is_empty() {
+            return;
+        }
+        let now = match hostcalls::get_current_time() {
+            Ok(now) => now,
+            Err(_) => return,
+        };
+        let mut expired = Vec::new();
+        self.sleeps.borrow_mut().retain(|_, (deadline, waker)| {
+            if *deadline > now {
+                return true;
+            }
+            expired.push(waker.clone());
+            false
+        });
+        expired.into_iter().for_each(Waker::wake);
+    }
+}
+
+// Wakers only carry the task ID, and push it onto the ready queue of the executor.
+fn waker(task_id: u32) -> Waker {
+    unsafe { Waker::from_raw(raw_waker(task_id as usize as *const ())) }
+}
+
+fn raw_waker(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(
+    raw_waker,
+    |data| wake(data as usize as u32),
+    |data| wake(data as usize as u32),
+    |_| {},
+);
+
+fn wake(task_id: u32) {
+    dispatcher::try_with_executor(|executor| executor.wake(task_id));
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Harness};
+    use crate::traits::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    struct Root;
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn on_configure(&mut self, _: usize) -> bool {
+            let queue_id = self.register_shared_queue("items");
+            spawn(async move {
+                sleep(Duration::from_secs(5)).await.unwrap();
+                hostcalls::set_shared_data("slept", Some(b"true"), None).unwrap();
+                let item = dequeue_shared_queue(queue_id).await.unwrap();
+                hostcalls::set_shared_data("item", Some(&item), None).unwrap();
+            });
+            true
+        }
+
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(Filter))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct Filter;
+
+    impl Context for Filter {}
+
+    impl HttpContext for Filter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            spawn(async {
+                let call = http_call("auth", vec![], None, vec![], Duration::from_secs(1));
+                let body = call.await.unwrap().body.unwrap();
+                let body = String::from_utf8(body).unwrap();
+                hostcalls::add_map_value(MapType::HttpRequestHeaders, "x-auth", &body).unwrap();
+            })
+        }
+    }
+
+    fn harness() -> Harness {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Root));
+        testing::set_current_time(SystemTime::UNIX_EPOCH);
+        harness.start();
+        harness
+    }
+
+    #[test]
+    fn test_http_call() {
+        let mut harness = harness();
+        let mut stream = harness.create_http_stream();
+        stream
+            .request_headers(vec![(":path", "/")], true)
+            .expect_action(Action::Pause);
+        let call = testing::take_http_calls().pop().unwrap();
+        harness.http_call_response(call.token_id, vec![], Some(b"ok"), vec![]);
+        assert_eq!(
+            testing::get_map_value(MapType::HttpRequestHeaders, "x-auth"),
+            Some("ok".to_string())
+        );
+        assert_eq!(
+            testing::get_resumed_streams(),
+            vec![StreamType::HttpRequest]
+        );
+    }
+
+    #[test]
+    fn test_sleep_and_dequeue() {
+        let mut harness = harness();
+        harness.tick();
+        assert_eq!(testing::get_shared_data("slept").0, None);
+        testing::set_current_time(SystemTime::UNIX_EPOCH + Duration::from_secs(5));
+        harness.tick();
+        assert_eq!(testing::get_shared_data("slept").0, Some(b"true".to_vec()));
+        let queue_id = testing::register_shared_queue("", "items");
+        testing::enqueue_shared_queue(queue_id, b"hello").unwrap();
+        harness.queue_ready(queue_id);
+        assert_eq!(testing::get_shared_data("item").0, Some(b"hello".to_vec()));
+    }
+
+    struct Consumers;
+
+    impl Context for Consumers {}
+
+    impl RootContext for Consumers {
+        fn on_configure(&mut self, _: usize) -> bool {
+            let queue_id = self.register_shared_queue("items");
+            spawn(async move {
+                let item = dequeue_shared_queue(queue_id).await.unwrap();
+                hostcalls::set_shared_data("awaited", Some(&item), None).unwrap();
+            });
+            true
+        }
+
+        fn on_queue_ready(&mut self, queue_id: u32) {
+            let item = self.dequeue_shared_queue(queue_id).unwrap().unwrap();
+            hostcalls::set_shared_data("delivered", Some(&item), None).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_dequeue_and_on_queue_ready() {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Consumers));
+        harness.start();
+        let queue_id = testing::register_shared_queue("", "items");
+        testing::enqueue_shared_queue(queue_id, b"first").unwrap();
+        testing::enqueue_shared_queue(queue_id, b"second").unwrap();
+        harness.queue_ready(queue_id);
+        assert_eq!(
+            testing::get_shared_data("awaited").0,
+            Some(b"first".to_vec())
+        );
+        assert_eq!(
+            testing::get_shared_data("delivered").0,
+            Some(b"second".to_vec())
+        );
+    }
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_sleep_woken_once() {
+        testing::reset();
+        testing::set_current_time(SystemTime::UNIX_EPOCH);
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = task::Context::from_waker(&waker);
+        let mut sleep = sleep(Duration::from_secs(5));
+        for _ in 0..3 {
+            assert!(Pin::new(&mut sleep).poll(&mut cx).is_pending());
+        }
+        testing::set_current_time(SystemTime::UNIX_EPOCH + Duration::from_secs(5));
+        dispatcher::with_executor(|executor| executor.on_tick());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(Pin::new(&mut sleep).poll(&mut cx), Poll::Ready(Ok(())));
+    }
+}
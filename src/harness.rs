@@ -0,0 +1,513 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::dispatcher::{self, Dispatcher};
+use crate::testing::{self, Phase};
+use crate::types::*;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+thread_local! {
+static NEXT_CONTEXT_ID: Cell<u32> = const { Cell::new(1) };
+}
+
+fn next_context_id() -> u32 {
+    NEXT_CONTEXT_ID.with(|next| {
+        let context_id = next.get();
+        next.set(context_id + 1);
+        context_id
+    })
+}
+
+struct PhaseGuard;
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        testing::set_phase(None);
+    }
+}
+
+// Calls into the dispatcher directly (rather than through the exported
+// `proxy_on_*` functions), so that panics raised by the plugin unwind into
+// the test instead of aborting at the `extern "C"` boundary. Violations take
+// precedence over such panics, since a rejected hostcall usually causes one.
+fn run<T>(phase: Phase, f: impl FnOnce(&Dispatcher) -> T) -> T {
+    let result = {
+        let _guard = PhaseGuard;
+        testing::set_phase(Some(phase));
+        panic::catch_unwind(AssertUnwindSafe(|| dispatcher::with_dispatcher(f)))
+    };
+    let violations = testing::take_violations();
+    if !violations.is_empty() {
+        panic!(
+            "plugin made hostcalls that are invalid in the current phase:\n  {}",
+            violations.join("\n  ")
+        );
+    }
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Drives the plugin registered in the current thread through the lifecycle
+/// of a root context, backed by the in-memory host from [`testing`].
+///
+/// Each step records its return value, which can be asserted with the
+/// `expect_*` methods:
+///
+/// ```ignore
+/// let mut harness = Harness::new()
+///     .with_root_context(|_| Box::new(MyRoot))
+///     .with_plugin_configuration(b"{}");
+/// harness.start();
+/// harness
+///     .create_http_stream()
+///     .request_headers(vec![(":path", "/")], false)
+///     .expect_action(Action::Continue);
+/// ```
+///
+/// Hostcalls that the host wouldn't allow in the callback currently being
+/// executed (e.g. modifying request headers from `on_http_response_body`)
+/// fail the test.
+pub struct Harness {
+    root_context_id: Option<u32>,
+    last_result: Option<bool>,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    pub fn new() -> Harness {
+        testing::reset();
+        Harness {
+            root_context_id: None,
+            last_result: None,
+        }
+    }
+
+    pub fn with_root_context(self, callback: NewRootContext) -> Harness {
+        crate::set_root_context(callback);
+        self
+    }
+
+    pub fn with_stream_context(self, callback: NewStreamContext) -> Harness {
+        crate::set_stream_context(callback);
+        self
+    }
+
+    pub fn with_http_context(self, callback: NewHttpContext) -> Harness {
+        crate::set_http_context(callback);
+        self
+    }
+
+    pub fn with_vm_configuration(self, configuration: &[u8]) -> Harness {
+        testing::set_buffer(BufferType::VmConfiguration, Some(configuration));
+        self
+    }
+
+    pub fn with_plugin_configuration(self, configuration: &[u8]) -> Harness {
+        testing::set_buffer(BufferType::PluginConfiguration, Some(configuration));
+        self
+    }
+
+    pub fn root_context_id(&self) -> u32 {
+        self.root_context_id.expect("root context not created")
+    }
+
+    pub fn create_root_context(&mut self) -> &mut Self {
+        let context_id = next_context_id();
+        dispatcher::with_dispatcher(|dispatcher| dispatcher.on_create_context(context_id, 0));
+        self.root_context_id = Some(context_id);
+        self
+    }
+
+    pub fn vm_start(&mut self) -> &mut Self {
+        let root_context_id = self.root_context_id();
+        let size = testing::get_buffer(BufferType::VmConfiguration).map_or(0, |c| c.len());
+        self.last_result = Some(run(Phase::VmStart, |dispatcher| {
+            dispatcher.on_vm_start(root_context_id, size)
+        }));
+        self
+    }
+
+    pub fn configure(&mut self) -> &mut Self {
+        let root_context_id = self.root_context_id();
+        let size = testing::get_buffer(BufferType::PluginConfiguration).map_or(0, |c| c.len());
+        self.last_result = Some(run(Phase::Configure, |dispatcher| {
+            dispatcher.on_configure(root_context_id, size)
+        }));
+        self
+    }
+
+    /// Creates the root context and runs `on_vm_start` and `on_configure`,
+    /// expecting both to succeed.
+    pub fn start(&mut self) -> &mut Self {
+        self.create_root_context()
+            .vm_start()
+            .expect_result(true)
+            .configure()
+            .expect_result(true)
+    }
+
+    pub fn tick(&mut self) -> &mut Self {
+        let root_context_id = self.root_context_id();
+        run(Phase::Tick, |dispatcher| {
+            dispatcher.on_tick(root_context_id)
+        });
+        self
+    }
+
+    pub fn queue_ready(&mut self, queue_id: u32) -> &mut Self {
+        let root_context_id = self.root_context_id();
+        run(Phase::QueueReady, |dispatcher| {
+            dispatcher.on_queue_ready(root_context_id, queue_id)
+        });
+        self
+    }
+
+    /// Delivers the response to the HTTP callout identified by `token_id`
+    /// to the context that dispatched it.
+    pub fn http_call_response(
+        &mut self,
+        token_id: u32,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+    ) -> &mut Self {
+        let (num_headers, body_size, num_trailers) =
+            (headers.len(), body.map_or(0, |b| b.len()), trailers.len());
+        testing::set_map(MapType::HttpCallResponseHeaders, headers);
+        testing::set_buffer(BufferType::HttpCallResponseBody, body);
+        testing::set_map(MapType::HttpCallResponseTrailers, trailers);
+        run(Phase::HttpCallResponse, |dispatcher| {
+            dispatcher.on_http_call_response(token_id, num_headers, body_size, num_trailers)
+        });
+        self
+    }
+
+    pub fn expect_result(&mut self, expected: bool) -> &mut Self {
+        assert_eq!(self.last_result, Some(expected), "unexpected return value");
+        self
+    }
+
+    pub fn create_http_stream(&mut self) -> HttpStream {
+        let root_context_id = self.root_context_id();
+        let context_id = next_context_id();
+        dispatcher::with_dispatcher(|dispatcher| {
+            dispatcher.on_create_context(context_id, root_context_id)
+        });
+        HttpStream {
+            context_id,
+            last_action: None,
+            request_body_paused: false,
+            response_body_paused: false,
+        }
+    }
+}
+
+/// HTTP stream created by [`Harness::create_http_stream`].
+pub struct HttpStream {
+    context_id: u32,
+    last_action: Option<Action>,
+    request_body_paused: bool,
+    response_body_paused: bool,
+}
+
+impl HttpStream {
+    pub fn context_id(&self) -> u32 {
+        self.context_id
+    }
+
+    pub fn last_action(&self) -> Option<Action> {
+        self.last_action
+    }
+
+    pub fn expect_action(&mut self, expected: Action) -> &mut Self {
+        assert_eq!(self.last_action, Some(expected), "unexpected action");
+        self
+    }
+
+    pub fn request_headers(
+        &mut self,
+        headers: Vec<(&str, &str)>,
+        end_of_stream: bool,
+    ) -> &mut Self {
+        let context_id = self.context_id;
+        let num_headers = headers.len();
+        testing::set_map(MapType::HttpRequestHeaders, headers);
+        self.last_action = Some(run(Phase::RequestHeaders, |dispatcher| {
+            dispatcher.on_http_request_headers(context_id, num_headers, end_of_stream)
+        }));
+        self
+    }
+
+    /// Delivers a chunk of the request body. As in Envoy, if the previous
+    /// chunk was paused, the new chunk is appended to the buffered data.
+    pub fn request_body(&mut self, chunk: &[u8], end_of_stream: bool) -> &mut Self {
+        let context_id = self.context_id;
+        let body_size = buffer_chunk(BufferType::HttpRequestBody, chunk, self.request_body_paused);
+        let action = run(Phase::RequestBody, |dispatcher| {
+            dispatcher.on_http_request_body(context_id, body_size, end_of_stream)
+        });
+        self.request_body_paused = action == Action::Pause;
+        self.last_action = Some(action);
+        self
+    }
+
+    pub fn request_trailers(&mut self, trailers: Vec<(&str, &str)>) -> &mut Self {
+        let context_id = self.context_id;
+        let num_trailers = trailers.len();
+        testing::set_map(MapType::HttpRequestTrailers, trailers);
+        self.last_action = Some(run(Phase::RequestTrailers, |dispatcher| {
+            dispatcher.on_http_request_trailers(context_id, num_trailers)
+        }));
+        self
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    pub fn request_metadata(&mut self, metadata: Vec<(&str, &str)>) -> &mut Self {
+        let context_id = self.context_id;
+        let num_elements = metadata.len();
+        testing::set_map(MapType::HttpRequestMetadata, metadata);
+        self.last_action = Some(run(Phase::RequestMetadata, |dispatcher| {
+            dispatcher.on_http_request_metadata(context_id, num_elements)
+        }));
+        self
+    }
+
+    pub fn response_headers(
+        &mut self,
+        headers: Vec<(&str, &str)>,
+        end_of_stream: bool,
+    ) -> &mut Self {
+        let context_id = self.context_id;
+        let num_headers = headers.len();
+        testing::set_map(MapType::HttpResponseHeaders, headers);
+        self.last_action = Some(run(Phase::ResponseHeaders, |dispatcher| {
+            dispatcher.on_http_response_headers(context_id, num_headers, end_of_stream)
+        }));
+        self
+    }
+
+    /// Delivers a chunk of the response body. As in Envoy, if the previous
+    /// chunk was paused, the new chunk is appended to the buffered data.
+    pub fn response_body(&mut self, chunk: &[u8], end_of_stream: bool) -> &mut Self {
+        let context_id = self.context_id;
+        let body_size = buffer_chunk(
+            BufferType::HttpResponseBody,
+            chunk,
+            self.response_body_paused,
+        );
+        let action = run(Phase::ResponseBody, |dispatcher| {
+            dispatcher.on_http_response_body(context_id, body_size, end_of_stream)
+        });
+        self.response_body_paused = action == Action::Pause;
+        self.last_action = Some(action);
+        self
+    }
+
+    pub fn response_trailers(&mut self, trailers: Vec<(&str, &str)>) -> &mut Self {
+        let context_id = self.context_id;
+        let num_trailers = trailers.len();
+        testing::set_map(MapType::HttpResponseTrailers, trailers);
+        self.last_action = Some(run(Phase::ResponseTrailers, |dispatcher| {
+            dispatcher.on_http_response_trailers(context_id, num_trailers)
+        }));
+        self
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    pub fn response_metadata(&mut self, metadata: Vec<(&str, &str)>) -> &mut Self {
+        let context_id = self.context_id;
+        let num_elements = metadata.len();
+        testing::set_map(MapType::HttpResponseMetadata, metadata);
+        self.last_action = Some(run(Phase::ResponseMetadata, |dispatcher| {
+            dispatcher.on_http_response_metadata(context_id, num_elements)
+        }));
+        self
+    }
+
+    /// Finishes the stream, calling `on_done`, `on_log` and `on_delete`
+    /// in the same order as the host.
+    pub fn finish(&mut self) {
+        let context_id = self.context_id;
+        run(Phase::Done, |dispatcher| dispatcher.on_done(context_id));
+        run(Phase::Log, |dispatcher| dispatcher.on_log(context_id));
+        dispatcher::with_dispatcher(|dispatcher| dispatcher.on_delete(context_id));
+    }
+}
+
+fn buffer_chunk(buffer_type: BufferType, chunk: &[u8], append: bool) -> usize {
+    let mut buffer = match append {
+        true => testing::get_buffer(buffer_type).unwrap_or_default(),
+        false => Vec::new(),
+    };
+    buffer.extend_from_slice(chunk);
+    testing::set_buffer(buffer_type, Some(&buffer));
+    buffer.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::*;
+    use std::time::Duration;
+
+    struct Root;
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn on_configure(&mut self, _: usize) -> bool {
+            self.get_plugin_configuration().as_deref() == Some(b"valid")
+        }
+
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(Filter))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct Filter;
+
+    impl Context for Filter {
+        fn on_http_call_response(&mut self, _: u32, _: usize, _: usize, _: usize) {
+            let status = self.get_http_call_response_header(":status");
+            self.add_http_request_header("x-auth", &status.unwrap());
+            self.resume_http_request();
+        }
+    }
+
+    impl HttpContext for Filter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            if self.get_http_request_header(":path").as_deref() == Some("/auth") {
+                self.dispatch_http_call("auth", vec![], None, vec![], Duration::from_secs(1))
+                    .unwrap();
+                return Action::Pause;
+            }
+            Action::Continue
+        }
+
+        fn on_http_request_body(&mut self, body_size: usize, end_of_stream: bool) -> Action {
+            if !end_of_stream {
+                return Action::Pause;
+            }
+            let body = self.get_http_request_body(0, body_size).unwrap();
+            self.set_http_request_body(0, body_size, &body.to_ascii_uppercase());
+            Action::Continue
+        }
+
+        fn on_http_response_body(&mut self, _: usize, _: bool) -> Action {
+            self.set_http_request_header("x-late", Some("true"));
+            Action::Continue
+        }
+
+        #[cfg(proxy_abi = "vnext")]
+        fn on_http_request_metadata(&mut self, _: usize) -> Action {
+            let tenant = self.get_http_request_metadata_value("x-tenant");
+            self.set_http_request_metadata_value("x-seen", tenant.as_deref());
+            Action::Pause
+        }
+    }
+
+    fn harness(configuration: &[u8]) -> Harness {
+        Harness::new()
+            .with_root_context(|_| Box::new(Root))
+            .with_plugin_configuration(configuration)
+    }
+
+    #[test]
+    fn test_configure() {
+        harness(b"invalid")
+            .create_root_context()
+            .vm_start()
+            .expect_result(true)
+            .configure()
+            .expect_result(false);
+    }
+
+    #[test]
+    fn test_http_stream() {
+        let mut harness = harness(b"valid");
+        harness.start();
+        harness
+            .create_http_stream()
+            .request_headers(vec![(":path", "/")], false)
+            .expect_action(Action::Continue)
+            .request_body(b"hello ", false)
+            .expect_action(Action::Pause)
+            .request_body(b"world", true)
+            .expect_action(Action::Continue)
+            .finish();
+        assert_eq!(
+            testing::get_buffer(BufferType::HttpRequestBody),
+            Some(b"HELLO WORLD".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_http_call_response() {
+        let mut harness = harness(b"valid");
+        harness.start();
+        let mut stream = harness.create_http_stream();
+        stream
+            .request_headers(vec![(":path", "/auth")], true)
+            .expect_action(Action::Pause);
+        let call = testing::take_http_calls().pop().unwrap();
+        harness.http_call_response(call.token_id, vec![(":status", "200")], None, vec![]);
+        assert_eq!(
+            testing::get_map_value(MapType::HttpRequestHeaders, "x-auth"),
+            Some("200".to_string())
+        );
+        assert_eq!(
+            testing::get_resumed_streams(),
+            vec![StreamType::HttpRequest]
+        );
+    }
+
+    #[test]
+    #[cfg(proxy_abi = "vnext")]
+    fn test_http_request_metadata() {
+        let mut harness = harness(b"valid");
+        harness.start();
+        harness
+            .create_http_stream()
+            .request_headers(vec![(":path", "/")], false)
+            .request_metadata(vec![("x-tenant", "acme")])
+            .expect_action(Action::Pause);
+        assert_eq!(
+            testing::get_map_value(MapType::HttpRequestMetadata, "x-seen"),
+            Some("acme".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "proxy_replace_header_map_value(HttpRequestHeaders) is not allowed in on_http_response_body"
+    )]
+    fn test_invalid_phase() {
+        let mut harness = harness(b"valid");
+        harness.start();
+        harness
+            .create_http_stream()
+            .request_headers(vec![(":path", "/")], true)
+            .response_headers(vec![(":status", "200")], false)
+            .response_body(b"body", true);
+    }
+}
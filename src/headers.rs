@@ -0,0 +1,284 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Header maps with repeated headers and case-insensitive names.
+
+use crate::hostcalls::utils;
+use crate::types::{Bytes, Status};
+
+/// Ordered list of headers, including pseudo-headers like `:path`.
+///
+/// Names are compared case-insensitively, and can be repeated. Methods that
+/// take a name or a value also accept the `http` crate's `HeaderName` and
+/// `HeaderValue`. Methods returning `&str` skip values that aren't valid UTF-8,
+/// which can be read with their `_bytes` variants.
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct HeaderMap {
+    entries: Vec<(String, Bytes)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    /// Parses a map serialized by the host.
+    pub fn from_bytes(bytes: &[u8]) -> Result<HeaderMap, Status> {
+        utils::deserialize_map_bytes(bytes).map(HeaderMap::from)
+    }
+
+    /// Serializes the map like the host does.
+    pub fn to_bytes(&self) -> Bytes {
+        utils::serialize_map_bytes(&self.to_vec())
+    }
+
+    /// Returns the headers in the format of `set_http_request_headers_bytes`.
+    pub fn to_vec(&self) -> Vec<(&str, &[u8])> {
+        self.iter().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_slice()))
+    }
+
+    pub fn contains_key(&self, name: impl AsRef<str>) -> bool {
+        self.get_all_bytes(name).next().is_some()
+    }
+
+    /// Returns the first value of the header.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.get_bytes(name)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    pub fn get_bytes(&self, name: impl AsRef<str>) -> Option<&[u8]> {
+        self.get_all_bytes(name).next()
+    }
+
+    pub fn get_all(&self, name: impl AsRef<str>) -> impl Iterator<Item = &str> {
+        self.get_all_bytes(name)
+            .filter_map(|value| std::str::from_utf8(value).ok())
+    }
+
+    pub fn get_all_bytes(&self, name: impl AsRef<str>) -> impl Iterator<Item = &[u8]> {
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name.as_ref()))
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Replaces all values of the header with `value`, in the position of the
+    /// first one.
+    pub fn insert(&mut self, name: impl AsRef<str>, value: impl AsRef<[u8]>) {
+        let name = name.as_ref();
+        let value = value.as_ref().to_vec();
+        match self.position(name) {
+            Some(index) => {
+                self.entries[index].1 = value;
+                let mut n = 0;
+                self.entries.retain(|(key, _)| {
+                    n += 1;
+                    n <= index + 1 || !key.eq_ignore_ascii_case(name)
+                });
+            }
+            None => self.entries.push((name.to_string(), value)),
+        }
+    }
+
+    /// Adds a value, keeping the existing ones.
+    pub fn append(&mut self, name: impl AsRef<str>, value: impl AsRef<[u8]>) {
+        self.entries
+            .push((name.as_ref().to_string(), value.as_ref().to_vec()));
+    }
+
+    /// Removes all values of the header, and returns the first one.
+    pub fn remove(&mut self, name: impl AsRef<str>) -> Option<Bytes> {
+        let name = name.as_ref();
+        let index = self.position(name)?;
+        let (_, value) = self.entries.remove(index);
+        self.entries
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        Some(value)
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &[u8]) -> bool) {
+        self.entries.retain(|(name, value)| f(name, value));
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        self.get(":method")
+    }
+
+    pub fn set_method(&mut self, method: impl AsRef<[u8]>) {
+        self.insert(":method", method)
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.get(":path")
+    }
+
+    pub fn set_path(&mut self, path: impl AsRef<[u8]>) {
+        self.insert(":path", path)
+    }
+
+    pub fn authority(&self) -> Option<&str> {
+        self.get(":authority")
+    }
+
+    pub fn set_authority(&mut self, authority: impl AsRef<[u8]>) {
+        self.insert(":authority", authority)
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.get(":scheme")
+    }
+
+    pub fn set_scheme(&mut self, scheme: impl AsRef<[u8]>) {
+        self.insert(":scheme", scheme)
+    }
+
+    /// Returns the headers that aren't pseudo-headers.
+    pub fn regular(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.iter().filter(|(name, _)| !name.starts_with(':'))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+}
+
+impl From<Vec<(String, Bytes)>> for HeaderMap {
+    fn from(entries: Vec<(String, Bytes)>) -> HeaderMap {
+        HeaderMap { entries }
+    }
+}
+
+impl From<Vec<(String, String)>> for HeaderMap {
+    fn from(entries: Vec<(String, String)>) -> HeaderMap {
+        entries.into_iter().collect()
+    }
+}
+
+impl<N: AsRef<str>, V: AsRef<[u8]>> FromIterator<(N, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<N: AsRef<str>, V: AsRef<[u8]>> Extend<(N, V)> for HeaderMap {
+    fn extend<I: IntoIterator<Item = (N, V)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.append(name, value);
+        }
+    }
+}
+
+impl From<HeaderMap> for Vec<(String, Bytes)> {
+    fn from(map: HeaderMap) -> Vec<(String, Bytes)> {
+        map.entries
+    }
+}
+
+// Pseudo-headers aren't valid names in the `http` crate, so they're left out.
+#[cfg(feature = "http")]
+impl TryFrom<&HeaderMap> for http::HeaderMap {
+    type Error = Status;
+
+    fn try_from(map: &HeaderMap) -> Result<http::HeaderMap, Status> {
+        let mut headers = http::HeaderMap::with_capacity(map.len());
+        for (name, value) in map.regular() {
+            let name =
+                http::HeaderName::from_bytes(name.as_bytes()).map_err(|_| Status::ParseFailure)?;
+            let value = http::HeaderValue::from_bytes(value).map_err(|_| Status::ParseFailure)?;
+            headers.append(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<&http::HeaderMap> for HeaderMap {
+    fn from(headers: &http::HeaderMap) -> HeaderMap {
+        headers.iter().collect()
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_map() {
+        let mut map = HeaderMap::from_iter([
+            (":method", "GET"),
+            (":path", "/"),
+            ("Set-Cookie", "a=1"),
+            ("x-id", "1"),
+            ("set-cookie", "b=2"),
+        ]);
+        assert_eq!(map.method(), Some("GET"));
+        assert_eq!(map.get("x-ID"), Some("1"));
+        assert_eq!(
+            map.get_all("set-cookie").collect::<Vec<_>>(),
+            vec!["a=1", "b=2"]
+        );
+
+        map.set_path("/index.html");
+        map.insert("SET-COOKIE", "c=3");
+        map.append("x-id", b"\xff");
+        assert_eq!(map.get_all("x-id").collect::<Vec<_>>(), vec!["1"]);
+        assert_eq!(map.remove("x-id"), Some(b"1".to_vec()));
+        assert!(!map.contains_key("x-id"));
+        map.retain(|name, _| name != ":method");
+        assert_eq!(
+            map.to_vec(),
+            vec![(":path", &b"/index.html"[..]), ("Set-Cookie", &b"c=3"[..])]
+        );
+        assert_eq!(HeaderMap::from_bytes(&map.to_bytes()), Ok(map));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http() {
+        let map = HeaderMap::from_iter([(":scheme", "https"), ("accept", "a"), ("Accept", "b")]);
+        let headers = http::HeaderMap::try_from(&map).unwrap();
+        assert_eq!(
+            headers
+                .get_all(http::header::ACCEPT)
+                .iter()
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        let map = HeaderMap::from(&headers);
+        assert_eq!(map.get(http::header::ACCEPT), Some("a"));
+        assert_eq!(map.len(), 2);
+
+        let map = HeaderMap::from_iter([("bad name", "value")]);
+        assert_eq!(http::HeaderMap::try_from(&map), Err(Status::ParseFailure));
+    }
+}
@@ -17,33 +17,47 @@ use crate::types::*;
 use std::ptr::{null, null_mut};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+#[cfg(feature = "trace")]
+use crate::trace::hostcall as traced;
+
+#[cfg(not(feature = "trace"))]
+#[inline(always)]
+fn traced<A, R>(_name: &str, _args: A, call: impl FnOnce() -> R) -> R {
+    call()
+}
+
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
     fn proxy_log(level: LogLevel, message_data: *const u8, message_size: usize) -> Status;
 }
 
 pub fn log(level: LogLevel, message: &str) -> Result<(), Status> {
-    unsafe {
+    traced("log", (level, message), || unsafe {
         match proxy_log(level, message.as_ptr(), message.len()) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+// Only available in ABI v0.2.1.
+#[cfg(not(any(proxy_abi = "0.1.0", proxy_abi = "0.2.0")))]
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
     fn proxy_get_log_level(return_level: *mut LogLevel) -> Status;
 }
 
+#[cfg(not(any(proxy_abi = "0.1.0", proxy_abi = "0.2.0")))]
 pub fn get_log_level() -> Result<LogLevel, Status> {
-    let mut return_level: LogLevel = LogLevel::Trace;
-    unsafe {
-        match proxy_get_log_level(&mut return_level) {
-            Status::Ok => Ok(return_level),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("get_log_level", (), || {
+        let mut return_level: LogLevel = LogLevel::Trace;
+        unsafe {
+            match proxy_get_log_level(&mut return_level) {
+                Status::Ok => Ok(return_level),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -52,13 +66,15 @@ unsafe extern "C" {
 }
 
 pub fn get_current_time() -> Result<SystemTime, Status> {
-    let mut return_time: u64 = 0;
-    unsafe {
-        match proxy_get_current_time_nanoseconds(&mut return_time) {
-            Status::Ok => Ok(UNIX_EPOCH + Duration::from_nanos(return_time)),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("get_current_time", (), || {
+        let mut return_time: u64 = 0;
+        unsafe {
+            match proxy_get_current_time_nanoseconds(&mut return_time) {
+                Status::Ok => Ok(UNIX_EPOCH + Duration::from_nanos(return_time)),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -67,16 +83,19 @@ unsafe extern "C" {
 }
 
 pub fn set_tick_period(period: Duration) -> Result<(), Status> {
-    unsafe {
+    traced("set_tick_period", period, || unsafe {
         match proxy_set_tick_period_milliseconds(period.as_millis() as u32) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    // The next ABI version replaces the `*_buffer_bytes` hostcalls with unified ones,
+    // and likewise renames the `*_header_map_*` hostcalls below.
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_get_buffer")]
     fn proxy_get_buffer_bytes(
         buffer_type: BufferType,
         start: usize,
@@ -91,6 +110,50 @@ pub fn get_buffer(
     start: usize,
     max_size: usize,
 ) -> Result<Option<Bytes>, Status> {
+    traced("get_buffer", (buffer_type, start, max_size), || {
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_get_buffer_bytes(
+                buffer_type,
+                start,
+                max_size,
+                &mut return_data,
+                &mut return_size,
+            ) {
+                Status::Ok => {
+                    if !return_data.is_null() {
+                        Ok(Some(Vec::from_raw_parts(
+                            return_data,
+                            return_size,
+                            return_size,
+                        )))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Status::NotFound => Ok(None),
+                status => Err(status),
+            }
+        }
+    })
+}
+
+// Like `get_buffer`, but copies the bytes into `buffer`, reusing its allocation,
+// and frees the host's allocation right away.
+pub(crate) fn get_buffer_into(
+    buffer_type: BufferType,
+    start: usize,
+    max_size: usize,
+    buffer: &mut Bytes,
+) -> Result<(), Status> {
+    // Traces record the bytes returned by the host, which needs owned ones.
+    if cfg!(feature = "trace") {
+        let bytes = get_buffer(buffer_type, start, max_size)?;
+        buffer.clear();
+        buffer.extend_from_slice(bytes.as_deref().unwrap_or_default());
+        return Ok(());
+    }
     let mut return_data: *mut u8 = null_mut();
     let mut return_size: usize = 0;
     unsafe {
@@ -102,24 +165,25 @@ pub fn get_buffer(
             &mut return_size,
         ) {
             Status::Ok => {
+                buffer.clear();
                 if !return_data.is_null() {
-                    Ok(Some(Vec::from_raw_parts(
-                        return_data,
-                        return_size,
-                        return_size,
-                    )))
-                } else {
-                    Ok(None)
+                    let data = Vec::from_raw_parts(return_data, return_size, return_size);
+                    buffer.extend_from_slice(&data);
                 }
+                Ok(())
+            }
+            Status::NotFound => {
+                buffer.clear();
+                Ok(())
             }
-            Status::NotFound => Ok(None),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
     }
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_set_buffer")]
     fn proxy_set_buffer_bytes(
         buffer_type: BufferType,
         start: usize,
@@ -135,17 +199,18 @@ pub fn set_buffer(
     size: usize,
     value: &[u8],
 ) -> Result<(), Status> {
-    unsafe {
+    traced("set_buffer", (buffer_type, start, size, value), || unsafe {
         match proxy_set_buffer_bytes(buffer_type, start, size, value.as_ptr(), value.len()) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[cfg(not(all(test, feature = "mockalloc")))]
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_get_map")]
     fn proxy_get_header_map_pairs(
         map_type: MapType,
         return_map_data: *mut *mut u8,
@@ -163,43 +228,44 @@ fn proxy_get_header_map_pairs(
 }
 
 pub fn get_map(map_type: MapType) -> Result<Vec<(String, String)>, Status> {
-    unsafe {
+    traced("get_map", map_type, || unsafe {
         let mut return_data: *mut u8 = null_mut();
         let mut return_size: usize = 0;
         match proxy_get_header_map_pairs(map_type, &mut return_data, &mut return_size) {
             Status::Ok => {
                 if !return_data.is_null() {
                     let serialized_map = Vec::from_raw_parts(return_data, return_size, return_size);
-                    Ok(utils::deserialize_map(&serialized_map))
+                    utils::deserialize_map(&serialized_map)
                 } else {
                     Ok(Vec::new())
                 }
             }
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 pub fn get_map_bytes(map_type: MapType) -> Result<Vec<(String, Bytes)>, Status> {
-    unsafe {
+    traced("get_map_bytes", map_type, || unsafe {
         let mut return_data: *mut u8 = null_mut();
         let mut return_size: usize = 0;
         match proxy_get_header_map_pairs(map_type, &mut return_data, &mut return_size) {
             Status::Ok => {
                 if !return_data.is_null() {
                     let serialized_map = Vec::from_raw_parts(return_data, return_size, return_size);
-                    Ok(utils::deserialize_map_bytes(&serialized_map))
+                    utils::deserialize_map_bytes(&serialized_map)
                 } else {
                     Ok(Vec::new())
                 }
             }
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_set_map")]
     fn proxy_set_header_map_pairs(
         map_type: MapType,
         map_data: *const u8,
@@ -208,27 +274,40 @@ unsafe extern "C" {
 }
 
 pub fn set_map(map_type: MapType, map: Vec<(&str, &str)>) -> Result<(), Status> {
-    let serialized_map = utils::serialize_map(&map);
-    unsafe {
-        match proxy_set_header_map_pairs(map_type, serialized_map.as_ptr(), serialized_map.len()) {
-            Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("set_map", (map_type, &map), || {
+        let serialized_map = utils::serialize_map(&map);
+        unsafe {
+            match proxy_set_header_map_pairs(
+                map_type,
+                serialized_map.as_ptr(),
+                serialized_map.len(),
+            ) {
+                Status::Ok => Ok(()),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 pub fn set_map_bytes(map_type: MapType, map: Vec<(&str, &[u8])>) -> Result<(), Status> {
-    let serialized_map = utils::serialize_map_bytes(&map);
-    unsafe {
-        match proxy_set_header_map_pairs(map_type, serialized_map.as_ptr(), serialized_map.len()) {
-            Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("set_map_bytes", (map_type, &map), || {
+        let serialized_map = utils::serialize_map_bytes(&map);
+        unsafe {
+            match proxy_set_header_map_pairs(
+                map_type,
+                serialized_map.as_ptr(),
+                serialized_map.len(),
+            ) {
+                Status::Ok => Ok(()),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_get_map_value")]
     fn proxy_get_header_map_value(
         map_type: MapType,
         key_data: *const u8,
@@ -239,66 +318,70 @@ unsafe extern "C" {
 }
 
 pub fn get_map_value(map_type: MapType, key: &str) -> Result<Option<String>, Status> {
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    unsafe {
-        match proxy_get_header_map_value(
-            map_type,
-            key.as_ptr(),
-            key.len(),
-            &mut return_data,
-            &mut return_size,
-        ) {
-            Status::Ok => {
-                if !return_data.is_null() {
-                    Ok(Some(
+    traced("get_map_value", (map_type, key), || {
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_get_header_map_value(
+                map_type,
+                key.as_ptr(),
+                key.len(),
+                &mut return_data,
+                &mut return_size,
+            ) {
+                Status::Ok => {
+                    if !return_data.is_null() {
                         String::from_utf8(Vec::from_raw_parts(
                             return_data,
                             return_size,
                             return_size,
                         ))
-                        .unwrap(),
-                    ))
-                } else {
-                    Ok(Some(String::new()))
+                        .map(Some)
+                        .map_err(|_| Status::ParseFailure)
+                    } else {
+                        Ok(Some(String::new()))
+                    }
                 }
+                Status::NotFound => Ok(None),
+                status => Err(status),
             }
-            Status::NotFound => Ok(None),
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 pub fn get_map_value_bytes(map_type: MapType, key: &str) -> Result<Option<Bytes>, Status> {
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    unsafe {
-        match proxy_get_header_map_value(
-            map_type,
-            key.as_ptr(),
-            key.len(),
-            &mut return_data,
-            &mut return_size,
-        ) {
-            Status::Ok => {
-                if !return_data.is_null() {
-                    Ok(Some(Vec::from_raw_parts(
-                        return_data,
-                        return_size,
-                        return_size,
-                    )))
-                } else {
-                    Ok(Some(Vec::new()))
+    traced("get_map_value_bytes", (map_type, key), || {
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_get_header_map_value(
+                map_type,
+                key.as_ptr(),
+                key.len(),
+                &mut return_data,
+                &mut return_size,
+            ) {
+                Status::Ok => {
+                    if !return_data.is_null() {
+                        Ok(Some(Vec::from_raw_parts(
+                            return_data,
+                            return_size,
+                            return_size,
+                        )))
+                    } else {
+                        Ok(Some(Vec::new()))
+                    }
                 }
+                Status::NotFound => Ok(None),
+                status => Err(status),
             }
-            Status::NotFound => Ok(None),
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_remove_map_value")]
     fn proxy_remove_header_map_value(
         map_type: MapType,
         key_data: *const u8,
@@ -307,16 +390,17 @@ unsafe extern "C" {
 }
 
 pub fn remove_map_value(map_type: MapType, key: &str) -> Result<(), Status> {
-    unsafe {
+    traced("remove_map_value", (map_type, key), || unsafe {
         match proxy_remove_header_map_value(map_type, key.as_ptr(), key.len()) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_set_map_value")]
     fn proxy_replace_header_map_value(
         map_type: MapType,
         key_data: *const u8,
@@ -327,7 +411,7 @@ unsafe extern "C" {
 }
 
 pub fn set_map_value(map_type: MapType, key: &str, value: Option<&str>) -> Result<(), Status> {
-    unsafe {
+    traced("set_map_value", (map_type, key, value), || unsafe {
         if let Some(value) = value {
             match proxy_replace_header_map_value(
                 map_type,
@@ -337,15 +421,15 @@ pub fn set_map_value(map_type: MapType, key: &str, value: Option<&str>) -> Resul
                 value.len(),
             ) {
                 Status::Ok => Ok(()),
-                status => panic!("unexpected status: {}", status as u32),
+                status => Err(status),
             }
         } else {
             match proxy_remove_header_map_value(map_type, key.as_ptr(), key.len()) {
                 Status::Ok => Ok(()),
-                status => panic!("unexpected status: {}", status as u32),
+                status => Err(status),
             }
         }
-    }
+    })
 }
 
 pub fn set_map_value_bytes(
@@ -353,7 +437,7 @@ pub fn set_map_value_bytes(
     key: &str,
     value: Option<&[u8]>,
 ) -> Result<(), Status> {
-    unsafe {
+    traced("set_map_value_bytes", (map_type, key, value), || unsafe {
         if let Some(value) = value {
             match proxy_replace_header_map_value(
                 map_type,
@@ -363,19 +447,20 @@ pub fn set_map_value_bytes(
                 value.len(),
             ) {
                 Status::Ok => Ok(()),
-                status => panic!("unexpected status: {}", status as u32),
+                status => Err(status),
             }
         } else {
             match proxy_remove_header_map_value(map_type, key.as_ptr(), key.len()) {
                 Status::Ok => Ok(()),
-                status => panic!("unexpected status: {}", status as u32),
+                status => Err(status),
             }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
+    #[cfg_attr(proxy_abi = "vnext", link_name = "proxy_add_map_value")]
     fn proxy_add_header_map_value(
         map_type: MapType,
         key_data: *const u8,
@@ -386,7 +471,7 @@ unsafe extern "C" {
 }
 
 pub fn add_map_value(map_type: MapType, key: &str, value: &str) -> Result<(), Status> {
-    unsafe {
+    traced("add_map_value", (map_type, key, value), || unsafe {
         match proxy_add_header_map_value(
             map_type,
             key.as_ptr(),
@@ -395,13 +480,13 @@ pub fn add_map_value(map_type: MapType, key: &str, value: &str) -> Result<(), St
             value.len(),
         ) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 pub fn add_map_value_bytes(map_type: MapType, key: &str, value: &[u8]) -> Result<(), Status> {
-    unsafe {
+    traced("add_map_value_bytes", (map_type, key, value), || unsafe {
         match proxy_add_header_map_value(
             map_type,
             key.as_ptr(),
@@ -410,9 +495,9 @@ pub fn add_map_value_bytes(map_type: MapType, key: &str, value: &[u8]) -> Result
             value.len(),
         ) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -426,33 +511,35 @@ unsafe extern "C" {
 }
 
 pub fn get_property(path: Vec<&str>) -> Result<Option<Bytes>, Status> {
-    let serialized_path = utils::serialize_property_path(path);
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    unsafe {
-        match proxy_get_property(
-            serialized_path.as_ptr(),
-            serialized_path.len(),
-            &mut return_data,
-            &mut return_size,
-        ) {
-            Status::Ok => {
-                if !return_data.is_null() {
-                    Ok(Some(Vec::from_raw_parts(
-                        return_data,
-                        return_size,
-                        return_size,
-                    )))
-                } else {
-                    Ok(None)
+    traced("get_property", &path, || {
+        let serialized_path = utils::serialize_property_path(&path);
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_get_property(
+                serialized_path.as_ptr(),
+                serialized_path.len(),
+                &mut return_data,
+                &mut return_size,
+            ) {
+                Status::Ok => {
+                    if !return_data.is_null() {
+                        Ok(Some(Vec::from_raw_parts(
+                            return_data,
+                            return_size,
+                            return_size,
+                        )))
+                    } else {
+                        Ok(None)
+                    }
                 }
+                Status::NotFound => Ok(None),
+                Status::SerializationFailure => Err(Status::SerializationFailure),
+                Status::InternalFailure => Err(Status::InternalFailure),
+                status => Err(status),
             }
-            Status::NotFound => Ok(None),
-            Status::SerializationFailure => Err(Status::SerializationFailure),
-            Status::InternalFailure => Err(Status::InternalFailure),
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -466,18 +553,20 @@ unsafe extern "C" {
 }
 
 pub fn set_property(path: Vec<&str>, value: Option<&[u8]>) -> Result<(), Status> {
-    let serialized_path = utils::serialize_property_path(path);
-    unsafe {
-        match proxy_set_property(
-            serialized_path.as_ptr(),
-            serialized_path.len(),
-            value.map_or(null(), |value| value.as_ptr()),
-            value.map_or(0, |value| value.len()),
-        ) {
-            Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("set_property", (&path, value), || {
+        let serialized_path = utils::serialize_property_path(&path);
+        unsafe {
+            match proxy_set_property(
+                serialized_path.as_ptr(),
+                serialized_path.len(),
+                value.map_or(null(), |value| value.as_ptr()),
+                value.map_or(0, |value| value.len()),
+            ) {
+                Status::Ok => Ok(()),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -492,35 +581,37 @@ unsafe extern "C" {
 }
 
 pub fn get_shared_data(key: &str) -> Result<(Option<Bytes>, Option<u32>), Status> {
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    let mut return_cas: u32 = 0;
-    unsafe {
-        match proxy_get_shared_data(
-            key.as_ptr(),
-            key.len(),
-            &mut return_data,
-            &mut return_size,
-            &mut return_cas,
-        ) {
-            Status::Ok => {
-                let cas = match return_cas {
-                    0 => None,
-                    cas => Some(cas),
-                };
-                if !return_data.is_null() {
-                    Ok((
-                        Some(Vec::from_raw_parts(return_data, return_size, return_size)),
-                        cas,
-                    ))
-                } else {
-                    Ok((None, cas))
+    traced("get_shared_data", key, || {
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        let mut return_cas: u32 = 0;
+        unsafe {
+            match proxy_get_shared_data(
+                key.as_ptr(),
+                key.len(),
+                &mut return_data,
+                &mut return_size,
+                &mut return_cas,
+            ) {
+                Status::Ok => {
+                    let cas = match return_cas {
+                        0 => None,
+                        cas => Some(cas),
+                    };
+                    if !return_data.is_null() {
+                        Ok((
+                            Some(Vec::from_raw_parts(return_data, return_size, return_size)),
+                            cas,
+                        ))
+                    } else {
+                        Ok((None, cas))
+                    }
                 }
+                Status::NotFound => Ok((None, None)),
+                status => Err(status),
             }
-            Status::NotFound => Ok((None, None)),
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -535,7 +626,7 @@ unsafe extern "C" {
 }
 
 pub fn set_shared_data(key: &str, value: Option<&[u8]>, cas: Option<u32>) -> Result<(), Status> {
-    unsafe {
+    traced("set_shared_data", (key, value, cas), || unsafe {
         match proxy_set_shared_data(
             key.as_ptr(),
             key.len(),
@@ -545,9 +636,9 @@ pub fn set_shared_data(key: &str, value: Option<&[u8]>, cas: Option<u32>) -> Res
         ) {
             Status::Ok => Ok(()),
             Status::CasMismatch => Err(Status::CasMismatch),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -560,13 +651,13 @@ unsafe extern "C" {
 }
 
 pub fn register_shared_queue(name: &str) -> Result<u32, Status> {
-    unsafe {
+    traced("register_shared_queue", name, || unsafe {
         let mut return_id: u32 = 0;
         match proxy_register_shared_queue(name.as_ptr(), name.len(), &mut return_id) {
             Status::Ok => Ok(return_id),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -581,20 +672,22 @@ unsafe extern "C" {
 }
 
 pub fn resolve_shared_queue(vm_id: &str, name: &str) -> Result<Option<u32>, Status> {
-    let mut return_id: u32 = 0;
-    unsafe {
-        match proxy_resolve_shared_queue(
-            vm_id.as_ptr(),
-            vm_id.len(),
-            name.as_ptr(),
-            name.len(),
-            &mut return_id,
-        ) {
-            Status::Ok => Ok(Some(return_id)),
-            Status::NotFound => Ok(None),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("resolve_shared_queue", (vm_id, name), || {
+        let mut return_id: u32 = 0;
+        unsafe {
+            match proxy_resolve_shared_queue(
+                vm_id.as_ptr(),
+                vm_id.len(),
+                name.as_ptr(),
+                name.len(),
+                &mut return_id,
+            ) {
+                Status::Ok => Ok(Some(return_id)),
+                Status::NotFound => Ok(None),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -607,26 +700,28 @@ unsafe extern "C" {
 }
 
 pub fn dequeue_shared_queue(queue_id: u32) -> Result<Option<Bytes>, Status> {
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    unsafe {
-        match proxy_dequeue_shared_queue(queue_id, &mut return_data, &mut return_size) {
-            Status::Ok => {
-                if !return_data.is_null() {
-                    Ok(Some(Vec::from_raw_parts(
-                        return_data,
-                        return_size,
-                        return_size,
-                    )))
-                } else {
-                    Ok(None)
+    traced("dequeue_shared_queue", queue_id, || {
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_dequeue_shared_queue(queue_id, &mut return_data, &mut return_size) {
+                Status::Ok => {
+                    if !return_data.is_null() {
+                        Ok(Some(Vec::from_raw_parts(
+                            return_data,
+                            return_size,
+                            return_size,
+                        )))
+                    } else {
+                        Ok(None)
+                    }
                 }
+                Status::Empty => Ok(None),
+                Status::NotFound => Err(Status::NotFound),
+                status => Err(status),
             }
-            Status::Empty => Ok(None),
-            Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -639,7 +734,7 @@ unsafe extern "C" {
 }
 
 pub fn enqueue_shared_queue(queue_id: u32, value: Option<&[u8]>) -> Result<(), Status> {
-    unsafe {
+    traced("enqueue_shared_queue", (queue_id, value), || unsafe {
         match proxy_enqueue_shared_queue(
             queue_id,
             value.map_or(null(), |value| value.as_ptr()),
@@ -647,90 +742,129 @@ pub fn enqueue_shared_queue(queue_id: u32, value: Option<&[u8]>) -> Result<(), S
         ) {
             Status::Ok => Ok(()),
             Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
     fn proxy_continue_stream(stream_type: StreamType) -> Status;
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn resume_downstream() -> Result<(), Status> {
-    unsafe {
+    traced("resume_downstream", (), || unsafe {
         match proxy_continue_stream(StreamType::Downstream) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn resume_upstream() -> Result<(), Status> {
-    unsafe {
+    traced("resume_upstream", (), || unsafe {
         match proxy_continue_stream(StreamType::Upstream) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn resume_http_request() -> Result<(), Status> {
-    unsafe {
+    traced("resume_http_request", (), || unsafe {
         match proxy_continue_stream(StreamType::HttpRequest) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn resume_http_response() -> Result<(), Status> {
-    unsafe {
+    traced("resume_http_response", (), || unsafe {
         match proxy_continue_stream(StreamType::HttpResponse) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+// ABI v0.1.0 resumes HTTP streams with dedicated hostcalls, and can't resume or close
+// TCP streams, nor reset HTTP streams.
+#[cfg(proxy_abi = "0.1.0")]
+#[link(wasm_import_module = "env")]
+unsafe extern "C" {
+    fn proxy_continue_request() -> Status;
+    fn proxy_continue_response() -> Status;
+}
+
+#[cfg(proxy_abi = "0.1.0")]
+pub fn resume_http_request() -> Result<(), Status> {
+    traced("resume_http_request", (), || unsafe {
+        match proxy_continue_request() {
+            Status::Ok => Ok(()),
+            status => Err(status),
+        }
+    })
+}
+
+#[cfg(proxy_abi = "0.1.0")]
+pub fn resume_http_response() -> Result<(), Status> {
+    traced("resume_http_response", (), || unsafe {
+        match proxy_continue_response() {
+            Status::Ok => Ok(()),
+            status => Err(status),
+        }
+    })
+}
+
+#[cfg(not(proxy_abi = "0.1.0"))]
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
     fn proxy_close_stream(stream_type: StreamType) -> Status;
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn close_downstream() -> Result<(), Status> {
-    unsafe {
+    traced("close_downstream", (), || unsafe {
         match proxy_close_stream(StreamType::Downstream) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn close_upstream() -> Result<(), Status> {
-    unsafe {
+    traced("close_upstream", (), || unsafe {
         match proxy_close_stream(StreamType::Upstream) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn reset_http_request() -> Result<(), Status> {
-    unsafe {
+    traced("reset_http_request", (), || unsafe {
         match proxy_close_stream(StreamType::HttpRequest) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
+#[cfg(not(proxy_abi = "0.1.0"))]
 pub fn reset_http_response() -> Result<(), Status> {
-    unsafe {
+    traced("reset_http_response", (), || unsafe {
         match proxy_close_stream(StreamType::HttpResponse) {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -752,22 +886,24 @@ pub fn send_http_response(
     headers: Vec<(&str, &str)>,
     body: Option<&[u8]>,
 ) -> Result<(), Status> {
-    let serialized_headers = utils::serialize_map(&headers);
-    unsafe {
-        match proxy_send_local_response(
-            status_code,
-            null(),
-            0,
-            body.map_or(null(), |body| body.as_ptr()),
-            body.map_or(0, |body| body.len()),
-            serialized_headers.as_ptr(),
-            serialized_headers.len(),
-            -1,
-        ) {
-            Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("send_http_response", (status_code, &headers, body), || {
+        let serialized_headers = utils::serialize_map(&headers);
+        unsafe {
+            match proxy_send_local_response(
+                status_code,
+                null(),
+                0,
+                body.map_or(null(), |body| body.as_ptr()),
+                body.map_or(0, |body| body.len()),
+                serialized_headers.as_ptr(),
+                serialized_headers.len(),
+                -1,
+            ) {
+                Status::Ok => Ok(()),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 pub fn send_grpc_response(
@@ -775,22 +911,29 @@ pub fn send_grpc_response(
     grpc_status_message: Option<&str>,
     custom_metadata: Vec<(&str, &[u8])>,
 ) -> Result<(), Status> {
-    let serialized_custom_metadata = utils::serialize_map_bytes(&custom_metadata);
-    unsafe {
-        match proxy_send_local_response(
-            200,
-            null(),
-            0,
-            grpc_status_message.map_or(null(), |grpc_status_message| grpc_status_message.as_ptr()),
-            grpc_status_message.map_or(0, |grpc_status_message| grpc_status_message.len()),
-            serialized_custom_metadata.as_ptr(),
-            serialized_custom_metadata.len(),
-            grpc_status as i32,
-        ) {
-            Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
-        }
-    }
+    traced(
+        "send_grpc_response",
+        (grpc_status, grpc_status_message, &custom_metadata),
+        || {
+            let serialized_custom_metadata = utils::serialize_map_bytes(&custom_metadata);
+            unsafe {
+                match proxy_send_local_response(
+                    200,
+                    null(),
+                    0,
+                    grpc_status_message
+                        .map_or(null(), |grpc_status_message| grpc_status_message.as_ptr()),
+                    grpc_status_message.map_or(0, |grpc_status_message| grpc_status_message.len()),
+                    serialized_custom_metadata.as_ptr(),
+                    serialized_custom_metadata.len(),
+                    grpc_status as i32,
+                ) {
+                    Status::Ok => Ok(()),
+                    status => Err(status),
+                }
+            }
+        },
+    )
 }
 
 #[link(wasm_import_module = "env")]
@@ -816,31 +959,35 @@ pub fn dispatch_http_call(
     trailers: Vec<(&str, &str)>,
     timeout: Duration,
 ) -> Result<u32, Status> {
-    let serialized_headers = utils::serialize_map(&headers);
-    let serialized_trailers = utils::serialize_map(&trailers);
-    let mut return_token: u32 = 0;
-    unsafe {
-        match proxy_http_call(
-            upstream.as_ptr(),
-            upstream.len(),
-            serialized_headers.as_ptr(),
-            serialized_headers.len(),
-            body.map_or(null(), |body| body.as_ptr()),
-            body.map_or(0, |body| body.len()),
-            serialized_trailers.as_ptr(),
-            serialized_trailers.len(),
-            timeout.as_millis() as u32,
-            &mut return_token,
-        ) {
-            Status::Ok => {
-                dispatcher::register_callout(return_token);
-                Ok(return_token)
+    traced(
+        "dispatch_http_call",
+        (upstream, &headers, body, &trailers, timeout),
+        || {
+            let serialized_headers = utils::serialize_map(&headers);
+            let serialized_trailers = utils::serialize_map(&trailers);
+            let mut return_token: u32 = 0;
+            unsafe {
+                match proxy_http_call(
+                    upstream.as_ptr(),
+                    upstream.len(),
+                    serialized_headers.as_ptr(),
+                    serialized_headers.len(),
+                    body.map_or(null(), |body| body.as_ptr()),
+                    body.map_or(0, |body| body.len()),
+                    serialized_trailers.as_ptr(),
+                    serialized_trailers.len(),
+                    timeout.as_millis() as u32,
+                    &mut return_token,
+                ) {
+                    Status::Ok => Ok(return_token),
+                    Status::BadArgument => Err(Status::BadArgument),
+                    Status::InternalFailure => Err(Status::InternalFailure),
+                    status => Err(status),
+                }
             }
-            Status::BadArgument => Err(Status::BadArgument),
-            Status::InternalFailure => Err(Status::InternalFailure),
-            status => panic!("unexpected status: {}", status as u32),
-        }
-    }
+        },
+    )
+    .inspect(|token_id| dispatcher::register_callout(*token_id))
 }
 
 #[link(wasm_import_module = "env")]
@@ -869,32 +1016,43 @@ pub fn dispatch_grpc_call(
     message: Option<&[u8]>,
     timeout: Duration,
 ) -> Result<u32, Status> {
-    let mut return_callout_id = 0;
-    let serialized_initial_metadata = utils::serialize_map_bytes(&initial_metadata);
-    unsafe {
-        match proxy_grpc_call(
-            upstream_name.as_ptr(),
-            upstream_name.len(),
-            service_name.as_ptr(),
-            service_name.len(),
-            method_name.as_ptr(),
-            method_name.len(),
-            serialized_initial_metadata.as_ptr(),
-            serialized_initial_metadata.len(),
-            message.map_or(null(), |message| message.as_ptr()),
-            message.map_or(0, |message| message.len()),
-            timeout.as_millis() as u32,
-            &mut return_callout_id,
-        ) {
-            Status::Ok => {
-                dispatcher::register_grpc_callout(return_callout_id);
-                Ok(return_callout_id)
+    traced(
+        "dispatch_grpc_call",
+        (
+            upstream_name,
+            service_name,
+            method_name,
+            &initial_metadata,
+            message,
+            timeout,
+        ),
+        || {
+            let mut return_callout_id = 0;
+            let serialized_initial_metadata = utils::serialize_map_bytes(&initial_metadata);
+            unsafe {
+                match proxy_grpc_call(
+                    upstream_name.as_ptr(),
+                    upstream_name.len(),
+                    service_name.as_ptr(),
+                    service_name.len(),
+                    method_name.as_ptr(),
+                    method_name.len(),
+                    serialized_initial_metadata.as_ptr(),
+                    serialized_initial_metadata.len(),
+                    message.map_or(null(), |message| message.as_ptr()),
+                    message.map_or(0, |message| message.len()),
+                    timeout.as_millis() as u32,
+                    &mut return_callout_id,
+                ) {
+                    Status::Ok => Ok(return_callout_id),
+                    Status::ParseFailure => Err(Status::ParseFailure),
+                    Status::InternalFailure => Err(Status::InternalFailure),
+                    status => Err(status),
+                }
             }
-            Status::ParseFailure => Err(Status::ParseFailure),
-            Status::InternalFailure => Err(Status::InternalFailure),
-            status => panic!("unexpected status: {}", status as u32),
-        }
-    }
+        },
+    )
+    .inspect(|token_id| dispatcher::register_grpc_callout(*token_id))
 }
 
 #[link(wasm_import_module = "env")]
@@ -918,29 +1076,33 @@ pub fn open_grpc_stream(
     method_name: &str,
     initial_metadata: Vec<(&str, &[u8])>,
 ) -> Result<u32, Status> {
-    let mut return_stream_id = 0;
-    let serialized_initial_metadata = utils::serialize_map_bytes(&initial_metadata);
-    unsafe {
-        match proxy_grpc_stream(
-            upstream_name.as_ptr(),
-            upstream_name.len(),
-            service_name.as_ptr(),
-            service_name.len(),
-            method_name.as_ptr(),
-            method_name.len(),
-            serialized_initial_metadata.as_ptr(),
-            serialized_initial_metadata.len(),
-            &mut return_stream_id,
-        ) {
-            Status::Ok => {
-                dispatcher::register_grpc_stream(return_stream_id);
-                Ok(return_stream_id)
+    traced(
+        "open_grpc_stream",
+        (upstream_name, service_name, method_name, &initial_metadata),
+        || {
+            let mut return_stream_id = 0;
+            let serialized_initial_metadata = utils::serialize_map_bytes(&initial_metadata);
+            unsafe {
+                match proxy_grpc_stream(
+                    upstream_name.as_ptr(),
+                    upstream_name.len(),
+                    service_name.as_ptr(),
+                    service_name.len(),
+                    method_name.as_ptr(),
+                    method_name.len(),
+                    serialized_initial_metadata.as_ptr(),
+                    serialized_initial_metadata.len(),
+                    &mut return_stream_id,
+                ) {
+                    Status::Ok => Ok(return_stream_id),
+                    Status::ParseFailure => Err(Status::ParseFailure),
+                    Status::InternalFailure => Err(Status::InternalFailure),
+                    status => Err(status),
+                }
             }
-            Status::ParseFailure => Err(Status::ParseFailure),
-            Status::InternalFailure => Err(Status::InternalFailure),
-            status => panic!("unexpected status: {}", status as u32),
-        }
-    }
+        },
+    )
+    .inspect(|token_id| dispatcher::register_grpc_stream(*token_id))
 }
 
 #[link(wasm_import_module = "env")]
@@ -958,19 +1120,23 @@ pub fn send_grpc_stream_message(
     message: Option<&[u8]>,
     end_stream: bool,
 ) -> Result<(), Status> {
-    unsafe {
-        match proxy_grpc_send(
-            token,
-            message.map_or(null(), |message| message.as_ptr()),
-            message.map_or(0, |message| message.len()),
-            end_stream,
-        ) {
-            Status::Ok => Ok(()),
-            Status::BadArgument => Err(Status::BadArgument),
-            Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
-        }
-    }
+    traced(
+        "send_grpc_stream_message",
+        (token, message, end_stream),
+        || unsafe {
+            match proxy_grpc_send(
+                token,
+                message.map_or(null(), |message| message.as_ptr()),
+                message.map_or(0, |message| message.len()),
+                end_stream,
+            ) {
+                Status::Ok => Ok(()),
+                Status::BadArgument => Err(Status::BadArgument),
+                Status::NotFound => Err(Status::NotFound),
+                status => Err(status),
+            }
+        },
+    )
 }
 
 #[link(wasm_import_module = "env")]
@@ -979,23 +1145,23 @@ unsafe extern "C" {
 }
 
 pub fn cancel_grpc_call(token_id: u32) -> Result<(), Status> {
-    unsafe {
+    traced("cancel_grpc_call", token_id, || unsafe {
         match proxy_grpc_cancel(token_id) {
             Status::Ok => Ok(()),
             Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 pub fn cancel_grpc_stream(token_id: u32) -> Result<(), Status> {
-    unsafe {
+    traced("cancel_grpc_stream", token_id, || unsafe {
         match proxy_grpc_cancel(token_id) {
             Status::Ok => Ok(()),
             Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1004,13 +1170,13 @@ unsafe extern "C" {
 }
 
 pub fn close_grpc_stream(token_id: u32) -> Result<(), Status> {
-    unsafe {
+    traced("close_grpc_stream", token_id, || unsafe {
         match proxy_grpc_close(token_id) {
             Status::Ok => Ok(()),
             Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1023,31 +1189,33 @@ unsafe extern "C" {
 }
 
 pub fn get_grpc_status() -> Result<(u32, Option<String>), Status> {
-    let mut return_code: u32 = 0;
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    unsafe {
-        match proxy_get_status(&mut return_code, &mut return_data, &mut return_size) {
-            Status::Ok => {
-                if !return_data.is_null() {
-                    Ok((
-                        return_code,
-                        Some(
-                            String::from_utf8(Vec::from_raw_parts(
-                                return_data,
-                                return_size,
-                                return_size,
-                            ))
-                            .unwrap(),
-                        ),
-                    ))
-                } else {
-                    Ok((return_code, None))
+    traced("get_grpc_status", (), || {
+        let mut return_code: u32 = 0;
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_get_status(&mut return_code, &mut return_data, &mut return_size) {
+                Status::Ok => {
+                    if !return_data.is_null() {
+                        Ok((
+                            return_code,
+                            Some(
+                                String::from_utf8(Vec::from_raw_parts(
+                                    return_data,
+                                    return_size,
+                                    return_size,
+                                ))
+                                .unwrap(),
+                            ),
+                        ))
+                    } else {
+                        Ok((return_code, None))
+                    }
                 }
+                status => Err(status),
             }
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1056,13 +1224,13 @@ unsafe extern "C" {
 }
 
 pub fn set_effective_context(context_id: u32) -> Result<(), Status> {
-    unsafe {
+    traced("set_effective_context", context_id, || unsafe {
         match proxy_set_effective_context(context_id) {
             Status::Ok => Ok(()),
             Status::BadArgument => Err(Status::BadArgument),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1081,35 +1249,37 @@ pub fn call_foreign_function(
     function_name: &str,
     arguments: Option<&[u8]>,
 ) -> Result<Option<Bytes>, Status> {
-    let mut return_data: *mut u8 = null_mut();
-    let mut return_size: usize = 0;
-    unsafe {
-        match proxy_call_foreign_function(
-            function_name.as_ptr(),
-            function_name.len(),
-            arguments.map_or(null(), |arguments| arguments.as_ptr()),
-            arguments.map_or(0, |arguments| arguments.len()),
-            &mut return_data,
-            &mut return_size,
-        ) {
-            Status::Ok => {
-                if !return_data.is_null() {
-                    Ok(Some(Vec::from_raw_parts(
-                        return_data,
-                        return_size,
-                        return_size,
-                    )))
-                } else {
-                    Ok(None)
+    traced("call_foreign_function", (function_name, arguments), || {
+        let mut return_data: *mut u8 = null_mut();
+        let mut return_size: usize = 0;
+        unsafe {
+            match proxy_call_foreign_function(
+                function_name.as_ptr(),
+                function_name.len(),
+                arguments.map_or(null(), |arguments| arguments.as_ptr()),
+                arguments.map_or(0, |arguments| arguments.len()),
+                &mut return_data,
+                &mut return_size,
+            ) {
+                Status::Ok => {
+                    if !return_data.is_null() {
+                        Ok(Some(Vec::from_raw_parts(
+                            return_data,
+                            return_size,
+                            return_size,
+                        )))
+                    } else {
+                        Ok(None)
+                    }
                 }
+                Status::NotFound => Err(Status::NotFound),
+                Status::BadArgument => Err(Status::BadArgument),
+                Status::SerializationFailure => Err(Status::SerializationFailure),
+                Status::InternalFailure => Err(Status::InternalFailure),
+                status => Err(status),
             }
-            Status::NotFound => Err(Status::NotFound),
-            Status::BadArgument => Err(Status::BadArgument),
-            Status::SerializationFailure => Err(Status::SerializationFailure),
-            Status::InternalFailure => Err(Status::InternalFailure),
-            status => panic!("unexpected status: {}", status as u32),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1118,12 +1288,12 @@ unsafe extern "C" {
 }
 
 pub fn done() -> Result<(), Status> {
-    unsafe {
+    traced("done", (), || unsafe {
         match proxy_done() {
             Status::Ok => Ok(()),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1137,13 +1307,15 @@ unsafe extern "C" {
 }
 
 pub fn define_metric(metric_type: MetricType, name: &str) -> Result<u32, Status> {
-    let mut return_id: u32 = 0;
-    unsafe {
-        match proxy_define_metric(metric_type, name.as_ptr(), name.len(), &mut return_id) {
-            Status::Ok => Ok(return_id),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("define_metric", (metric_type, name), || {
+        let mut return_id: u32 = 0;
+        unsafe {
+            match proxy_define_metric(metric_type, name.as_ptr(), name.len(), &mut return_id) {
+                Status::Ok => Ok(return_id),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1152,15 +1324,17 @@ unsafe extern "C" {
 }
 
 pub fn get_metric(metric_id: u32) -> Result<u64, Status> {
-    let mut return_value: u64 = 0;
-    unsafe {
-        match proxy_get_metric(metric_id, &mut return_value) {
-            Status::Ok => Ok(return_value),
-            Status::NotFound => Err(Status::NotFound),
-            Status::BadArgument => Err(Status::BadArgument),
-            status => panic!("unexpected status: {}", status as u32),
+    traced("get_metric", metric_id, || {
+        let mut return_value: u64 = 0;
+        unsafe {
+            match proxy_get_metric(metric_id, &mut return_value) {
+                Status::Ok => Ok(return_value),
+                Status::NotFound => Err(Status::NotFound),
+                Status::BadArgument => Err(Status::BadArgument),
+                status => Err(status),
+            }
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1169,13 +1343,13 @@ unsafe extern "C" {
 }
 
 pub fn record_metric(metric_id: u32, value: u64) -> Result<(), Status> {
-    unsafe {
+    traced("record_metric", (metric_id, value), || unsafe {
         match proxy_record_metric(metric_id, value) {
             Status::Ok => Ok(()),
             Status::NotFound => Err(Status::NotFound),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[link(wasm_import_module = "env")]
@@ -1184,14 +1358,14 @@ unsafe extern "C" {
 }
 
 pub fn increment_metric(metric_id: u32, offset: i64) -> Result<(), Status> {
-    unsafe {
+    traced("increment_metric", (metric_id, offset), || unsafe {
         match proxy_increment_metric(metric_id, offset) {
             Status::Ok => Ok(()),
             Status::NotFound => Err(Status::NotFound),
             Status::BadArgument => Err(Status::BadArgument),
-            status => panic!("unexpected status: {}", status as u32),
+            status => Err(status),
         }
-    }
+    })
 }
 
 #[cfg(all(test, feature = "mockalloc"))]
@@ -1241,20 +1415,25 @@ mod tests {
     }
 }
 
-mod utils {
-    use crate::types::Bytes;
+// Exposed for the fuzz targets in `fuzz/`.
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub use utils::{deserialize_map, deserialize_map_bytes, serialize_map, serialize_map_bytes};
+
+pub(crate) mod utils {
+    use crate::types::{Bytes, Status};
     use std::convert::TryFrom;
 
-    pub(super) fn serialize_property_path(path: Vec<&str>) -> Bytes {
+    pub(super) fn serialize_property_path(path: &[&str]) -> Bytes {
         if path.is_empty() {
             return Vec::new();
         }
         let mut size: usize = 0;
-        for part in &path {
+        for part in path {
             size += part.len() + 1;
         }
         let mut bytes: Bytes = Vec::with_capacity(size);
-        for part in &path {
+        for part in path {
             bytes.extend_from_slice(part.as_bytes());
             bytes.push(0);
         }
@@ -1262,7 +1441,7 @@ mod utils {
         bytes
     }
 
-    pub(super) fn serialize_map(map: &[(&str, &str)]) -> Bytes {
+    pub fn serialize_map(map: &[(&str, &str)]) -> Bytes {
         let mut size: usize = 4;
         for (name, value) in map {
             size += name.len() + value.len() + 10;
@@ -1282,7 +1461,7 @@ mod utils {
         bytes
     }
 
-    pub(super) fn serialize_map_bytes(map: &[(&str, &[u8])]) -> Bytes {
+    pub fn serialize_map_bytes(map: &[(&str, &[u8])]) -> Bytes {
         let mut size: usize = 4;
         for (name, value) in map {
             size += name.len() + value.len() + 10;
@@ -1302,49 +1481,54 @@ mod utils {
         bytes
     }
 
-    pub(super) fn deserialize_map(bytes: &[u8]) -> Vec<(String, String)> {
+    pub fn deserialize_map(bytes: &[u8]) -> Result<Vec<(String, String)>, Status> {
+        deserialize(bytes, |value| {
+            String::from_utf8(value.to_vec()).map_err(|_| Status::ParseFailure)
+        })
+    }
+
+    pub fn deserialize_map_bytes(bytes: &[u8]) -> Result<Vec<(String, Bytes)>, Status> {
+        deserialize(bytes, |value| Ok(value.to_vec()))
+    }
+
+    fn deserialize<T>(
+        bytes: &[u8],
+        value: impl Fn(&[u8]) -> Result<T, Status>,
+    ) -> Result<Vec<(String, T)>, Status> {
         if bytes.is_empty() {
-            return Vec::new();
-        }
-        let size = u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[0..4]).unwrap()) as usize;
+            return Ok(Vec::new());
+        }
+        let size = read_size(bytes, 0)?;
+        // Sizes of all keys and values must fit before the data.
+        let mut p = size
+            .checked_mul(8)
+            .and_then(|sizes| sizes.checked_add(4))
+            .filter(|&p| p <= bytes.len())
+            .ok_or(Status::ParseFailure)?;
         let mut map = Vec::with_capacity(size);
-        let mut p = 4 + size * 8;
         for n in 0..size {
             let s = 4 + n * 8;
-            let size = u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[s..s + 4]).unwrap()) as usize;
-            let key = bytes[p..p + size].to_vec();
-            p += size + 1;
-            let size =
-                u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[s + 4..s + 8]).unwrap()) as usize;
-            let value = bytes[p..p + size].to_vec();
-            p += size + 1;
-            map.push((
-                String::from_utf8(key).unwrap(),
-                String::from_utf8(value).unwrap(),
-            ));
-        }
-        map
+            let key = read_field(bytes, &mut p, read_size(bytes, s)?)?;
+            let key = String::from_utf8(key.to_vec()).map_err(|_| Status::ParseFailure)?;
+            let field = read_field(bytes, &mut p, read_size(bytes, s + 4)?)?;
+            map.push((key, value(field)?));
+        }
+        Ok(map)
     }
 
-    pub(super) fn deserialize_map_bytes(bytes: &[u8]) -> Vec<(String, Bytes)> {
-        if bytes.is_empty() {
-            return Vec::new();
+    fn read_size(bytes: &[u8], offset: usize) -> Result<usize, Status> {
+        match bytes.get(offset..offset + 4) {
+            Some(size) => Ok(u32::from_le_bytes(<[u8; 4]>::try_from(size).unwrap()) as usize),
+            None => Err(Status::ParseFailure),
         }
-        let size = u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[0..4]).unwrap()) as usize;
-        let mut map = Vec::with_capacity(size);
-        let mut p = 4 + size * 8;
-        for n in 0..size {
-            let s = 4 + n * 8;
-            let size = u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[s..s + 4]).unwrap()) as usize;
-            let key = bytes[p..p + size].to_vec();
-            p += size + 1;
-            let size =
-                u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[s + 4..s + 8]).unwrap()) as usize;
-            let value = bytes[p..p + size].to_vec();
-            p += size + 1;
-            map.push((String::from_utf8(key).unwrap(), value));
-        }
-        map
+    }
+
+    // Returns the field at `p`, and moves `p` past it and its NUL terminator.
+    fn read_field<'a>(bytes: &'a [u8], p: &mut usize, size: usize) -> Result<&'a [u8], Status> {
+        let end = p.checked_add(size).ok_or(Status::ParseFailure)?;
+        let field = bytes.get(*p..end).ok_or(Status::ParseFailure)?;
+        *p = end + 1;
+        Ok(field)
     }
 
     #[cfg(test)]
@@ -1405,17 +1589,17 @@ mod utils {
 
         #[test]
         fn test_deserialize_map_empty() {
-            let map = deserialize_map(&[]);
+            let map = deserialize_map(&[]).unwrap();
             assert_eq!(map, []);
-            let map = deserialize_map(&[0, 0, 0, 0]);
+            let map = deserialize_map(&[0, 0, 0, 0]).unwrap();
             assert_eq!(map, []);
         }
 
         #[test]
         fn test_deserialize_map_empty_bytes() {
-            let map = deserialize_map_bytes(&[]);
+            let map = deserialize_map_bytes(&[]).unwrap();
             assert_eq!(map, []);
-            let map = deserialize_map_bytes(&[0, 0, 0, 0]);
+            let map = deserialize_map_bytes(&[0, 0, 0, 0]).unwrap();
             assert_eq!(map, []);
         }
 
@@ -1434,7 +1618,7 @@ mod utils {
 
         #[test]
         fn test_deserialize_map() {
-            let map = deserialize_map(SERIALIZED_MAP);
+            let map = deserialize_map(SERIALIZED_MAP).unwrap();
             assert_eq!(map.len(), MAP.len());
             for (got, expected) in map.into_iter().zip(MAP) {
                 assert_eq!(got.0, expected.0);
@@ -1444,7 +1628,7 @@ mod utils {
 
         #[test]
         fn test_deserialize_map_bytes() {
-            let map = deserialize_map_bytes(SERIALIZED_MAP);
+            let map = deserialize_map_bytes(SERIALIZED_MAP).unwrap();
             assert_eq!(map.len(), MAP.len());
             for (got, expected) in map.into_iter().zip(MAP) {
                 assert_eq!(got.0, expected.0);
@@ -1454,7 +1638,7 @@ mod utils {
 
         #[test]
         fn test_deserialize_map_roundtrip() {
-            let map = deserialize_map(SERIALIZED_MAP);
+            let map = deserialize_map(SERIALIZED_MAP).unwrap();
             // TODO(v0.3): fix arguments, so that maps can be reused without conversion.
             let map_refs: Vec<(&str, &str)> =
                 map.iter().map(|x| (x.0.as_ref(), x.1.as_ref())).collect();
@@ -1464,7 +1648,7 @@ mod utils {
 
         #[test]
         fn test_deserialize_map_roundtrip_bytes() {
-            let map = deserialize_map_bytes(SERIALIZED_MAP);
+            let map = deserialize_map_bytes(SERIALIZED_MAP).unwrap();
             // TODO(v0.3): fix arguments, so that maps can be reused without conversion.
             let map_refs: Vec<(&str, &[u8])> =
                 map.iter().map(|x| (x.0.as_ref(), x.1.as_ref())).collect();
@@ -1477,7 +1661,7 @@ mod utils {
             // 0x00-0x7f are valid single-byte UTF-8 characters.
             for i in 0..0x7f {
                 let serialized_src = [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 99, 0, i, 0];
-                let map = deserialize_map(&serialized_src);
+                let map = deserialize_map(&serialized_src).unwrap();
                 // TODO(v0.3): fix arguments, so that maps can be reused without conversion.
                 let map_refs: Vec<(&str, &str)> =
                     map.iter().map(|x| (x.0.as_ref(), x.1.as_ref())).collect();
@@ -1487,12 +1671,24 @@ mod utils {
             // 0x80-0xff are invalid single-byte UTF-8 characters.
             for i in 0x80..0xff {
                 let serialized_src = [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 99, 0, i, 0];
-                std::panic::set_hook(Box::new(|_| {}));
-                let result = std::panic::catch_unwind(|| {
-                    deserialize_map(&serialized_src);
-                });
-                assert!(result.is_err());
+                let map = deserialize_map(&serialized_src);
+                assert_eq!(map, Err(Status::ParseFailure));
+            }
+        }
+
+        #[test]
+        fn test_deserialize_map_truncated() {
+            // The final NUL terminator isn't needed to parse the map.
+            for size in 1..SERIALIZED_MAP.len() - 1 {
+                let serialized_src = &SERIALIZED_MAP[..size];
+                assert_eq!(deserialize_map(serialized_src), Err(Status::ParseFailure));
+                assert_eq!(
+                    deserialize_map_bytes(serialized_src),
+                    Err(Status::ParseFailure)
+                );
             }
+            let serialized_src = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+            assert_eq!(deserialize_map(&serialized_src), Err(Status::ParseFailure));
         }
 
         #[test]
@@ -1500,7 +1696,7 @@ mod utils {
             // All 256 single-byte characters are allowed when emitting bytes.
             for i in 0..0xff {
                 let serialized_src = [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 99, 0, i, 0];
-                let map = deserialize_map_bytes(&serialized_src);
+                let map = deserialize_map_bytes(&serialized_src).unwrap();
                 // TODO(v0.3): fix arguments, so that maps can be reused without conversion.
                 let map_refs: Vec<(&str, &[u8])> =
                     map.iter().map(|x| (x.0.as_ref(), x.1.as_ref())).collect();
@@ -1532,7 +1728,7 @@ mod utils {
         fn bench_deserialize_map(b: &mut Bencher) {
             let serialized_map = SERIALIZED_MAP.to_vec();
             b.iter(|| {
-                deserialize_map(test::black_box(&serialized_map));
+                deserialize_map(test::black_box(&serialized_map)).unwrap();
             });
         }
 
@@ -1541,7 +1737,7 @@ mod utils {
         fn bench_deserialize_map_bytes(b: &mut Bencher) {
             let serialized_map = SERIALIZED_MAP.to_vec();
             b.iter(|| {
-                deserialize_map_bytes(test::black_box(&serialized_map));
+                deserialize_map_bytes(test::black_box(&serialized_map)).unwrap();
             });
         }
     }
@@ -17,13 +17,35 @@
 #[cfg(all(test, nightly))]
 extern crate test;
 
+pub mod body;
+pub mod buffered;
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(any(feature = "json", feature = "yaml"))]
+pub mod config;
+pub mod envoy;
+#[cfg(feature = "async")]
+pub mod executor;
+pub mod headers;
 pub mod hostcalls;
+pub mod metrics;
+pub mod properties;
+pub mod protobuf;
+pub mod shared_data;
+pub mod shared_queue;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+#[cfg(feature = "trace")]
+pub mod trace;
 pub mod traits;
 pub mod types;
 
 mod allocator;
 mod dispatcher;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+mod harness;
 mod logger;
+mod timers;
 
 // For crate-type="cdylib".
 #[cfg(not(wasi_exec_model_reactor))]
@@ -59,6 +81,9 @@ macro_rules! main {
     };
 }
 
+#[cfg(feature = "macros")]
+pub use proxy_wasm_macros::{http_filter, stream_filter};
+
 pub fn set_log_level(level: types::LogLevel) {
     logger::set_log_level(level);
 }
@@ -75,5 +100,27 @@ pub fn set_http_context(callback: types::NewHttpContext) {
     dispatcher::set_http_context(callback);
 }
 
+pub fn set_error_policy(policy: types::ErrorPolicy) {
+    dispatcher::set_error_policy(policy);
+}
+
+pub fn set_error_hook(hook: types::ErrorHook) {
+    dispatcher::set_error_hook(hook);
+}
+
+#[cfg(proxy_abi = "0.1.0")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_abi_version_0_1_0() {}
+
+#[cfg(proxy_abi = "0.2.0")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_abi_version_0_2_0() {}
+
+// The next version of the ABI is still a draft, so hosts might not agree on its marker.
+#[cfg(proxy_abi = "vnext")]
+#[unsafe(no_mangle)]
+pub extern "C" fn proxy_abi_version_vnext() {}
+
+#[cfg(proxy_abi = "0.2.1")]
 #[unsafe(no_mangle)]
 pub extern "C" fn proxy_abi_version_0_2_1() {}
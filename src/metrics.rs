@@ -0,0 +1,250 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed metric handles.
+//!
+//! Metrics are defined on the host the first time they're used, and handles
+//! share their ID, so they're cheap to clone from a root context into each
+//! stream context.
+
+use crate::hostcalls;
+use crate::types::MetricType;
+use hashbrown::HashMap;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+struct Definition {
+    metric_type: MetricType,
+    name: String,
+    id: Cell<Option<u32>>,
+}
+
+impl Definition {
+    fn new(metric_type: MetricType, name: String) -> Rc<Definition> {
+        Rc::new(Definition {
+            metric_type,
+            name,
+            id: Cell::new(None),
+        })
+    }
+
+    fn id(&self) -> u32 {
+        match self.id.get() {
+            Some(id) => id,
+            None => {
+                let id = hostcalls::define_metric(self.metric_type, &self.name).unwrap();
+                self.id.set(Some(id));
+                id
+            }
+        }
+    }
+}
+
+/// A metric handle that can be created by name, see [`Family`].
+pub trait Metric: Clone {
+    fn define(name: String) -> Self;
+
+    fn name(&self) -> &str;
+}
+
+#[derive(Clone)]
+pub struct Counter(Rc<Definition>);
+
+impl Counter {
+    pub fn new(name: &str) -> Counter {
+        Counter::define(name.to_string())
+    }
+
+    pub fn increment(&self, offset: u64) {
+        let offset = i64::try_from(offset).unwrap_or(i64::MAX);
+        hostcalls::increment_metric(self.0.id(), offset).unwrap()
+    }
+
+    pub fn value(&self) -> u64 {
+        hostcalls::get_metric(self.0.id()).unwrap()
+    }
+}
+
+impl Metric for Counter {
+    fn define(name: String) -> Counter {
+        Counter(Definition::new(MetricType::Counter, name))
+    }
+
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+}
+
+#[derive(Clone)]
+pub struct Gauge(Rc<Definition>);
+
+impl Gauge {
+    pub fn new(name: &str) -> Gauge {
+        Gauge::define(name.to_string())
+    }
+
+    pub fn set(&self, value: u64) {
+        hostcalls::record_metric(self.0.id(), value).unwrap()
+    }
+
+    pub fn increment(&self, offset: i64) {
+        hostcalls::increment_metric(self.0.id(), offset).unwrap()
+    }
+
+    pub fn value(&self) -> u64 {
+        hostcalls::get_metric(self.0.id()).unwrap()
+    }
+}
+
+impl Metric for Gauge {
+    fn define(name: String) -> Gauge {
+        Gauge(Definition::new(MetricType::Gauge, name))
+    }
+
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+}
+
+#[derive(Clone)]
+pub struct Histogram(Rc<Definition>);
+
+impl Histogram {
+    pub fn new(name: &str) -> Histogram {
+        Histogram::define(name.to_string())
+    }
+
+    pub fn record(&self, value: u64) {
+        hostcalls::record_metric(self.0.id(), value).unwrap()
+    }
+}
+
+impl Metric for Histogram {
+    fn define(name: String) -> Histogram {
+        Histogram(Definition::new(MetricType::Histogram, name))
+    }
+
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+}
+
+struct Labels<M> {
+    name: String,
+    labels: Vec<String>,
+    metrics: RefCell<HashMap<Vec<String>, M>>,
+}
+
+/// A family of metrics that share a name, and differ by the values of their labels.
+///
+/// Label values are encoded into the metric name as `label=.=value;.;`, followed
+/// by the name, so that Envoy can extract them as tags with a `stats_tags` regex
+/// like `(label=\.=(.*?);\.;)`. Values must not contain `;.;`.
+#[derive(Clone)]
+pub struct Family<M>(Rc<Labels<M>>);
+
+impl<M: Metric> Family<M> {
+    pub fn new(name: &str, labels: &[&str]) -> Family<M> {
+        Family(Rc::new(Labels {
+            name: name.to_string(),
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            metrics: RefCell::new(HashMap::new()),
+        }))
+    }
+
+    /// Returns the metric for the given label values, in the order of the labels.
+    pub fn with_labels(&self, values: &[&str]) -> M {
+        let family = &self.0;
+        assert_eq!(
+            values.len(),
+            family.labels.len(),
+            "metric {} has labels {:?}",
+            family.name,
+            family.labels
+        );
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        if let Some(metric) = family.metrics.borrow().get(&values) {
+            return metric.clone();
+        }
+        let mut name = String::new();
+        for (label, value) in family.labels.iter().zip(&values) {
+            name.push_str(&format!("{label}=.={value};.;"));
+        }
+        name.push_str(&family.name);
+        let metric = M::define(name);
+        family.metrics.borrow_mut().insert(values, metric.clone());
+        metric
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_lazy_definition() {
+        testing::reset();
+        let counter = Counter::new("requests");
+        let gauge = Gauge::new("connections");
+        assert!(testing::get_metrics().is_empty());
+        counter.increment(2);
+        counter.clone().increment(1);
+        gauge.set(5);
+        gauge.increment(-2);
+        Histogram::new("latency").record(7);
+        assert_eq!(counter.value(), 3);
+        assert_eq!(gauge.value(), 3);
+        assert_eq!(
+            testing::get_metrics(),
+            vec![
+                (MetricType::Counter, "requests".to_string(), 3),
+                (MetricType::Gauge, "connections".to_string(), 3),
+                (MetricType::Histogram, "latency".to_string(), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_family() {
+        testing::reset();
+        let family = Family::<Counter>::new("requests_total", &["method", "code"]);
+        let counter = family.with_labels(&["GET", "200"]);
+        assert_eq!(counter.name(), "method=.=GET;.;code=.=200;.;requests_total");
+        counter.increment(1);
+        family.clone().with_labels(&["GET", "200"]).increment(1);
+        family.with_labels(&["POST", "500"]).increment(1);
+        assert_eq!(
+            testing::get_metrics(),
+            vec![
+                (
+                    MetricType::Counter,
+                    "method=.=GET;.;code=.=200;.;requests_total".to_string(),
+                    2
+                ),
+                (
+                    MetricType::Counter,
+                    "method=.=POST;.;code=.=500;.;requests_total".to_string(),
+                    1
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "metric requests_total has labels [\"method\"]")]
+    fn test_family_labels_mismatch() {
+        Family::<Counter>::new("requests_total", &["method"]).with_labels(&[]);
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed getters for well-known attributes.
+//!
+//! Hosts encode integers and durations as 64-bit little-endian values,
+//! timestamps as nanoseconds since the Unix epoch, booleans as a single byte,
+//! and addresses as `ip:port` strings. Values that don't match the expected
+//! encoding are reported as [`Status::ParseFailure`], and missing attributes
+//! as `Ok(None)`.
+
+use crate::hostcalls;
+use crate::types::Status;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn request_path() -> Result<Option<String>, Status> {
+    get(&["request", "path"], string)
+}
+
+pub fn request_url_path() -> Result<Option<String>, Status> {
+    get(&["request", "url_path"], string)
+}
+
+pub fn request_host() -> Result<Option<String>, Status> {
+    get(&["request", "host"], string)
+}
+
+pub fn request_scheme() -> Result<Option<String>, Status> {
+    get(&["request", "scheme"], string)
+}
+
+pub fn request_method() -> Result<Option<String>, Status> {
+    get(&["request", "method"], string)
+}
+
+pub fn request_referer() -> Result<Option<String>, Status> {
+    get(&["request", "referer"], string)
+}
+
+pub fn request_user_agent() -> Result<Option<String>, Status> {
+    get(&["request", "useragent"], string)
+}
+
+pub fn request_id() -> Result<Option<String>, Status> {
+    get(&["request", "id"], string)
+}
+
+pub fn request_protocol() -> Result<Option<String>, Status> {
+    get(&["request", "protocol"], string)
+}
+
+pub fn request_query() -> Result<Option<String>, Status> {
+    get(&["request", "query"], string)
+}
+
+pub fn request_time() -> Result<Option<SystemTime>, Status> {
+    get(&["request", "time"], timestamp)
+}
+
+pub fn request_duration() -> Result<Option<Duration>, Status> {
+    get(&["request", "duration"], duration)
+}
+
+pub fn request_size() -> Result<Option<u64>, Status> {
+    get(&["request", "size"], uint)
+}
+
+pub fn request_total_size() -> Result<Option<u64>, Status> {
+    get(&["request", "total_size"], uint)
+}
+
+pub fn response_code() -> Result<Option<u16>, Status> {
+    get(&["response", "code"], |bytes| narrow(int(bytes)?))
+}
+
+pub fn response_code_details() -> Result<Option<String>, Status> {
+    get(&["response", "code_details"], string)
+}
+
+pub fn response_flags() -> Result<Option<u64>, Status> {
+    get(&["response", "flags"], uint)
+}
+
+pub fn response_grpc_status() -> Result<Option<u32>, Status> {
+    get(&["response", "grpc_status"], |bytes| narrow(int(bytes)?))
+}
+
+pub fn response_size() -> Result<Option<u64>, Status> {
+    get(&["response", "size"], uint)
+}
+
+pub fn response_total_size() -> Result<Option<u64>, Status> {
+    get(&["response", "total_size"], uint)
+}
+
+pub fn source_address() -> Result<Option<SocketAddr>, Status> {
+    get(&["source", "address"], address)
+}
+
+pub fn source_port() -> Result<Option<u16>, Status> {
+    get(&["source", "port"], |bytes| narrow(int(bytes)?))
+}
+
+pub fn destination_address() -> Result<Option<SocketAddr>, Status> {
+    get(&["destination", "address"], address)
+}
+
+pub fn destination_port() -> Result<Option<u16>, Status> {
+    get(&["destination", "port"], |bytes| narrow(int(bytes)?))
+}
+
+pub fn connection_id() -> Result<Option<u64>, Status> {
+    get(&["connection", "id"], uint)
+}
+
+pub fn connection_mtls() -> Result<Option<bool>, Status> {
+    get(&["connection", "mtls"], boolean)
+}
+
+pub fn connection_requested_server_name() -> Result<Option<String>, Status> {
+    get(&["connection", "requested_server_name"], string)
+}
+
+pub fn connection_tls_version() -> Result<Option<String>, Status> {
+    get(&["connection", "tls_version"], string)
+}
+
+pub fn connection_subject_local_certificate() -> Result<Option<String>, Status> {
+    get(&["connection", "subject_local_certificate"], string)
+}
+
+pub fn connection_subject_peer_certificate() -> Result<Option<String>, Status> {
+    get(&["connection", "subject_peer_certificate"], string)
+}
+
+pub fn connection_termination_details() -> Result<Option<String>, Status> {
+    get(&["connection", "termination_details"], string)
+}
+
+pub fn upstream_address() -> Result<Option<SocketAddr>, Status> {
+    get(&["upstream", "address"], address)
+}
+
+pub fn upstream_port() -> Result<Option<u16>, Status> {
+    get(&["upstream", "port"], |bytes| narrow(int(bytes)?))
+}
+
+pub fn upstream_tls_version() -> Result<Option<String>, Status> {
+    get(&["upstream", "tls_version"], string)
+}
+
+pub fn upstream_transport_failure_reason() -> Result<Option<String>, Status> {
+    get(&["upstream", "transport_failure_reason"], string)
+}
+
+pub fn plugin_name() -> Result<Option<String>, Status> {
+    get(&["plugin_name"], string)
+}
+
+pub fn plugin_root_id() -> Result<Option<String>, Status> {
+    get(&["plugin_root_id"], string)
+}
+
+pub fn plugin_vm_id() -> Result<Option<String>, Status> {
+    get(&["plugin_vm_id"], string)
+}
+
+pub fn cluster_name() -> Result<Option<String>, Status> {
+    get(&["cluster_name"], string)
+}
+
+pub fn route_name() -> Result<Option<String>, Status> {
+    get(&["route_name"], string)
+}
+
+fn get<T>(path: &[&str], decode: impl Fn(&[u8]) -> Result<T, Status>) -> Result<Option<T>, Status> {
+    hostcalls::get_property(path.to_vec())?
+        .map(|bytes| decode(&bytes))
+        .transpose()
+}
+
+fn string(bytes: &[u8]) -> Result<String, Status> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| Status::ParseFailure)
+}
+
+fn int(bytes: &[u8]) -> Result<i64, Status> {
+    bytes
+        .try_into()
+        .map(i64::from_le_bytes)
+        .map_err(|_| Status::ParseFailure)
+}
+
+fn uint(bytes: &[u8]) -> Result<u64, Status> {
+    bytes
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| Status::ParseFailure)
+}
+
+fn narrow<T: TryFrom<i64>>(value: i64) -> Result<T, Status> {
+    T::try_from(value).map_err(|_| Status::ParseFailure)
+}
+
+fn boolean(bytes: &[u8]) -> Result<bool, Status> {
+    match bytes {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(Status::ParseFailure),
+    }
+}
+
+fn timestamp(bytes: &[u8]) -> Result<SystemTime, Status> {
+    let nanos = int(bytes)?;
+    let offset = Duration::from_nanos(nanos.unsigned_abs());
+    if nanos >= 0 {
+        UNIX_EPOCH.checked_add(offset)
+    } else {
+        UNIX_EPOCH.checked_sub(offset)
+    }
+    .ok_or(Status::ParseFailure)
+}
+
+fn duration(bytes: &[u8]) -> Result<Duration, Status> {
+    narrow(int(bytes)?).map(Duration::from_nanos)
+}
+
+fn address(bytes: &[u8]) -> Result<SocketAddr, Status> {
+    string(bytes)?.parse().map_err(|_| Status::ParseFailure)
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_encodings() {
+        testing::reset();
+        let time = 1_700_000_000_123_456_789i64;
+        testing::set_property(vec!["request", "time"], Some(&time.to_le_bytes()));
+        testing::set_property(vec!["response", "code"], Some(&200i64.to_le_bytes()));
+        testing::set_property(vec!["source", "address"], Some(b"[::1]:8080"));
+        testing::set_property(vec!["connection", "mtls"], Some(&[1]));
+        testing::set_property(vec!["connection", "tls_version"], Some(b"TLSv1.3"));
+        assert_eq!(
+            request_time(),
+            Ok(Some(UNIX_EPOCH + Duration::from_nanos(time as u64)))
+        );
+        assert_eq!(response_code(), Ok(Some(200)));
+        assert_eq!(source_address(), Ok(Some("[::1]:8080".parse().unwrap())));
+        assert_eq!(connection_mtls(), Ok(Some(true)));
+        assert_eq!(connection_tls_version(), Ok(Some("TLSv1.3".to_string())));
+        assert_eq!(upstream_port(), Ok(None));
+    }
+
+    #[test]
+    fn test_invalid_encodings() {
+        testing::reset();
+        testing::set_property(vec!["request", "time"], Some(&[1, 2, 3]));
+        testing::set_property(vec!["response", "code"], Some(&70000i64.to_le_bytes()));
+        testing::set_property(vec!["source", "address"], Some(b"/tmp/socket"));
+        testing::set_property(vec!["connection", "mtls"], Some(&[2]));
+        assert_eq!(request_time(), Err(Status::ParseFailure));
+        assert_eq!(response_code(), Err(Status::ParseFailure));
+        assert_eq!(source_address(), Err(Status::ParseFailure));
+        assert_eq!(connection_mtls(), Err(Status::ParseFailure));
+    }
+}
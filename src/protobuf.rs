@@ -0,0 +1,455 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoder for the `google.protobuf.Struct`, `Value` and `ListValue`
+//! well-known types, which hosts use to serialize structured properties
+//! such as `node.metadata`.
+
+use crate::types::{Bytes, Status};
+use std::collections::BTreeMap;
+
+// Nested messages deeper than this are rejected, instead of overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Object(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// Returns the field of an object, or `None` for other values.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    /// Follows a path of object fields, e.g. `["istio", "labels", "app"]`.
+    pub fn pointer(&self, path: &[&str]) -> Option<&Value> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Number(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::String(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(values: Vec<Value>) -> Value {
+        Value::Array(values)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(fields: BTreeMap<String, Value>) -> Value {
+        Value::Object(fields)
+    }
+}
+
+/// Decodes a serialized `google.protobuf.Struct` into a [`Value::Object`].
+pub fn decode_struct(bytes: &[u8]) -> Result<Value, Status> {
+    Reader::new(bytes, 0).read_struct().map(Value::Object)
+}
+
+/// Decodes a serialized `google.protobuf.Value`.
+pub fn decode_value(bytes: &[u8]) -> Result<Value, Status> {
+    Reader::new(bytes, 0).read_value()
+}
+
+/// Decodes a serialized `google.protobuf.ListValue` into a [`Value::Array`].
+pub fn decode_list_value(bytes: &[u8]) -> Result<Value, Status> {
+    Reader::new(bytes, 0).read_list().map(Value::Array)
+}
+
+/// Encodes a [`Value`] as a serialized `google.protobuf.Value`.
+pub fn encode_value(value: &Value) -> Bytes {
+    let mut bytes = Vec::new();
+    write_value(&mut bytes, value);
+    bytes
+}
+
+/// Encodes the fields of an object as a serialized `google.protobuf.Struct`.
+pub fn encode_struct(fields: &BTreeMap<String, Value>) -> Bytes {
+    let mut bytes = Vec::new();
+    for (key, value) in fields {
+        write_entry(&mut bytes, key, &encode_value(value));
+    }
+    bytes
+}
+
+// Decodes a message whose first field is a `map<string, Struct>`, ignoring
+// the other fields, e.g. `envoy.config.core.v3.Metadata`.
+pub(crate) fn decode_struct_map(bytes: &[u8]) -> Result<BTreeMap<String, Value>, Status> {
+    let mut reader = Reader::new(bytes, 0);
+    let mut map = BTreeMap::new();
+    while let Some((number, field)) = reader.read_field()? {
+        if let (1, Field::Bytes(entry)) = (number, field) {
+            let mut key = String::new();
+            let mut value = Value::Object(BTreeMap::new());
+            let mut entry = reader.nested(entry)?;
+            while let Some((number, field)) = entry.read_field()? {
+                match (number, field) {
+                    (1, Field::Bytes(bytes)) => key = string(bytes)?,
+                    (2, Field::Bytes(bytes)) => {
+                        value = Value::Object(entry.nested(bytes)?.read_struct()?)
+                    }
+                    _ => {}
+                }
+            }
+            map.insert(key, value);
+        }
+    }
+    Ok(map)
+}
+
+// Encodes a message whose first field is a `map<string, Struct>`.
+pub(crate) fn encode_struct_map(map: &[(&str, &BTreeMap<String, Value>)]) -> Bytes {
+    let mut bytes = Vec::new();
+    for (key, fields) in map {
+        write_entry(&mut bytes, key, &encode_struct(fields));
+    }
+    bytes
+}
+
+pub(crate) fn write_varint(bytes: &mut Bytes, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+pub(crate) fn write_bytes(bytes: &mut Bytes, number: u64, value: &[u8]) {
+    write_varint(bytes, number << 3 | 2);
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value);
+}
+
+// Writes a map entry as field 1, with the key and value as its fields 1 and 2.
+fn write_entry(bytes: &mut Bytes, key: &str, value: &[u8]) {
+    let mut entry = Vec::new();
+    write_bytes(&mut entry, 1, key.as_bytes());
+    write_bytes(&mut entry, 2, value);
+    write_bytes(bytes, 1, &entry);
+}
+
+fn write_value(bytes: &mut Bytes, value: &Value) {
+    match value {
+        Value::Null => {
+            write_varint(bytes, 1 << 3);
+            write_varint(bytes, 0);
+        }
+        Value::Number(number) => {
+            write_varint(bytes, 2 << 3 | 1);
+            bytes.extend_from_slice(&number.to_bits().to_le_bytes());
+        }
+        Value::String(string) => write_bytes(bytes, 3, string.as_bytes()),
+        Value::Bool(flag) => {
+            write_varint(bytes, 4 << 3);
+            write_varint(bytes, u64::from(*flag));
+        }
+        Value::Object(fields) => write_bytes(bytes, 5, &encode_struct(fields)),
+        Value::Array(values) => {
+            let mut list = Vec::new();
+            for value in values {
+                write_bytes(&mut list, 1, &encode_value(value));
+            }
+            write_bytes(bytes, 6, &list);
+        }
+    }
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], depth: usize) -> Reader<'a> {
+        Reader { bytes, depth }
+    }
+
+    fn nested(&self, bytes: &'a [u8]) -> Result<Reader<'a>, Status> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Status::ParseFailure);
+        }
+        Ok(Reader::new(bytes, self.depth + 1))
+    }
+
+    fn read_struct(mut self) -> Result<BTreeMap<String, Value>, Status> {
+        let mut fields = BTreeMap::new();
+        while let Some((number, field)) = self.read_field()? {
+            if let (1, Field::Bytes(entry)) = (number, field) {
+                let (key, value) = self.nested(entry)?.read_entry()?;
+                fields.insert(key, value);
+            }
+        }
+        Ok(fields)
+    }
+
+    // Reads a `map<string, Value>` entry, where missing fields have default values.
+    fn read_entry(mut self) -> Result<(String, Value), Status> {
+        let mut key = String::new();
+        let mut value = Value::Null;
+        while let Some((number, field)) = self.read_field()? {
+            match (number, field) {
+                (1, Field::Bytes(bytes)) => key = string(bytes)?,
+                (2, Field::Bytes(bytes)) => value = self.nested(bytes)?.read_value()?,
+                _ => {}
+            }
+        }
+        Ok((key, value))
+    }
+
+    fn read_list(mut self) -> Result<Vec<Value>, Status> {
+        let mut values = Vec::new();
+        while let Some((number, field)) = self.read_field()? {
+            if let (1, Field::Bytes(bytes)) = (number, field) {
+                values.push(self.nested(bytes)?.read_value()?);
+            }
+        }
+        Ok(values)
+    }
+
+    // Fields of a `oneof` overwrite each other, so the last one wins.
+    fn read_value(mut self) -> Result<Value, Status> {
+        let mut value = Value::Null;
+        while let Some((number, field)) = self.read_field()? {
+            value = match (number, field) {
+                (1, Field::Varint(_)) => Value::Null,
+                (2, Field::Fixed64(bits)) => Value::Number(f64::from_bits(bits)),
+                (3, Field::Bytes(bytes)) => Value::String(string(bytes)?),
+                (4, Field::Varint(flag)) => Value::Bool(flag != 0),
+                (5, Field::Bytes(bytes)) => Value::Object(self.nested(bytes)?.read_struct()?),
+                (6, Field::Bytes(bytes)) => Value::Array(self.nested(bytes)?.read_list()?),
+                _ => continue,
+            };
+        }
+        Ok(value)
+    }
+
+    fn read_field(&mut self) -> Result<Option<(u64, Field<'a>)>, Status> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+        let key = self.read_varint()?;
+        let field = match key & 0x7 {
+            0 => Field::Varint(self.read_varint()?),
+            1 => Field::Fixed64(u64::from_le_bytes(self.read_exact()?)),
+            2 => {
+                let size =
+                    usize::try_from(self.read_varint()?).map_err(|_| Status::ParseFailure)?;
+                Field::Bytes(self.read_bytes(size)?)
+            }
+            5 => {
+                self.read_exact::<4>()?;
+                Field::Fixed32
+            }
+            // Groups are deprecated, and never used by the well-known types.
+            _ => return Err(Status::ParseFailure),
+        };
+        Ok(Some((key >> 3, field)))
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Status> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let [byte] = self.read_exact()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Status::ParseFailure)
+    }
+
+    fn read_exact<const N: usize>(&mut self) -> Result<[u8; N], Status> {
+        let bytes = self.read_bytes(N)?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_bytes(&mut self, size: usize) -> Result<&'a [u8], Status> {
+        if size > self.bytes.len() {
+            return Err(Status::ParseFailure);
+        }
+        let (bytes, rest) = self.bytes.split_at(size);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+}
+
+fn string(bytes: &[u8]) -> Result<String, Status> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| Status::ParseFailure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // {"name": "ingress", "replicas": 2, "tags": ["a", true, null], "tls": {"enabled": false}}
+    static STRUCT: &[u8] = &[
+        0x0a, 0x11, 0x0a, 0x04, b'n', b'a', b'm', b'e', 0x12, 0x09, 0x1a, 0x07, b'i', b'n', b'g',
+        b'r', b'e', b's', b's', 0x0a, 0x15, 0x0a, 0x08, b'r', b'e', b'p', b'l', b'i', b'c', b'a',
+        b's', 0x12, 0x09, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x0a, 0x17, 0x0a,
+        0x04, b't', b'a', b'g', b's', 0x12, 0x0f, 0x32, 0x0d, 0x0a, 0x03, 0x1a, 0x01, b'a', 0x0a,
+        0x02, 0x20, 0x01, 0x0a, 0x02, 0x08, 0x00, 0x0a, 0x18, 0x0a, 0x03, b't', b'l', b's', 0x12,
+        0x11, 0x2a, 0x0f, 0x0a, 0x0d, 0x0a, 0x07, b'e', b'n', b'a', b'b', b'l', b'e', b'd', 0x12,
+        0x02, 0x20, 0x00,
+    ];
+
+    #[test]
+    fn test_decode_struct() {
+        let value = decode_struct(STRUCT).unwrap();
+        assert_eq!(value.get("name").and_then(Value::as_str), Some("ingress"));
+        assert_eq!(value.get("replicas").and_then(Value::as_f64), Some(2.0));
+        assert_eq!(
+            value.get("tags").and_then(Value::as_array),
+            Some(&vec![
+                Value::String("a".to_string()),
+                Value::Bool(true),
+                Value::Null
+            ])
+        );
+        assert_eq!(
+            value.pointer(&["tls", "enabled"]).and_then(Value::as_bool),
+            Some(false)
+        );
+        assert_eq!(value.pointer(&["tls", "missing"]), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        let value = decode_struct(STRUCT).unwrap();
+        assert_eq!(encode_struct(value.as_object().unwrap()), STRUCT);
+        assert_eq!(decode_value(&encode_value(&value)), Ok(value));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let truncated = &STRUCT[..STRUCT.len() - 1];
+        assert_eq!(decode_struct(truncated), Err(Status::ParseFailure));
+        assert_eq!(decode_value(&[0x1a, 0x01, 0xff]), Err(Status::ParseFailure));
+        // Values nested in single-element lists, past the maximum depth.
+        fn wrap(tag: u8, message: Vec<u8>) -> Vec<u8> {
+            let mut bytes = vec![tag];
+            let mut size = message.len();
+            while size >= 0x80 {
+                bytes.push(size as u8 | 0x80);
+                size >>= 7;
+            }
+            bytes.push(size as u8);
+            bytes.extend(message);
+            bytes
+        }
+        let mut nested = Vec::new();
+        for _ in 0..MAX_DEPTH {
+            nested = wrap(0x32, wrap(0x0a, nested));
+        }
+        assert_eq!(decode_value(&nested), Err(Status::ParseFailure));
+    }
+
+    #[test]
+    #[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+    fn test_get_struct_property() {
+        use crate::testing;
+        use crate::traits::*;
+
+        struct Root;
+
+        impl Context for Root {}
+
+        testing::reset();
+        testing::set_property(vec!["node", "metadata"], Some(STRUCT));
+        let metadata = Root.get_struct_property(vec!["node", "metadata"]).unwrap();
+        assert_eq!(
+            metadata.get("name"),
+            Some(&Value::String("ingress".to_string()))
+        );
+        testing::set_property(vec!["node", "metadata"], Some(&[0xff]));
+        let error = Root
+            .try_get_struct_property(vec!["node", "metadata"])
+            .unwrap_err();
+        assert_eq!(error.status(), Status::ParseFailure);
+    }
+}
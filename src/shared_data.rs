@@ -0,0 +1,233 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed key-value store on top of the shared data hostcalls.
+
+use crate::hostcalls;
+use crate::properties;
+use crate::types::{Bytes, Status};
+use std::marker::PhantomData;
+
+const DEFAULT_MAX_RETRIES: u32 = 8;
+
+/// Serialization of values stored in a [`SharedKv`].
+///
+/// Implement it for your own types, e.g. on top of `serde_json`.
+pub trait Codec: Sized {
+    fn encode(&self) -> Bytes;
+
+    fn decode(bytes: &[u8]) -> Result<Self, Status>;
+}
+
+impl Codec for Bytes {
+    fn encode(&self) -> Bytes {
+        self.clone()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Bytes, Status> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Codec for String {
+    fn encode(&self) -> Bytes {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<String, Status> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| Status::ParseFailure)
+    }
+}
+
+impl Codec for u64 {
+    fn encode(&self) -> Bytes {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<u64, Status> {
+        bytes
+            .try_into()
+            .map(u64::from_le_bytes)
+            .map_err(|_| Status::ParseFailure)
+    }
+}
+
+impl Codec for i64 {
+    fn encode(&self) -> Bytes {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<i64, Status> {
+        bytes
+            .try_into()
+            .map(i64::from_le_bytes)
+            .map_err(|_| Status::ParseFailure)
+    }
+}
+
+/// Shared data whose keys are prefixed with a namespace, so that plugins
+/// running in the same VM don't overwrite each other's values.
+///
+/// Keys are stored as `<namespace length>:<namespace>/<key>`, so that
+/// namespaces and keys containing `/` can't collide.
+///
+/// Removed keys and empty values are both read as missing.
+pub struct SharedKv<T> {
+    namespace: String,
+    max_retries: u32,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for SharedKv<T> {
+    fn clone(&self) -> Self {
+        SharedKv {
+            namespace: self.namespace.clone(),
+            max_retries: self.max_retries,
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T: Codec> SharedKv<T> {
+    pub fn new(namespace: &str) -> SharedKv<T> {
+        SharedKv {
+            namespace: namespace.to_string(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            value: PhantomData,
+        }
+    }
+
+    /// Uses the name of the plugin, from the `plugin_name` property, as the namespace.
+    pub fn for_plugin() -> Result<SharedKv<T>, Status> {
+        let name = properties::plugin_name()?.ok_or(Status::NotFound)?;
+        Ok(SharedKv::new(&name))
+    }
+
+    /// Sets how many times [`update`](Self::update) retries after a CAS mismatch.
+    pub fn with_max_retries(mut self, max_retries: u32) -> SharedKv<T> {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<T>, Status> {
+        self.get_with_cas(key).map(|(value, _)| value)
+    }
+
+    pub fn get_with_cas(&self, key: &str) -> Result<(Option<T>, Option<u32>), Status> {
+        let (bytes, cas) = hostcalls::get_shared_data(&self.key(key))?;
+        let value = match bytes {
+            Some(bytes) if !bytes.is_empty() => Some(T::decode(&bytes)?),
+            _ => None,
+        };
+        Ok((value, cas))
+    }
+
+    pub fn set(&self, key: &str, value: &T) -> Result<(), Status> {
+        hostcalls::set_shared_data(&self.key(key), Some(&value.encode()), None)
+    }
+
+    /// Sets the value if it wasn't modified since `cas` was returned by
+    /// [`get_with_cas`](Self::get_with_cas), or fails with [`Status::CasMismatch`].
+    pub fn set_with_cas(&self, key: &str, value: &T, cas: Option<u32>) -> Result<(), Status> {
+        hostcalls::set_shared_data(&self.key(key), Some(&value.encode()), cas)
+    }
+
+    pub fn remove(&self, key: &str) -> Result<(), Status> {
+        hostcalls::set_shared_data(&self.key(key), None, None)
+    }
+
+    /// Replaces the value with the result of `f`, and returns it.
+    ///
+    /// If another plugin modifies an existing value concurrently, `f` is called
+    /// again with the new value, up to the maximum number of retries. The first
+    /// write of a missing key is not atomic: the host has no CAS for keys that
+    /// don't exist yet, so concurrent updates can overwrite each other's values.
+    pub fn update(&self, key: &str, mut f: impl FnMut(Option<T>) -> T) -> Result<T, Status> {
+        let mut retries = 0;
+        loop {
+            let (old, cas) = self.get_with_cas(key)?;
+            let new = f(old);
+            match self.set_with_cas(key, &new, cas) {
+                Ok(()) => return Ok(new),
+                Err(Status::CasMismatch) if retries < self.max_retries => retries += 1,
+                Err(status) => return Err(status),
+            }
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}:{}/{}", self.namespace.len(), self.namespace, key)
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_namespaces() {
+        testing::reset();
+        let first = SharedKv::<String>::new("first");
+        let second = SharedKv::<String>::new("second");
+        first.set("key", &"one".to_string()).unwrap();
+        second.set("key", &"two".to_string()).unwrap();
+        assert_eq!(first.get("key"), Ok(Some("one".to_string())));
+        assert_eq!(second.get("key"), Ok(Some("two".to_string())));
+        assert_eq!(
+            hostcalls::get_shared_data("5:first/key").unwrap().0,
+            Some(b"one".to_vec())
+        );
+        first.remove("key").unwrap();
+        assert_eq!(first.get("key"), Ok(None));
+
+        SharedKv::<String>::new("a/b")
+            .set("c", &"one".to_string())
+            .unwrap();
+        assert_eq!(SharedKv::<String>::new("a").get("b/c"), Ok(None));
+
+        testing::set_property(vec!["plugin_name"], Some(b"auth"));
+        let kv = SharedKv::<u64>::for_plugin().unwrap();
+        assert_eq!(kv.namespace(), "auth");
+    }
+
+    #[test]
+    fn test_update_retries() {
+        testing::reset();
+        let kv = SharedKv::<u64>::new("counter").with_max_retries(1);
+        assert_eq!(kv.update("hits", |old| old.unwrap_or(0) + 1), Ok(1));
+
+        // Another plugin sets the value while this one is updating it.
+        let mut attempts = 0;
+        let result = kv.update("hits", |old| {
+            attempts += 1;
+            if attempts == 1 {
+                hostcalls::set_shared_data("7:counter/hits", Some(&10u64.encode()), None).unwrap();
+            }
+            old.unwrap() + 1
+        });
+        assert_eq!(result, Ok(11));
+        assert_eq!(attempts, 2);
+
+        let result = kv.update("hits", |old| {
+            hostcalls::set_shared_data("7:counter/hits", Some(&0u64.encode()), None).unwrap();
+            old.unwrap() + 1
+        });
+        assert_eq!(result, Err(Status::CasMismatch));
+    }
+}
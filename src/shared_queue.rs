@@ -0,0 +1,227 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed messages on top of the shared queue hostcalls.
+//!
+//! Each message is framed in an envelope that starts with a version byte,
+//! so that the encoding can evolve while older and newer plugins share a queue.
+
+use crate::dispatcher;
+use crate::hostcalls;
+use crate::shared_data::Codec;
+use crate::types::{Bytes, Status};
+use log::warn;
+use std::marker::PhantomData;
+
+const ENVELOPE_VERSION: u8 = 1;
+
+fn encode<T: Codec>(message: &T) -> Bytes {
+    let mut envelope = vec![ENVELOPE_VERSION];
+    envelope.extend(message.encode());
+    envelope
+}
+
+fn decode<T: Codec>(envelope: &[u8]) -> Result<T, Status> {
+    match envelope.split_first() {
+        Some((&ENVELOPE_VERSION, payload)) => T::decode(payload),
+        _ => Err(Status::ParseFailure),
+    }
+}
+
+/// Sends messages to a queue, possibly registered by a plugin in another VM.
+pub struct QueueSender<T> {
+    queue_id: u32,
+    message: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        QueueSender {
+            queue_id: self.queue_id,
+            message: PhantomData,
+        }
+    }
+}
+
+impl<T: Codec> QueueSender<T> {
+    pub fn new(queue_id: u32) -> QueueSender<T> {
+        QueueSender {
+            queue_id,
+            message: PhantomData,
+        }
+    }
+
+    /// Returns `Ok(None)` if no plugin in `vm_id` registered the queue yet.
+    pub fn resolve(vm_id: &str, name: &str) -> Result<Option<QueueSender<T>>, Status> {
+        Ok(hostcalls::resolve_shared_queue(vm_id, name)?.map(QueueSender::new))
+    }
+
+    pub fn queue_id(&self) -> u32 {
+        self.queue_id
+    }
+
+    pub fn send(&self, message: &T) -> Result<(), Status> {
+        hostcalls::enqueue_shared_queue(self.queue_id, Some(&encode(message)))
+    }
+}
+
+/// Receives messages from a queue registered by this plugin.
+pub struct QueueReceiver<T> {
+    queue_id: u32,
+    message: PhantomData<fn() -> T>,
+}
+
+impl<T: Codec + 'static> QueueReceiver<T> {
+    /// Registers the queue in the current VM, or returns the existing one.
+    pub fn register(name: &str) -> Result<QueueReceiver<T>, Status> {
+        Ok(QueueReceiver {
+            queue_id: hostcalls::register_shared_queue(name)?,
+            message: PhantomData,
+        })
+    }
+
+    pub fn queue_id(&self) -> u32 {
+        self.queue_id
+    }
+
+    pub fn receive(&self) -> Result<Option<T>, Status> {
+        receive(self.queue_id)
+    }
+
+    /// Returns all pending messages.
+    pub fn drain(&self) -> Result<Vec<T>, Status> {
+        let mut messages = Vec::new();
+        while let Some(message) = self.receive()? {
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    /// Drains the queue each time the host reports that it's ready, and passes
+    /// the messages to `handler` instead of [`RootContext::on_queue_ready`].
+    ///
+    /// Messages that can't be decoded are logged and dropped.
+    ///
+    /// [`RootContext::on_queue_ready`]: crate::traits::RootContext::on_queue_ready
+    pub fn on_messages(self, mut handler: impl FnMut(Vec<T>) + 'static) {
+        let queue_id = self.queue_id;
+        dispatcher::register_queue_handler(
+            queue_id,
+            Box::new(move || {
+                let mut messages = Vec::new();
+                loop {
+                    match receive(queue_id) {
+                        Ok(Some(message)) => messages.push(message),
+                        Ok(None) => break,
+                        Err(Status::ParseFailure) => {
+                            warn!("dropped malformed message from queue {queue_id}")
+                        }
+                        Err(status) => {
+                            warn!("failed to dequeue from queue {queue_id}: {status:?}");
+                            break;
+                        }
+                    }
+                }
+                if !messages.is_empty() {
+                    handler(messages)
+                }
+            }),
+        );
+    }
+}
+
+fn receive<T: Codec>(queue_id: u32) -> Result<Option<T>, Status> {
+    hostcalls::dequeue_shared_queue(queue_id)?
+        .map(|envelope| decode(&envelope))
+        .transpose()
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::harness::Harness;
+    use crate::testing;
+    use crate::traits::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+    static RECEIVED: RefCell<Vec<Vec<String>>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct Shipper;
+
+    impl Context for Shipper {}
+
+    impl RootContext for Shipper {
+        fn on_configure(&mut self, _: usize) -> bool {
+            let receiver = QueueReceiver::<String>::register("logs").unwrap();
+            receiver.on_messages(|messages| RECEIVED.with(|r| r.borrow_mut().push(messages)));
+            true
+        }
+
+        fn on_queue_ready(&mut self, _: u32) {
+            panic!("queue should be drained by the handler");
+        }
+    }
+
+    #[test]
+    fn test_envelope() {
+        testing::reset();
+        testing::set_vm_id("vm");
+        let receiver = QueueReceiver::<String>::register("logs").unwrap();
+        let sender = QueueSender::<String>::resolve("vm", "logs")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sender.queue_id(), receiver.queue_id());
+        sender.send(&"one".to_string()).unwrap();
+        sender.send(&"two".to_string()).unwrap();
+        assert_eq!(
+            testing::get_shared_queue(receiver.queue_id()),
+            Some(vec![b"\x01one".to_vec(), b"\x01two".to_vec()])
+        );
+        assert_eq!(
+            receiver.drain(),
+            Ok(vec!["one".to_string(), "two".to_string()])
+        );
+        assert_eq!(receiver.receive(), Ok(None));
+
+        testing::enqueue_shared_queue(receiver.queue_id(), b"\x02one").unwrap();
+        assert_eq!(receiver.receive(), Err(Status::ParseFailure));
+        assert!(
+            QueueSender::<String>::resolve("vm", "other")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_on_messages() {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Shipper));
+        testing::set_vm_id("vm");
+        harness.start();
+        let sender = QueueSender::<String>::resolve("vm", "logs")
+            .unwrap()
+            .unwrap();
+        sender.send(&"one".to_string()).unwrap();
+        testing::enqueue_shared_queue(sender.queue_id(), b"\x02three").unwrap();
+        sender.send(&"two".to_string()).unwrap();
+        harness.queue_ready(sender.queue_id());
+        harness.queue_ready(sender.queue_id());
+        assert_eq!(
+            RECEIVED.with(|r| r.take()),
+            vec![vec!["one".to_string(), "two".to_string()]]
+        );
+        assert_eq!(testing::get_shared_queue(sender.queue_id()), Some(vec![]));
+    }
+}
@@ -0,0 +1,1666 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory host for native (non-Wasm) builds.
+//!
+//! With the `testing` feature enabled, this module provides an implementation
+//! of every `env` import used by [`hostcalls`](crate::hostcalls), so plugins
+//! can be exercised with plain `cargo test`. The host state is thread-local,
+//! which keeps tests running in parallel isolated from each other.
+
+pub use crate::harness::{Harness, HttpStream};
+
+use crate::allocator;
+use crate::types::*;
+use hashbrown::HashMap;
+use std::cell::RefCell;
+use std::ptr::null_mut;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub type ForeignFunction = Box<dyn FnMut(&[u8]) -> Result<Option<Bytes>, Status>>;
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct HttpCall {
+    pub token_id: u32,
+    pub upstream: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Bytes>,
+    pub trailers: Vec<(String, String)>,
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct GrpcCall {
+    pub token_id: u32,
+    pub upstream: String,
+    pub service: String,
+    pub method: String,
+    pub initial_metadata: Vec<(String, Bytes)>,
+    pub message: Option<Bytes>,
+    pub timeout: Duration,
+    pub cancelled: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct GrpcStream {
+    pub token_id: u32,
+    pub upstream: String,
+    pub service: String,
+    pub method: String,
+    pub initial_metadata: Vec<(String, Bytes)>,
+    pub messages: Vec<Bytes>,
+    pub end_stream: bool,
+    pub cancelled: bool,
+    pub closed: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LocalResponse {
+    pub status_code: u32,
+    pub status_code_details: String,
+    pub body: Bytes,
+    pub headers: Vec<(String, Bytes)>,
+    pub grpc_status: i32,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Metric {
+    metric_type: MetricType,
+    name: String,
+    value: u64,
+}
+
+/// Callback currently being driven by the [`Harness`], used to validate that
+/// hostcalls are only made when the host would allow them.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Phase {
+    VmStart,
+    Configure,
+    Tick,
+    QueueReady,
+    RequestHeaders,
+    RequestBody,
+    RequestTrailers,
+    ResponseHeaders,
+    ResponseBody,
+    ResponseTrailers,
+    #[cfg(proxy_abi = "vnext")]
+    RequestMetadata,
+    #[cfg(proxy_abi = "vnext")]
+    ResponseMetadata,
+    HttpCallResponse,
+    Log,
+    Done,
+}
+
+impl Phase {
+    fn callback(self) -> &'static str {
+        match self {
+            Phase::VmStart => "on_vm_start",
+            Phase::Configure => "on_configure",
+            Phase::Tick => "on_tick",
+            Phase::QueueReady => "on_queue_ready",
+            Phase::RequestHeaders => "on_http_request_headers",
+            Phase::RequestBody => "on_http_request_body",
+            Phase::RequestTrailers => "on_http_request_trailers",
+            Phase::ResponseHeaders => "on_http_response_headers",
+            Phase::ResponseBody => "on_http_response_body",
+            Phase::ResponseTrailers => "on_http_response_trailers",
+            #[cfg(proxy_abi = "vnext")]
+            Phase::RequestMetadata => "on_http_request_metadata",
+            #[cfg(proxy_abi = "vnext")]
+            Phase::ResponseMetadata => "on_http_response_metadata",
+            Phase::HttpCallResponse => "on_http_call_response",
+            Phase::Log => "on_log",
+            Phase::Done => "on_done",
+        }
+    }
+
+    fn allows_map(self, map_type: MapType, write: bool) -> bool {
+        use Phase::*;
+        match (map_type, write) {
+            (MapType::HttpRequestHeaders, true) => {
+                matches!(self, RequestHeaders | HttpCallResponse)
+            }
+            (MapType::HttpRequestHeaders, false) => matches!(
+                self,
+                RequestHeaders
+                    | RequestBody
+                    | RequestTrailers
+                    | ResponseHeaders
+                    | ResponseBody
+                    | ResponseTrailers
+                    | HttpCallResponse
+                    | Log
+            ),
+            (MapType::HttpRequestTrailers, true) => {
+                matches!(self, RequestTrailers | HttpCallResponse)
+            }
+            (MapType::HttpRequestTrailers, false) => matches!(
+                self,
+                RequestTrailers
+                    | ResponseHeaders
+                    | ResponseBody
+                    | ResponseTrailers
+                    | HttpCallResponse
+                    | Log
+            ),
+            (MapType::HttpResponseHeaders, true) => {
+                matches!(self, ResponseHeaders | HttpCallResponse)
+            }
+            (MapType::HttpResponseHeaders, false) => matches!(
+                self,
+                ResponseHeaders | ResponseBody | ResponseTrailers | HttpCallResponse | Log
+            ),
+            (MapType::HttpResponseTrailers, true) => {
+                matches!(self, ResponseTrailers | HttpCallResponse)
+            }
+            (MapType::HttpResponseTrailers, false) => {
+                matches!(self, ResponseTrailers | HttpCallResponse | Log)
+            }
+            (MapType::HttpCallResponseHeaders | MapType::HttpCallResponseTrailers, false) => {
+                self == HttpCallResponse
+            }
+            #[cfg(proxy_abi = "vnext")]
+            (MapType::HttpRequestMetadata, _) => self == RequestMetadata,
+            #[cfg(proxy_abi = "vnext")]
+            (MapType::HttpResponseMetadata, _) => self == ResponseMetadata,
+            _ => false,
+        }
+    }
+
+    fn allows_buffer(self, buffer_type: BufferType, write: bool) -> bool {
+        use Phase::*;
+        match (buffer_type, write) {
+            // Bodies paused until the trailers are still buffered in the trailers callback.
+            (BufferType::HttpRequestBody, _) => {
+                matches!(self, RequestBody | RequestTrailers | HttpCallResponse)
+            }
+            (BufferType::HttpResponseBody, _) => {
+                matches!(self, ResponseBody | ResponseTrailers | HttpCallResponse)
+            }
+            (BufferType::HttpCallResponseBody, false) => self == HttpCallResponse,
+            (BufferType::VmConfiguration, false) => self == VmStart,
+            (BufferType::PluginConfiguration, false) => self == Configure,
+            _ => false,
+        }
+    }
+
+    fn allows_local_response(self) -> bool {
+        use Phase::*;
+        matches!(
+            self,
+            RequestHeaders
+                | RequestBody
+                | RequestTrailers
+                | ResponseHeaders
+                | ResponseBody
+                | ResponseTrailers
+                | HttpCallResponse
+        )
+    }
+}
+
+struct Queue {
+    vm_id: String,
+    name: String,
+    items: Vec<Bytes>,
+}
+
+struct Host {
+    vm_id: String,
+    log_level: LogLevel,
+    logs: Vec<(LogLevel, String)>,
+    current_time: SystemTime,
+    tick_period: Duration,
+    buffers: HashMap<BufferType, Bytes>,
+    maps: HashMap<MapType, Vec<(String, Bytes)>>,
+    properties: HashMap<Bytes, Bytes>,
+    shared_data: HashMap<String, (Bytes, u32)>,
+    queues: Vec<Queue>,
+    metrics: Vec<Metric>,
+    next_token_id: u32,
+    http_calls: Vec<HttpCall>,
+    grpc_calls: Vec<GrpcCall>,
+    grpc_streams: Vec<GrpcStream>,
+    grpc_status: (u32, Option<String>),
+    local_response: Option<LocalResponse>,
+    resumed_streams: Vec<StreamType>,
+    closed_streams: Vec<StreamType>,
+    effective_context_id: u32,
+    foreign_functions: HashMap<String, ForeignFunction>,
+    done_calls: usize,
+    phase: Option<Phase>,
+    violations: Vec<String>,
+}
+
+impl Host {
+    fn new() -> Host {
+        Host {
+            vm_id: String::new(),
+            log_level: LogLevel::Trace,
+            logs: Vec::new(),
+            current_time: UNIX_EPOCH,
+            tick_period: Duration::ZERO,
+            buffers: HashMap::new(),
+            maps: HashMap::new(),
+            properties: HashMap::new(),
+            shared_data: HashMap::new(),
+            queues: Vec::new(),
+            metrics: Vec::new(),
+            next_token_id: 1,
+            http_calls: Vec::new(),
+            grpc_calls: Vec::new(),
+            grpc_streams: Vec::new(),
+            grpc_status: (0, None),
+            local_response: None,
+            resumed_streams: Vec::new(),
+            closed_streams: Vec::new(),
+            effective_context_id: 0,
+            foreign_functions: HashMap::new(),
+            done_calls: 0,
+            phase: None,
+            violations: Vec::new(),
+        }
+    }
+
+    fn next_token_id(&mut self) -> u32 {
+        let token_id = self.next_token_id;
+        self.next_token_id += 1;
+        token_id
+    }
+
+    // Hostcalls made outside of a callback driven by the harness (e.g. from
+    // the test itself) are always allowed.
+    fn check(&mut self, hostcall: &str, allowed: impl FnOnce(Phase) -> bool) -> bool {
+        match self.phase {
+            Some(phase) if !allowed(phase) => {
+                let violation = format!("{hostcall} is not allowed in {}", phase.callback());
+                self.violations.push(violation);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    fn check_map(&mut self, hostcall: &str, map_type: MapType, write: bool) -> bool {
+        self.check(&format!("{hostcall}({map_type:?})"), |phase| {
+            phase.allows_map(map_type, write)
+        })
+    }
+
+    fn check_buffer(&mut self, hostcall: &str, buffer_type: BufferType, write: bool) -> bool {
+        self.check(&format!("{hostcall}({buffer_type:?})"), |phase| {
+            phase.allows_buffer(buffer_type, write)
+        })
+    }
+
+    fn map_get(&self, map_type: MapType, key: &str) -> Option<&Bytes> {
+        self.maps
+            .get(&map_type)?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    fn map_remove(&mut self, map_type: MapType, key: &str) {
+        if let Some(map) = self.maps.get_mut(&map_type) {
+            map.retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+        }
+    }
+
+    fn map_add(&mut self, map_type: MapType, key: &str, value: &[u8]) {
+        self.maps
+            .entry(map_type)
+            .or_default()
+            .push((key.to_lowercase(), value.to_vec()));
+    }
+}
+
+thread_local! {
+static HOST: RefCell<Host> = RefCell::new(Host::new());
+}
+
+fn with_host<T>(f: impl FnOnce(&mut Host) -> T) -> T {
+    HOST.with(|host| f(&mut host.borrow_mut()))
+}
+
+/// Resets the host to its initial state.
+///
+/// Token IDs keep increasing across resets, since the dispatcher might still
+/// be tracking callouts issued before the reset.
+pub fn reset() {
+    with_host(|host| {
+        let next_token_id = host.next_token_id;
+        *host = Host::new();
+        host.next_token_id = next_token_id;
+    })
+}
+
+pub fn set_vm_id(vm_id: &str) {
+    with_host(|host| host.vm_id = vm_id.to_string())
+}
+
+pub fn set_log_level(level: LogLevel) {
+    with_host(|host| host.log_level = level)
+}
+
+pub fn get_logs() -> Vec<(LogLevel, String)> {
+    with_host(|host| host.logs.clone())
+}
+
+pub fn take_logs() -> Vec<(LogLevel, String)> {
+    with_host(|host| std::mem::take(&mut host.logs))
+}
+
+pub fn set_current_time(time: SystemTime) {
+    with_host(|host| host.current_time = time)
+}
+
+pub fn get_tick_period() -> Option<Duration> {
+    with_host(|host| match host.tick_period {
+        Duration::ZERO => None,
+        period => Some(period),
+    })
+}
+
+pub fn set_buffer(buffer_type: BufferType, value: Option<&[u8]>) {
+    with_host(|host| match value {
+        Some(value) => {
+            host.buffers.insert(buffer_type, value.to_vec());
+        }
+        None => {
+            host.buffers.remove(&buffer_type);
+        }
+    })
+}
+
+pub fn get_buffer(buffer_type: BufferType) -> Option<Bytes> {
+    with_host(|host| host.buffers.get(&buffer_type).cloned())
+}
+
+pub fn set_map(map_type: MapType, map: Vec<(&str, &str)>) {
+    let map = map
+        .into_iter()
+        .map(|(name, value)| (name, value.as_bytes()))
+        .collect();
+    set_map_bytes(map_type, map)
+}
+
+pub fn set_map_bytes(map_type: MapType, map: Vec<(&str, &[u8])>) {
+    with_host(|host| {
+        host.maps.insert(
+            map_type,
+            map.into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_vec()))
+                .collect(),
+        );
+    })
+}
+
+pub fn get_map(map_type: MapType) -> Vec<(String, String)> {
+    get_map_bytes(map_type)
+        .into_iter()
+        .map(|(name, value)| (name, String::from_utf8_lossy(&value).into_owned()))
+        .collect()
+}
+
+pub fn get_map_bytes(map_type: MapType) -> Vec<(String, Bytes)> {
+    with_host(|host| host.maps.get(&map_type).cloned().unwrap_or_default())
+}
+
+pub fn get_map_value(map_type: MapType, name: &str) -> Option<String> {
+    with_host(|host| {
+        host.map_get(map_type, name)
+            .map(|value| String::from_utf8_lossy(value).into_owned())
+    })
+}
+
+pub fn set_property(path: Vec<&str>, value: Option<&[u8]>) {
+    let path = path.join("\0").into_bytes();
+    with_host(|host| match value {
+        Some(value) => {
+            host.properties.insert(path, value.to_vec());
+        }
+        None => {
+            host.properties.remove(&path);
+        }
+    })
+}
+
+pub fn get_property(path: Vec<&str>) -> Option<Bytes> {
+    let path = path.join("\0").into_bytes();
+    with_host(|host| host.properties.get(&path).cloned())
+}
+
+pub fn set_shared_data(key: &str, value: Option<&[u8]>) {
+    with_host(|host| match value {
+        Some(value) => {
+            let cas = host.shared_data.get(key).map_or(0, |(_, cas)| *cas) + 1;
+            host.shared_data
+                .insert(key.to_string(), (value.to_vec(), cas));
+        }
+        None => {
+            host.shared_data.remove(key);
+        }
+    })
+}
+
+pub fn get_shared_data(key: &str) -> (Option<Bytes>, Option<u32>) {
+    with_host(|host| match host.shared_data.get(key) {
+        Some((value, cas)) => (Some(value.clone()), Some(*cas)),
+        None => (None, None),
+    })
+}
+
+pub fn register_shared_queue(vm_id: &str, name: &str) -> u32 {
+    with_host(|host| {
+        if let Some(index) = host
+            .queues
+            .iter()
+            .position(|queue| queue.vm_id == vm_id && queue.name == name)
+        {
+            return index as u32 + 1;
+        }
+        host.queues.push(Queue {
+            vm_id: vm_id.to_string(),
+            name: name.to_string(),
+            items: Vec::new(),
+        });
+        host.queues.len() as u32
+    })
+}
+
+pub fn enqueue_shared_queue(queue_id: u32, value: &[u8]) -> Result<(), Status> {
+    with_host(
+        |host| match host.queues.get_mut((queue_id as usize).wrapping_sub(1)) {
+            Some(queue) => {
+                queue.items.push(value.to_vec());
+                Ok(())
+            }
+            None => Err(Status::NotFound),
+        },
+    )
+}
+
+pub fn get_shared_queue(queue_id: u32) -> Option<Vec<Bytes>> {
+    with_host(|host| {
+        host.queues
+            .get((queue_id as usize).wrapping_sub(1))
+            .map(|queue| queue.items.clone())
+    })
+}
+
+pub fn get_metric(name: &str) -> Option<(MetricType, u64)> {
+    with_host(|host| {
+        host.metrics
+            .iter()
+            .find(|metric| metric.name == name)
+            .map(|metric| (metric.metric_type, metric.value))
+    })
+}
+
+pub fn get_metrics() -> Vec<(MetricType, String, u64)> {
+    with_host(|host| {
+        host.metrics
+            .iter()
+            .map(|metric| (metric.metric_type, metric.name.clone(), metric.value))
+            .collect()
+    })
+}
+
+pub fn get_http_calls() -> Vec<HttpCall> {
+    with_host(|host| host.http_calls.clone())
+}
+
+pub fn take_http_calls() -> Vec<HttpCall> {
+    with_host(|host| std::mem::take(&mut host.http_calls))
+}
+
+pub fn get_grpc_calls() -> Vec<GrpcCall> {
+    with_host(|host| host.grpc_calls.clone())
+}
+
+pub fn take_grpc_calls() -> Vec<GrpcCall> {
+    with_host(|host| std::mem::take(&mut host.grpc_calls))
+}
+
+pub fn get_grpc_streams() -> Vec<GrpcStream> {
+    with_host(|host| host.grpc_streams.clone())
+}
+
+pub fn set_grpc_status(status_code: u32, message: Option<&str>) {
+    with_host(|host| host.grpc_status = (status_code, message.map(str::to_string)))
+}
+
+pub fn get_local_response() -> Option<LocalResponse> {
+    with_host(|host| host.local_response.clone())
+}
+
+pub fn take_local_response() -> Option<LocalResponse> {
+    with_host(|host| host.local_response.take())
+}
+
+pub fn get_resumed_streams() -> Vec<StreamType> {
+    with_host(|host| host.resumed_streams.clone())
+}
+
+pub fn get_closed_streams() -> Vec<StreamType> {
+    with_host(|host| host.closed_streams.clone())
+}
+
+pub fn get_effective_context() -> u32 {
+    with_host(|host| host.effective_context_id)
+}
+
+pub fn set_foreign_function(name: &str, function: ForeignFunction) {
+    with_host(|host| {
+        host.foreign_functions.insert(name.to_string(), function);
+    })
+}
+
+pub fn get_done_calls() -> usize {
+    with_host(|host| host.done_calls)
+}
+
+pub(crate) fn set_phase(phase: Option<Phase>) {
+    with_host(|host| host.phase = phase)
+}
+
+pub(crate) fn take_violations() -> Vec<String> {
+    with_host(|host| std::mem::take(&mut host.violations))
+}
+
+unsafe fn slice<'a>(data: *const u8, size: usize) -> &'a [u8] {
+    if data.is_null() || size == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data, size) }
+    }
+}
+
+unsafe fn string(data: *const u8, size: usize) -> String {
+    String::from_utf8_lossy(unsafe { slice(data, size) }).into_owned()
+}
+
+unsafe fn write_bytes(value: &[u8], return_data: *mut *mut u8, return_size: *mut usize) {
+    unsafe {
+        if value.is_empty() {
+            *return_data = null_mut();
+            *return_size = 0;
+        } else {
+            let data = allocator::proxy_on_memory_allocate(value.len());
+            std::ptr::copy_nonoverlapping(value.as_ptr(), data, value.len());
+            *return_data = data;
+            *return_size = value.len();
+        }
+    }
+}
+
+fn serialize_map(map: &[(String, Bytes)]) -> Bytes {
+    let mut bytes: Bytes = Vec::new();
+    bytes.extend_from_slice(&(map.len() as u32).to_le_bytes());
+    for (name, value) in map {
+        bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    }
+    for (name, value) in map {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(value);
+        bytes.push(0);
+    }
+    bytes
+}
+
+fn deserialize_map(bytes: &[u8]) -> Option<Vec<(String, Bytes)>> {
+    if bytes.is_empty() {
+        return Some(Vec::new());
+    }
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let data = bytes.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(data.try_into().ok()?) as usize)
+    };
+    let size = read_u32(0)?;
+    // Sizes of all keys and values must fit before the data.
+    let mut p = size
+        .checked_mul(8)
+        .and_then(|sizes| sizes.checked_add(4))
+        .filter(|&p| p <= bytes.len())?;
+    let mut map = Vec::with_capacity(size);
+    for n in 0..size {
+        let key_size = read_u32(4 + n * 8)?;
+        let value_size = read_u32(8 + n * 8)?;
+        let key = bytes.get(p..p.checked_add(key_size)?)?;
+        p += key_size + 1;
+        let value = bytes.get(p..p.checked_add(value_size)?)?;
+        p += value_size + 1;
+        map.push((String::from_utf8_lossy(key).to_lowercase(), value.to_vec()));
+    }
+    Some(map)
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_log(
+    level: LogLevel,
+    message_data: *const u8,
+    message_size: usize,
+) -> Status {
+    let message = unsafe { string(message_data, message_size) };
+    with_host(|host| host.logs.push((level, message)));
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_get_log_level(return_level: *mut LogLevel) -> Status {
+    unsafe { *return_level = with_host(|host| host.log_level) };
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_get_current_time_nanoseconds(return_time: *mut u64) -> Status {
+    let time = with_host(|host| host.current_time);
+    let nanos = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    unsafe { *return_time = nanos as u64 };
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_set_tick_period_milliseconds(period: u32) -> Status {
+    with_host(|host| host.tick_period = Duration::from_millis(period as u64));
+    Status::Ok
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_get_buffer"))]
+unsafe extern "C" fn proxy_get_buffer_bytes(
+    buffer_type: BufferType,
+    start: usize,
+    max_size: usize,
+    return_buffer_data: *mut *mut u8,
+    return_buffer_size: *mut usize,
+) -> Status {
+    with_host(|host| {
+        if !host.check_buffer("proxy_get_buffer_bytes", buffer_type, false) {
+            return Status::BadArgument;
+        }
+        match host.buffers.get(&buffer_type) {
+            Some(buffer) => {
+                let start = start.min(buffer.len());
+                let end = start.saturating_add(max_size).min(buffer.len());
+                unsafe { write_bytes(&buffer[start..end], return_buffer_data, return_buffer_size) };
+                Status::Ok
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_set_buffer"))]
+unsafe extern "C" fn proxy_set_buffer_bytes(
+    buffer_type: BufferType,
+    start: usize,
+    size: usize,
+    buffer_data: *const u8,
+    buffer_size: usize,
+) -> Status {
+    let value = unsafe { slice(buffer_data, buffer_size) };
+    with_host(|host| {
+        if !host.check_buffer("proxy_set_buffer_bytes", buffer_type, true) {
+            return Status::BadArgument;
+        }
+        let buffer = host.buffers.entry(buffer_type).or_default();
+        if start > buffer.len() {
+            return Status::BadArgument;
+        }
+        let end = start.saturating_add(size).min(buffer.len());
+        buffer.splice(start..end, value.iter().copied());
+        Status::Ok
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_get_map"))]
+unsafe extern "C" fn proxy_get_header_map_pairs(
+    map_type: MapType,
+    return_map_data: *mut *mut u8,
+    return_map_size: *mut usize,
+) -> Status {
+    with_host(|host| {
+        if !host.check_map("proxy_get_header_map_pairs", map_type, false) {
+            return Status::BadArgument;
+        }
+        let map = host.maps.get(&map_type).map_or(&[][..], |map| map);
+        unsafe { write_bytes(&serialize_map(map), return_map_data, return_map_size) };
+        Status::Ok
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_set_map"))]
+unsafe extern "C" fn proxy_set_header_map_pairs(
+    map_type: MapType,
+    map_data: *const u8,
+    map_size: usize,
+) -> Status {
+    let map = match deserialize_map(unsafe { slice(map_data, map_size) }) {
+        Some(map) => map,
+        None => return Status::ParseFailure,
+    };
+    with_host(|host| {
+        if !host.check_map("proxy_set_header_map_pairs", map_type, true) {
+            return Status::BadArgument;
+        }
+        host.maps.insert(map_type, map);
+        Status::Ok
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_get_map_value"))]
+unsafe extern "C" fn proxy_get_header_map_value(
+    map_type: MapType,
+    key_data: *const u8,
+    key_size: usize,
+    return_value_data: *mut *mut u8,
+    return_value_size: *mut usize,
+) -> Status {
+    let key = unsafe { string(key_data, key_size) };
+    with_host(|host| {
+        if !host.check_map("proxy_get_header_map_value", map_type, false) {
+            return Status::BadArgument;
+        }
+        match host.map_get(map_type, &key) {
+            Some(value) => {
+                unsafe { write_bytes(value, return_value_data, return_value_size) };
+                Status::Ok
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_remove_map_value"))]
+unsafe extern "C" fn proxy_remove_header_map_value(
+    map_type: MapType,
+    key_data: *const u8,
+    key_size: usize,
+) -> Status {
+    let key = unsafe { string(key_data, key_size) };
+    with_host(|host| {
+        if !host.check_map("proxy_remove_header_map_value", map_type, true) {
+            return Status::BadArgument;
+        }
+        host.map_remove(map_type, &key);
+        Status::Ok
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_set_map_value"))]
+unsafe extern "C" fn proxy_replace_header_map_value(
+    map_type: MapType,
+    key_data: *const u8,
+    key_size: usize,
+    value_data: *const u8,
+    value_size: usize,
+) -> Status {
+    let key = unsafe { string(key_data, key_size) };
+    let value = unsafe { slice(value_data, value_size) };
+    with_host(|host| {
+        if !host.check_map("proxy_replace_header_map_value", map_type, true) {
+            return Status::BadArgument;
+        }
+        host.map_remove(map_type, &key);
+        host.map_add(map_type, &key, value);
+        Status::Ok
+    })
+}
+
+#[cfg_attr(not(proxy_abi = "vnext"), unsafe(no_mangle))]
+#[cfg_attr(proxy_abi = "vnext", unsafe(export_name = "proxy_add_map_value"))]
+unsafe extern "C" fn proxy_add_header_map_value(
+    map_type: MapType,
+    key_data: *const u8,
+    key_size: usize,
+    value_data: *const u8,
+    value_size: usize,
+) -> Status {
+    let key = unsafe { string(key_data, key_size) };
+    let value = unsafe { slice(value_data, value_size) };
+    with_host(|host| {
+        if !host.check_map("proxy_add_header_map_value", map_type, true) {
+            return Status::BadArgument;
+        }
+        host.map_add(map_type, &key, value);
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_get_property(
+    path_data: *const u8,
+    path_size: usize,
+    return_value_data: *mut *mut u8,
+    return_value_size: *mut usize,
+) -> Status {
+    let path = unsafe { slice(path_data, path_size) };
+    with_host(|host| match host.properties.get(path) {
+        Some(value) => {
+            unsafe { write_bytes(value, return_value_data, return_value_size) };
+            Status::Ok
+        }
+        None => Status::NotFound,
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_set_property(
+    path_data: *const u8,
+    path_size: usize,
+    value_data: *const u8,
+    value_size: usize,
+) -> Status {
+    let path = unsafe { slice(path_data, path_size) }.to_vec();
+    let value = unsafe { slice(value_data, value_size) }.to_vec();
+    with_host(|host| host.properties.insert(path, value));
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_get_shared_data(
+    key_data: *const u8,
+    key_size: usize,
+    return_value_data: *mut *mut u8,
+    return_value_size: *mut usize,
+    return_cas: *mut u32,
+) -> Status {
+    let key = unsafe { string(key_data, key_size) };
+    with_host(|host| match host.shared_data.get(&key) {
+        Some((value, cas)) => {
+            unsafe {
+                write_bytes(value, return_value_data, return_value_size);
+                *return_cas = *cas;
+            }
+            Status::Ok
+        }
+        None => Status::NotFound,
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_set_shared_data(
+    key_data: *const u8,
+    key_size: usize,
+    value_data: *const u8,
+    value_size: usize,
+    cas: u32,
+) -> Status {
+    let key = unsafe { string(key_data, key_size) };
+    let value = unsafe { slice(value_data, value_size) };
+    with_host(|host| {
+        let current_cas = host.shared_data.get(&key).map_or(0, |(_, cas)| *cas);
+        if cas != 0 && cas != current_cas {
+            return Status::CasMismatch;
+        }
+        if value_data.is_null() {
+            host.shared_data.remove(&key);
+        } else {
+            host.shared_data
+                .insert(key, (value.to_vec(), current_cas + 1));
+        }
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_register_shared_queue(
+    name_data: *const u8,
+    name_size: usize,
+    return_id: *mut u32,
+) -> Status {
+    let name = unsafe { string(name_data, name_size) };
+    let vm_id = with_host(|host| host.vm_id.clone());
+    unsafe { *return_id = register_shared_queue(&vm_id, &name) };
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_resolve_shared_queue(
+    vm_id_data: *const u8,
+    vm_id_size: usize,
+    name_data: *const u8,
+    name_size: usize,
+    return_id: *mut u32,
+) -> Status {
+    let vm_id = unsafe { string(vm_id_data, vm_id_size) };
+    let name = unsafe { string(name_data, name_size) };
+    with_host(|host| {
+        match host
+            .queues
+            .iter()
+            .position(|queue| queue.vm_id == vm_id && queue.name == name)
+        {
+            Some(index) => {
+                unsafe { *return_id = index as u32 + 1 };
+                Status::Ok
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_dequeue_shared_queue(
+    queue_id: u32,
+    return_value_data: *mut *mut u8,
+    return_value_size: *mut usize,
+) -> Status {
+    with_host(
+        |host| match host.queues.get_mut((queue_id as usize).wrapping_sub(1)) {
+            Some(queue) if queue.items.is_empty() => Status::Empty,
+            Some(queue) => {
+                let value = queue.items.remove(0);
+                unsafe { write_bytes(&value, return_value_data, return_value_size) };
+                Status::Ok
+            }
+            None => Status::NotFound,
+        },
+    )
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_enqueue_shared_queue(
+    queue_id: u32,
+    value_data: *const u8,
+    value_size: usize,
+) -> Status {
+    let value = unsafe { slice(value_data, value_size) };
+    match enqueue_shared_queue(queue_id, value) {
+        Ok(()) => Status::Ok,
+        Err(status) => status,
+    }
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_continue_stream(stream_type: StreamType) -> Status {
+    with_host(|host| host.resumed_streams.push(stream_type));
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_continue_request() -> Status {
+    proxy_continue_stream(StreamType::HttpRequest)
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_continue_response() -> Status {
+    proxy_continue_stream(StreamType::HttpResponse)
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_close_stream(stream_type: StreamType) -> Status {
+    with_host(|host| host.closed_streams.push(stream_type));
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn proxy_send_local_response(
+    status_code: u32,
+    status_code_details_data: *const u8,
+    status_code_details_size: usize,
+    body_data: *const u8,
+    body_size: usize,
+    headers_data: *const u8,
+    headers_size: usize,
+    grpc_status: i32,
+) -> Status {
+    let headers = match deserialize_map(unsafe { slice(headers_data, headers_size) }) {
+        Some(headers) => headers,
+        None => return Status::ParseFailure,
+    };
+    let response = LocalResponse {
+        status_code,
+        status_code_details: unsafe { string(status_code_details_data, status_code_details_size) },
+        body: unsafe { slice(body_data, body_size) }.to_vec(),
+        headers,
+        grpc_status,
+    };
+    with_host(|host| {
+        if !host.check("proxy_send_local_response", Phase::allows_local_response) {
+            return Status::BadArgument;
+        }
+        host.local_response = Some(response);
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn proxy_http_call(
+    upstream_data: *const u8,
+    upstream_size: usize,
+    headers_data: *const u8,
+    headers_size: usize,
+    body_data: *const u8,
+    body_size: usize,
+    trailers_data: *const u8,
+    trailers_size: usize,
+    timeout: u32,
+    return_token: *mut u32,
+) -> Status {
+    let (Some(headers), Some(trailers)) = (
+        deserialize_map(unsafe { slice(headers_data, headers_size) }),
+        deserialize_map(unsafe { slice(trailers_data, trailers_size) }),
+    ) else {
+        return Status::ParseFailure;
+    };
+    let lossy = |map: Vec<(String, Bytes)>| -> Vec<(String, String)> {
+        map.into_iter()
+            .map(|(name, value)| (name, String::from_utf8_lossy(&value).into_owned()))
+            .collect()
+    };
+    with_host(|host| {
+        let token_id = host.next_token_id();
+        host.http_calls.push(HttpCall {
+            token_id,
+            upstream: unsafe { string(upstream_data, upstream_size) },
+            headers: lossy(headers),
+            body: (!body_data.is_null()).then(|| unsafe { slice(body_data, body_size) }.to_vec()),
+            trailers: lossy(trailers),
+            timeout: Duration::from_millis(timeout as u64),
+        });
+        unsafe { *return_token = token_id };
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn proxy_grpc_call(
+    upstream_data: *const u8,
+    upstream_size: usize,
+    service_name_data: *const u8,
+    service_name_size: usize,
+    method_name_data: *const u8,
+    method_name_size: usize,
+    initial_metadata_data: *const u8,
+    initial_metadata_size: usize,
+    message_data_data: *const u8,
+    message_data_size: usize,
+    timeout: u32,
+    return_callout_id: *mut u32,
+) -> Status {
+    let Some(initial_metadata) =
+        deserialize_map(unsafe { slice(initial_metadata_data, initial_metadata_size) })
+    else {
+        return Status::ParseFailure;
+    };
+    with_host(|host| {
+        let token_id = host.next_token_id();
+        host.grpc_calls.push(GrpcCall {
+            token_id,
+            upstream: unsafe { string(upstream_data, upstream_size) },
+            service: unsafe { string(service_name_data, service_name_size) },
+            method: unsafe { string(method_name_data, method_name_size) },
+            initial_metadata,
+            message: (!message_data_data.is_null())
+                .then(|| unsafe { slice(message_data_data, message_data_size) }.to_vec()),
+            timeout: Duration::from_millis(timeout as u64),
+            cancelled: false,
+        });
+        unsafe { *return_callout_id = token_id };
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn proxy_grpc_stream(
+    upstream_data: *const u8,
+    upstream_size: usize,
+    service_name_data: *const u8,
+    service_name_size: usize,
+    method_name_data: *const u8,
+    method_name_size: usize,
+    initial_metadata_data: *const u8,
+    initial_metadata_size: usize,
+    return_stream_id: *mut u32,
+) -> Status {
+    let Some(initial_metadata) =
+        deserialize_map(unsafe { slice(initial_metadata_data, initial_metadata_size) })
+    else {
+        return Status::ParseFailure;
+    };
+    with_host(|host| {
+        let token_id = host.next_token_id();
+        host.grpc_streams.push(GrpcStream {
+            token_id,
+            upstream: unsafe { string(upstream_data, upstream_size) },
+            service: unsafe { string(service_name_data, service_name_size) },
+            method: unsafe { string(method_name_data, method_name_size) },
+            initial_metadata,
+            messages: Vec::new(),
+            end_stream: false,
+            cancelled: false,
+            closed: false,
+        });
+        unsafe { *return_stream_id = token_id };
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_grpc_send(
+    token: u32,
+    message_ptr: *const u8,
+    message_len: usize,
+    end_stream: bool,
+) -> Status {
+    let message = unsafe { slice(message_ptr, message_len) };
+    with_host(|host| {
+        match host
+            .grpc_streams
+            .iter_mut()
+            .find(|stream| stream.token_id == token)
+        {
+            Some(stream) if stream.end_stream || stream.closed || stream.cancelled => {
+                Status::BadArgument
+            }
+            Some(stream) => {
+                stream.messages.push(message.to_vec());
+                stream.end_stream = end_stream;
+                Status::Ok
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_grpc_cancel(token_id: u32) -> Status {
+    with_host(|host| {
+        if let Some(call) = host
+            .grpc_calls
+            .iter_mut()
+            .find(|call| call.token_id == token_id)
+        {
+            call.cancelled = true;
+            Status::Ok
+        } else if let Some(stream) = host
+            .grpc_streams
+            .iter_mut()
+            .find(|stream| stream.token_id == token_id)
+        {
+            stream.cancelled = true;
+            Status::Ok
+        } else {
+            Status::NotFound
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_grpc_close(token_id: u32) -> Status {
+    with_host(|host| {
+        match host
+            .grpc_streams
+            .iter_mut()
+            .find(|stream| stream.token_id == token_id)
+        {
+            Some(stream) => {
+                stream.closed = true;
+                Status::Ok
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_get_status(
+    return_code: *mut u32,
+    return_message_data: *mut *mut u8,
+    return_message_size: *mut usize,
+) -> Status {
+    with_host(|host| {
+        let (code, message) = &host.grpc_status;
+        unsafe {
+            *return_code = *code;
+            match message {
+                Some(message) => {
+                    write_bytes(message.as_bytes(), return_message_data, return_message_size)
+                }
+                None => write_bytes(&[], return_message_data, return_message_size),
+            }
+        }
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_set_effective_context(context_id: u32) -> Status {
+    with_host(|host| host.effective_context_id = context_id);
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_call_foreign_function(
+    function_name_data: *const u8,
+    function_name_size: usize,
+    arguments_data: *const u8,
+    arguments_size: usize,
+    results_data: *mut *mut u8,
+    results_size: *mut usize,
+) -> Status {
+    let function_name = unsafe { string(function_name_data, function_name_size) };
+    let arguments = unsafe { slice(arguments_data, arguments_size) };
+    // The function is taken out of the host while it runs, so that it can
+    // freely call into the testing API.
+    let Some(mut function) = with_host(|host| host.foreign_functions.remove(&function_name)) else {
+        return Status::NotFound;
+    };
+    let result = function(arguments);
+    with_host(|host| {
+        host.foreign_functions
+            .entry(function_name)
+            .or_insert(function);
+    });
+    match result {
+        Ok(Some(results)) => {
+            unsafe { write_bytes(&results, results_data, results_size) };
+            Status::Ok
+        }
+        Ok(None) => {
+            unsafe { write_bytes(&[], results_data, results_size) };
+            Status::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_done() -> Status {
+    with_host(|host| host.done_calls += 1);
+    Status::Ok
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_define_metric(
+    metric_type: MetricType,
+    name_data: *const u8,
+    name_size: usize,
+    return_id: *mut u32,
+) -> Status {
+    let name = unsafe { string(name_data, name_size) };
+    with_host(|host| {
+        let index = match host.metrics.iter().position(|metric| metric.name == name) {
+            Some(index) if host.metrics[index].metric_type != metric_type => {
+                return Status::BadArgument;
+            }
+            Some(index) => index,
+            None => {
+                host.metrics.push(Metric {
+                    metric_type,
+                    name,
+                    value: 0,
+                });
+                host.metrics.len() - 1
+            }
+        };
+        unsafe { *return_id = index as u32 + 1 };
+        Status::Ok
+    })
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn proxy_get_metric(metric_id: u32, return_value: *mut u64) -> Status {
+    with_host(
+        |host| match host.metrics.get((metric_id as usize).wrapping_sub(1)) {
+            Some(metric) => {
+                unsafe { *return_value = metric.value };
+                Status::Ok
+            }
+            None => Status::NotFound,
+        },
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_record_metric(metric_id: u32, value: u64) -> Status {
+    with_host(
+        |host| match host.metrics.get_mut((metric_id as usize).wrapping_sub(1)) {
+            Some(metric) => {
+                metric.value = value;
+                Status::Ok
+            }
+            None => Status::NotFound,
+        },
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn proxy_increment_metric(metric_id: u32, offset: i64) -> Status {
+    with_host(
+        |host| match host.metrics.get_mut((metric_id as usize).wrapping_sub(1)) {
+            Some(metric) if metric.metric_type == MetricType::Counter && offset < 0 => {
+                Status::BadArgument
+            }
+            Some(metric) => {
+                metric.value = metric.value.saturating_add_signed(offset);
+                Status::Ok
+            }
+            None => Status::NotFound,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hostcalls;
+
+    #[test]
+    fn test_log() {
+        reset();
+        hostcalls::log(LogLevel::Info, "hello").unwrap();
+        assert_eq!(get_logs(), vec![(LogLevel::Info, "hello".to_string())]);
+        set_log_level(LogLevel::Warn);
+        #[cfg(not(any(proxy_abi = "0.1.0", proxy_abi = "0.2.0")))]
+        assert_eq!(hostcalls::get_log_level(), Ok(LogLevel::Warn));
+    }
+
+    #[test]
+    fn test_buffers() {
+        reset();
+        assert_eq!(
+            hostcalls::get_buffer(BufferType::HttpRequestBody, 0, 10),
+            Ok(None)
+        );
+        set_buffer(BufferType::HttpRequestBody, Some(b"hello world"));
+        assert_eq!(
+            hostcalls::get_buffer(BufferType::HttpRequestBody, 6, usize::MAX),
+            Ok(Some(b"world".to_vec()))
+        );
+        hostcalls::set_buffer(BufferType::HttpRequestBody, 0, 5, b"howdy").unwrap();
+        hostcalls::set_buffer(BufferType::HttpRequestBody, 0, 0, b">").unwrap();
+        hostcalls::set_buffer(BufferType::HttpRequestBody, 12, 0, b"!").unwrap();
+        assert_eq!(
+            get_buffer(BufferType::HttpRequestBody),
+            Some(b">howdy world!".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_header_maps() {
+        reset();
+        set_map(
+            MapType::HttpRequestHeaders,
+            vec![(":path", "/"), ("X-Foo", "bar")],
+        );
+        assert_eq!(
+            hostcalls::get_map_value(MapType::HttpRequestHeaders, "x-foo"),
+            Ok(Some("bar".to_string()))
+        );
+        hostcalls::add_map_value(MapType::HttpRequestHeaders, "x-foo", "baz").unwrap();
+        hostcalls::set_map_value(MapType::HttpRequestHeaders, ":path", Some("/new")).unwrap();
+        hostcalls::remove_map_value(MapType::HttpRequestHeaders, "missing").unwrap();
+        assert_eq!(
+            get_map(MapType::HttpRequestHeaders),
+            vec![
+                ("x-foo".to_string(), "bar".to_string()),
+                ("x-foo".to_string(), "baz".to_string()),
+                (":path".to_string(), "/new".to_string()),
+            ]
+        );
+        hostcalls::set_map(MapType::HttpResponseHeaders, vec![(":status", "200")]).unwrap();
+        assert_eq!(
+            get_map_value(MapType::HttpResponseHeaders, ":status"),
+            Some("200".to_string())
+        );
+    }
+
+    #[test]
+    fn test_malformed_maps() {
+        reset();
+        // Claims more pairs than the bytes can hold.
+        let map = u32::MAX.to_le_bytes();
+        let mut token = 0;
+        unsafe {
+            assert_eq!(
+                proxy_set_header_map_pairs(MapType::HttpRequestHeaders, map.as_ptr(), map.len()),
+                Status::ParseFailure
+            );
+            assert_eq!(
+                proxy_http_call(
+                    b"upstream".as_ptr(),
+                    8,
+                    map.as_ptr(),
+                    map.len(),
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null(),
+                    0,
+                    1000,
+                    &mut token,
+                ),
+                Status::ParseFailure
+            );
+            assert_eq!(
+                proxy_grpc_stream(
+                    b"upstream".as_ptr(),
+                    8,
+                    b"service".as_ptr(),
+                    7,
+                    b"method".as_ptr(),
+                    6,
+                    map.as_ptr(),
+                    map.len(),
+                    &mut token,
+                ),
+                Status::ParseFailure
+            );
+        }
+        assert!(get_http_calls().is_empty());
+    }
+
+    #[test]
+    fn test_properties() {
+        reset();
+        set_property(vec!["source", "address"], Some(b"127.0.0.1:8080"));
+        assert_eq!(
+            hostcalls::get_property(vec!["source", "address"]),
+            Ok(Some(b"127.0.0.1:8080".to_vec()))
+        );
+        assert_eq!(hostcalls::get_property(vec!["source"]), Ok(None));
+        hostcalls::set_property(vec!["wasm", "key"], Some(b"value")).unwrap();
+        assert_eq!(get_property(vec!["wasm", "key"]), Some(b"value".to_vec()));
+    }
+
+    #[test]
+    fn test_shared_data_cas() {
+        reset();
+        assert_eq!(hostcalls::get_shared_data("key"), Ok((None, None)));
+        hostcalls::set_shared_data("key", Some(b"one"), None).unwrap();
+        let (value, cas) = hostcalls::get_shared_data("key").unwrap();
+        assert_eq!(value, Some(b"one".to_vec()));
+        hostcalls::set_shared_data("key", Some(b"two"), cas).unwrap();
+        assert_eq!(
+            hostcalls::set_shared_data("key", Some(b"three"), cas),
+            Err(Status::CasMismatch)
+        );
+        assert_eq!(get_shared_data("key").0, Some(b"two".to_vec()));
+        hostcalls::set_shared_data("key", None, None).unwrap();
+        assert_eq!(get_shared_data("key"), (None, None));
+    }
+
+    #[test]
+    fn test_shared_queues() {
+        reset();
+        set_vm_id("vm");
+        let queue_id = hostcalls::register_shared_queue("queue").unwrap();
+        assert_eq!(
+            hostcalls::resolve_shared_queue("vm", "queue"),
+            Ok(Some(queue_id))
+        );
+        assert_eq!(hostcalls::resolve_shared_queue("vm", "other"), Ok(None));
+        assert_eq!(hostcalls::dequeue_shared_queue(queue_id), Ok(None));
+        hostcalls::enqueue_shared_queue(queue_id, Some(b"one")).unwrap();
+        enqueue_shared_queue(queue_id, b"two").unwrap();
+        assert_eq!(
+            hostcalls::dequeue_shared_queue(queue_id),
+            Ok(Some(b"one".to_vec()))
+        );
+        assert_eq!(get_shared_queue(queue_id), Some(vec![b"two".to_vec()]));
+        assert_eq!(
+            hostcalls::enqueue_shared_queue(42, None),
+            Err(Status::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        reset();
+        let counter = hostcalls::define_metric(MetricType::Counter, "requests").unwrap();
+        let gauge = hostcalls::define_metric(MetricType::Gauge, "active").unwrap();
+        hostcalls::increment_metric(counter, 2).unwrap();
+        hostcalls::increment_metric(gauge, 5).unwrap();
+        hostcalls::increment_metric(gauge, -1).unwrap();
+        assert_eq!(
+            hostcalls::increment_metric(counter, -1),
+            Err(Status::BadArgument)
+        );
+        assert_eq!(hostcalls::get_metric(counter), Ok(2));
+        assert_eq!(get_metric("active"), Some((MetricType::Gauge, 4)));
+        assert_eq!(
+            hostcalls::define_metric(MetricType::Counter, "requests"),
+            Ok(counter)
+        );
+    }
+
+    #[test]
+    fn test_timers_and_time() {
+        reset();
+        assert_eq!(get_tick_period(), None);
+        hostcalls::set_tick_period(Duration::from_secs(5)).unwrap();
+        assert_eq!(get_tick_period(), Some(Duration::from_secs(5)));
+        set_current_time(UNIX_EPOCH + Duration::from_secs(42));
+        assert_eq!(
+            hostcalls::get_current_time(),
+            Ok(UNIX_EPOCH + Duration::from_secs(42))
+        );
+    }
+
+    #[test]
+    fn test_http_call() {
+        reset();
+        let token_id = hostcalls::dispatch_http_call(
+            "cluster",
+            vec![(":method", "GET"), (":path", "/")],
+            Some(b"body"),
+            vec![],
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(
+            take_http_calls(),
+            vec![HttpCall {
+                token_id,
+                upstream: "cluster".to_string(),
+                headers: vec![
+                    (":method".to_string(), "GET".to_string()),
+                    (":path".to_string(), "/".to_string()),
+                ],
+                body: Some(b"body".to_vec()),
+                trailers: vec![],
+                timeout: Duration::from_secs(1),
+            }]
+        );
+        assert!(get_http_calls().is_empty());
+    }
+
+    #[test]
+    fn test_grpc() {
+        reset();
+        let call = hostcalls::dispatch_grpc_call(
+            "cluster",
+            "service",
+            "method",
+            vec![("key", b"value")],
+            Some(b"message"),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        hostcalls::cancel_grpc_call(call).unwrap();
+        assert!(get_grpc_calls()[0].cancelled);
+
+        let stream = hostcalls::open_grpc_stream("cluster", "service", "method", vec![]).unwrap();
+        hostcalls::send_grpc_stream_message(stream, Some(b"one"), true).unwrap();
+        assert_eq!(
+            hostcalls::send_grpc_stream_message(stream, Some(b"two"), false),
+            Err(Status::BadArgument)
+        );
+        hostcalls::close_grpc_stream(stream).unwrap();
+        let streams = get_grpc_streams();
+        assert_eq!(streams[0].messages, vec![b"one".to_vec()]);
+        assert!(streams[0].end_stream && streams[0].closed);
+
+        set_grpc_status(14, Some("unavailable"));
+        assert_eq!(
+            hostcalls::get_grpc_status(),
+            Ok((14, Some("unavailable".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_local_response_and_streams() {
+        reset();
+        hostcalls::send_http_response(403, vec![("powered-by", "proxy-wasm")], Some(b"denied"))
+            .unwrap();
+        let response = take_local_response().unwrap();
+        assert_eq!(response.status_code, 403);
+        assert_eq!(response.body, b"denied");
+        assert_eq!(
+            response.headers,
+            vec![("powered-by".to_string(), b"proxy-wasm".to_vec())]
+        );
+        hostcalls::resume_http_request().unwrap();
+        assert_eq!(get_resumed_streams(), vec![StreamType::HttpRequest]);
+        #[cfg(not(proxy_abi = "0.1.0"))]
+        {
+            hostcalls::reset_http_response().unwrap();
+            assert_eq!(get_closed_streams(), vec![StreamType::HttpResponse]);
+        }
+    }
+
+    #[test]
+    fn test_foreign_function() {
+        reset();
+        assert_eq!(
+            hostcalls::call_foreign_function("echo", None),
+            Err(Status::NotFound)
+        );
+        set_foreign_function("echo", Box::new(|args| Ok(Some(args.to_vec()))));
+        assert_eq!(
+            hostcalls::call_foreign_function("echo", Some(b"ping")),
+            Ok(Some(b"ping".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Timers of each root context, multiplexed on top of its tick period.
+//
+// The host only supports a single tick period per root context, so it's
+// reprogrammed to the earliest deadline after every change, and ticks that
+// were requested with `set_tick_period` are only delivered once their own
+// period has elapsed.
+
+use crate::hostcalls;
+use crate::traits::RootContext;
+use crate::types::*;
+use hashbrown::HashMap;
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, SystemTime};
+
+struct Entry {
+    id: u32,
+    deadline: SystemTime,
+    interval: Option<Duration>,
+    callback: TimerCallback,
+}
+
+#[derive(Default)]
+struct Schedule {
+    tick_period: Option<Duration>,
+    next_tick: Option<SystemTime>,
+    programmed: Duration,
+    entries: Vec<Entry>,
+}
+
+impl Schedule {
+    // Sets the host's tick period to fire at the earliest deadline.
+    fn reprogram(&mut self, now: SystemTime) -> Result<(), Status> {
+        let next = self
+            .entries
+            .iter()
+            .map(|entry| entry.deadline)
+            .chain(self.next_tick)
+            .min();
+        let period = match next {
+            // The host counts in milliseconds, so round up to avoid early ticks.
+            Some(next) => {
+                let delay = next.duration_since(now).unwrap_or_default();
+                Duration::from_millis((delay.as_nanos().div_ceil(1_000_000) as u64).max(1))
+            }
+            None => Duration::ZERO,
+        };
+        if period != self.programmed {
+            hostcalls::set_tick_period(period)?;
+            self.programmed = period;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Timers {
+    next_id: Cell<u32>,
+    schedules: RefCell<HashMap<u32, Schedule>>,
+    running: Cell<Option<u32>>,
+}
+
+impl Timers {
+    pub(crate) fn set_tick_period(&self, context_id: u32, period: Duration) -> Result<(), Status> {
+        let mut schedules = self.schedules.borrow_mut();
+        let schedule = schedules.entry(context_id).or_default();
+        schedule.tick_period = Some(period).filter(|period| !period.is_zero());
+        // Without timers, the period is passed through as is, which doesn't need the time.
+        if schedule.entries.is_empty() {
+            schedule.next_tick = None;
+            hostcalls::set_tick_period(period)?;
+            schedule.programmed = period;
+            return Ok(());
+        }
+        let now = hostcalls::get_current_time()?;
+        schedule.next_tick = schedule.tick_period.map(|period| now + period);
+        schedule.reprogram(now)
+    }
+
+    pub(crate) fn add(
+        &self,
+        context_id: u32,
+        delay: Duration,
+        interval: Option<Duration>,
+        callback: TimerCallback,
+    ) -> Result<Timer, Status> {
+        let now = hostcalls::get_current_time()?;
+        let id = self.next_id.get().wrapping_add(1);
+        self.next_id.set(id);
+        let mut schedules = self.schedules.borrow_mut();
+        let schedule = schedules.entry(context_id).or_default();
+        if schedule.next_tick.is_none() {
+            schedule.next_tick = schedule.tick_period.map(|period| now + period);
+        }
+        schedule.entries.push(Entry {
+            id,
+            deadline: now + delay,
+            interval,
+            callback,
+        });
+        schedule.reprogram(now)?;
+        Ok(Timer::new(id))
+    }
+
+    // The host's tick period is left as is, and reprogrammed on the next tick,
+    // or stopped if there are no other timers left.
+    pub(crate) fn cancel(&self, id: u32) {
+        if self.running.get() == Some(id) {
+            self.running.set(None);
+        }
+        for schedule in self.schedules.borrow_mut().values_mut() {
+            schedule.entries.retain(|entry| entry.id != id);
+        }
+    }
+
+    pub(crate) fn remove(&self, context_id: u32) {
+        self.schedules.borrow_mut().remove(&context_id);
+    }
+
+    // Runs the timers that are due, and returns whether the tick should also
+    // be delivered to `RootContext::on_tick`.
+    pub(crate) fn on_tick(&self, context_id: u32, root: &mut dyn RootContext) -> bool {
+        match self.schedules.borrow_mut().get_mut(&context_id) {
+            Some(schedule) if !schedule.entries.is_empty() || schedule.next_tick.is_some() => {}
+            // The host was only ticking for timers that were cancelled since.
+            Some(schedule) if schedule.tick_period.is_none() && !schedule.programmed.is_zero() => {
+                if hostcalls::set_tick_period(Duration::ZERO).is_ok() {
+                    schedule.programmed = Duration::ZERO;
+                }
+                return false;
+            }
+            // Ticks of a tick period that was passed through are all delivered.
+            _ => return true,
+        }
+        let now = match hostcalls::get_current_time() {
+            Ok(now) => now,
+            Err(_) => return true,
+        };
+
+        // Timers rescheduled while running wait for the next tick, even if
+        // they're already due again.
+        let mut due = match self.schedules.borrow().get(&context_id) {
+            Some(schedule) => schedule
+                .entries
+                .iter()
+                .filter(|entry| entry.deadline <= now)
+                .map(|entry| (entry.deadline, entry.id))
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+        due.sort();
+        for (_, id) in due {
+            // The timer is taken out while it runs, since it can add or cancel timers.
+            let entry = self
+                .schedules
+                .borrow_mut()
+                .get_mut(&context_id)
+                .and_then(|s| {
+                    let index = s.entries.iter().position(|entry| entry.id == id)?;
+                    Some(s.entries.remove(index))
+                });
+            let mut entry = match entry {
+                Some(entry) => entry,
+                None => continue,
+            };
+            self.running.set(Some(id));
+            (entry.callback)(root);
+            let cancelled = self.running.take().is_none();
+            if let (Some(interval), false) = (entry.interval, cancelled) {
+                entry.deadline = (entry.deadline + interval).max(now + interval);
+                if let Some(schedule) = self.schedules.borrow_mut().get_mut(&context_id) {
+                    schedule.entries.push(entry);
+                }
+            }
+        }
+
+        let mut schedules = self.schedules.borrow_mut();
+        let schedule = match schedules.get_mut(&context_id) {
+            Some(schedule) => schedule,
+            None => return false,
+        };
+        let tick = match (schedule.tick_period, schedule.next_tick) {
+            (Some(period), Some(next_tick))
+                if schedule.programmed == period || next_tick <= now =>
+            {
+                schedule.next_tick = Some(now + period);
+                true
+            }
+            _ => false,
+        };
+        schedule.reprogram(now).ok();
+        tick
+    }
+}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::Timers;
+    use crate::harness::Harness;
+    use crate::testing;
+    use crate::traits::*;
+    use crate::types::*;
+    use std::cell::RefCell;
+    use std::time::{Duration, SystemTime};
+
+    thread_local! {
+    static EVENTS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(event: &'static str) {
+        EVENTS.with(|events| events.borrow_mut().push(event));
+    }
+
+    #[derive(Default)]
+    struct Root {
+        flush: Option<Timer>,
+    }
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn on_configure(&mut self, _: usize) -> bool {
+            self.set_tick_period(Duration::from_secs(10));
+            self.flush =
+                Some(self.set_interval(Duration::from_secs(5), Box::new(|_| record("flush"))));
+            self.set_timeout(
+                Duration::from_secs(7),
+                Box::new(|root| {
+                    record("renew");
+                    root.set_timeout(Duration::from_secs(1), Box::new(|_| record("retry")));
+                }),
+            );
+            true
+        }
+
+        fn on_tick(&mut self) {
+            record("tick");
+            if let Some(flush) = self.flush.take() {
+                flush.cancel();
+            }
+        }
+    }
+
+    #[test]
+    fn test_timers() {
+        let mut harness = Harness::new().with_root_context(|_| Box::<Root>::default());
+        testing::set_current_time(SystemTime::UNIX_EPOCH);
+        harness.start();
+        assert_eq!(testing::get_tick_period(), Some(Duration::from_secs(5)));
+
+        let mut tick = |secs| {
+            testing::set_current_time(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            harness.tick();
+            (
+                EVENTS.with(|events| events.take()),
+                testing::get_tick_period(),
+            )
+        };
+        let secs = |secs| Some(Duration::from_secs(secs));
+        assert_eq!(tick(5), (vec!["flush"], secs(2)));
+        assert_eq!(tick(7), (vec!["renew"], secs(1)));
+        assert_eq!(tick(8), (vec!["retry"], secs(2)));
+        assert_eq!(tick(10), (vec!["flush", "tick"], secs(5)));
+        assert_eq!(tick(15), (vec![], secs(5)));
+        assert_eq!(tick(20), (vec!["tick"], secs(10)));
+    }
+
+    struct Cancelled;
+
+    impl Context for Cancelled {}
+
+    impl RootContext for Cancelled {
+        fn on_configure(&mut self, _: usize) -> bool {
+            self.set_timeout(Duration::from_secs(5), Box::new(|_| record("timeout")))
+                .cancel();
+            true
+        }
+
+        fn on_tick(&mut self) {
+            record("tick");
+        }
+    }
+
+    #[test]
+    fn test_cancel_last_timer() {
+        let mut harness = Harness::new().with_root_context(|_| Box::new(Cancelled));
+        testing::set_current_time(SystemTime::UNIX_EPOCH);
+        harness.start();
+        assert_eq!(testing::get_tick_period(), Some(Duration::from_secs(5)));
+
+        testing::set_current_time(SystemTime::UNIX_EPOCH + Duration::from_secs(5));
+        harness.tick();
+        assert_eq!(testing::get_tick_period(), None);
+        assert_eq!(EVENTS.with(|events| events.take()), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_tick_period_without_timers() {
+        testing::reset();
+        let timers = Timers::default();
+        timers.set_tick_period(1, Duration::from_secs(10)).unwrap();
+        assert_eq!(testing::get_tick_period(), Some(Duration::from_secs(10)));
+        // The time isn't needed until a timer is added.
+        assert_eq!(timers.schedules.borrow()[&1].next_tick, None);
+        assert!(timers.on_tick(1, &mut Root::default()));
+    }
+}
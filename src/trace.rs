@@ -0,0 +1,808 @@
+// Copyright 2026 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording and replay of hostcalls.
+//!
+//! With the `trace` feature enabled, the callbacks made by the host and the
+//! hostcalls made by the plugin in response can be recorded into a compact,
+//! line-based trace:
+//!
+//! ```text
+//! > proxy_on_request_headers 2 5 0
+//! < get_map_value 0 ":path" = "/hello"
+//! < log 2 "#2%20->%20/hello" = ()
+//! < get_shared_data "missing" = - -
+//! < set_buffer 0 0 5 "HELLO" = !2
+//! ```
+//!
+//! Callbacks (`>`) list their arguments. Hostcalls (`<`) list their arguments
+//! and the value returned by the host, or `!` followed by the [`Status`] for
+//! errors. Strings and bytes are quoted, with spaces, quotes, `%` and
+//! non-printable bytes percent-encoded; `-` stands for `None`, and `[...]`
+//! for lists.
+//!
+//! Recording is started with [`start`], and the trace is retrieved with
+//! [`take`], or written to the host's log with [`flush`]. With the `testing`
+//! feature, [`replay`] feeds a trace back to the plugin.
+
+use crate::hostcalls;
+use crate::types::*;
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Prefix of trace lines written to the host's log by [`flush`].
+pub const LOG_PREFIX: &str = "proxy-wasm-trace: ";
+
+#[derive(Default)]
+struct Tracer {
+    recording: bool,
+    suspended: bool,
+    trace: String,
+    #[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+    replay: Option<std::collections::VecDeque<Record>>,
+}
+
+thread_local! {
+static TRACER: RefCell<Tracer> = RefCell::new(Tracer::default());
+}
+
+/// Starts recording callbacks and hostcalls.
+pub fn start() {
+    TRACER.with_borrow_mut(|tracer| tracer.recording = true);
+}
+
+/// Stops recording. Records that weren't taken yet are kept.
+pub fn stop() {
+    TRACER.with_borrow_mut(|tracer| tracer.recording = false);
+}
+
+/// Returns the records made since the previous call, one per line.
+pub fn take() -> String {
+    TRACER.with_borrow_mut(|tracer| std::mem::take(&mut tracer.trace))
+}
+
+/// Writes the records made since the previous call to the host's log, each
+/// prefixed with [`LOG_PREFIX`]. The log hostcalls made by `flush` itself
+/// aren't recorded.
+pub fn flush(level: LogLevel) {
+    let trace = take();
+    TRACER.with_borrow_mut(|tracer| tracer.suspended = true);
+    for line in trace.lines() {
+        hostcalls::log(level, &format!("{LOG_PREFIX}{line}")).ok();
+    }
+    TRACER.with_borrow_mut(|tracer| tracer.suspended = false);
+}
+
+pub(crate) fn callback(name: &str, args: &[u64]) {
+    TRACER.with_borrow_mut(|tracer| {
+        if tracer.recording && !tracer.suspended {
+            tracer.trace.push_str("> ");
+            tracer.trace.push_str(name);
+            for arg in args {
+                write!(tracer.trace, " {arg}").unwrap();
+            }
+            tracer.trace.push('\n');
+        }
+    });
+}
+
+pub(crate) fn hostcall<A: Encode, R: Encode + Decode>(
+    name: &str,
+    args: A,
+    call: impl FnOnce() -> R,
+) -> R {
+    #[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+    if TRACER.with_borrow(|tracer| tracer.replay.is_some() && !tracer.suspended) {
+        return replay_hostcall(name, args);
+    }
+    let result = call();
+    TRACER.with_borrow_mut(|tracer| {
+        if tracer.recording && !tracer.suspended {
+            let mut line = Encoder(format!("< {name}"));
+            args.encode(&mut line);
+            line.token("=");
+            let len = line.0.len();
+            result.encode(&mut line);
+            if line.0.len() == len {
+                line.token("()");
+            }
+            tracer.trace.push_str(&line.0);
+            tracer.trace.push('\n');
+        }
+    });
+    result
+}
+
+pub(crate) struct Encoder(String);
+
+impl Encoder {
+    fn separate(&mut self) {
+        if !self.0.is_empty() && !self.0.ends_with('[') {
+            self.0.push(' ');
+        }
+    }
+
+    fn token(&mut self, token: &str) {
+        self.separate();
+        self.0.push_str(token);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.separate();
+        self.0.push('"');
+        for &byte in bytes {
+            match byte {
+                b'"' | b'%' => write!(self.0, "%{byte:02X}").unwrap(),
+                0x21..=0x7e => self.0.push(byte as char),
+                _ => write!(self.0, "%{byte:02X}").unwrap(),
+            }
+        }
+        self.0.push('"');
+    }
+
+    fn list(&mut self, items: impl FnOnce(&mut Self)) {
+        self.separate();
+        self.0.push('[');
+        items(self);
+        self.0.push(']');
+    }
+}
+
+// Decoding is only needed to replay traces.
+#[cfg_attr(
+    not(all(feature = "testing", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) struct Decoder<'a>(&'a str);
+
+#[cfg_attr(
+    not(all(feature = "testing", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+impl<'a> Decoder<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        let input = self.0.trim_start();
+        let end = match input.chars().next()? {
+            '[' | ']' => 1,
+            '"' => input[1..].find('"')? + 2,
+            _ => input.find([' ', ']']).unwrap_or(input.len()),
+        };
+        Some(&input[..end])
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        let input = self.0.trim_start();
+        self.0 = &input[token.len()..];
+        Some(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Option<()> {
+        (self.next()? == expected).then_some(())
+    }
+
+    fn int<T: std::str::FromStr>(&mut self) -> Option<T> {
+        self.next()?.parse().ok()
+    }
+
+    fn bytes(&mut self) -> Option<Bytes> {
+        let token = self.next()?.strip_prefix('"')?.strip_suffix('"')?;
+        let mut bytes = Vec::with_capacity(token.len());
+        let mut input = token.as_bytes();
+        while let Some((&byte, rest)) = input.split_first() {
+            if byte == b'%' {
+                let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                input = &rest[2..];
+            } else {
+                bytes.push(byte);
+                input = rest;
+            }
+        }
+        Some(bytes)
+    }
+
+    fn list<T>(&mut self, item: impl Fn(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        while self.peek()? != "]" {
+            items.push(item(self)?);
+        }
+        self.expect("]")?;
+        Some(items)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+pub(crate) trait Encode {
+    fn encode(&self, encoder: &mut Encoder);
+}
+
+#[cfg_attr(
+    not(all(feature = "testing", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) trait Decode: Sized {
+    fn decode(decoder: &mut Decoder) -> Option<Self>;
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self).encode(encoder)
+    }
+}
+
+macro_rules! int {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, encoder: &mut Encoder) {
+                encoder.token(&self.to_string());
+            }
+        }
+
+        impl Decode for $t {
+            fn decode(decoder: &mut Decoder) -> Option<Self> {
+                decoder.int()
+            }
+        }
+    )*};
+}
+
+int!(u32, u64, i64, usize);
+
+macro_rules! enums {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, encoder: &mut Encoder) {
+                (*self as u32).encode(encoder);
+            }
+        }
+    )*};
+}
+
+enums!(BufferType, MapType, MetricType, GrpcStatusCode);
+
+impl Encode for bool {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u32).encode(encoder);
+    }
+}
+
+impl Encode for LogLevel {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u32).encode(encoder);
+    }
+}
+
+impl Decode for LogLevel {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        match decoder.int()? {
+            0 => Some(LogLevel::Trace),
+            1 => Some(LogLevel::Debug),
+            2 => Some(LogLevel::Info),
+            3 => Some(LogLevel::Warn),
+            4 => Some(LogLevel::Error),
+            5 => Some(LogLevel::Critical),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(
+    not(all(feature = "testing", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+fn decode_status(status: u32) -> Option<Status> {
+    match status {
+        0 => Some(Status::Ok),
+        1 => Some(Status::NotFound),
+        2 => Some(Status::BadArgument),
+        3 => Some(Status::SerializationFailure),
+        4 => Some(Status::ParseFailure),
+        5 => Some(Status::BadExpression),
+        6 => Some(Status::InvalidMemoryAccess),
+        7 => Some(Status::Empty),
+        8 => Some(Status::CasMismatch),
+        9 => Some(Status::ResultMismatch),
+        10 => Some(Status::InternalFailure),
+        11 => Some(Status::BrokenConnection),
+        12 => Some(Status::Unimplemented),
+        _ => None,
+    }
+}
+
+impl Encode for Duration {
+    fn encode(&self, encoder: &mut Encoder) {
+        (self.as_millis() as u64).encode(encoder);
+    }
+}
+
+impl Encode for SystemTime {
+    fn encode(&self, encoder: &mut Encoder) {
+        let nanos = self
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        (nanos as u64).encode(encoder);
+    }
+}
+
+impl Decode for SystemTime {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(UNIX_EPOCH + Duration::from_nanos(decoder.int()?))
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        String::from_utf8(decoder.bytes()?).ok()
+    }
+}
+
+impl Encode for [u8] {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(self);
+    }
+}
+
+impl Encode for Bytes {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(self);
+    }
+}
+
+impl Decode for Bytes {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        decoder.bytes()
+    }
+}
+
+impl Encode for Vec<&str> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(|encoder| self.iter().for_each(|item| item.encode(encoder)));
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for Vec<(A, B)> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(|encoder| {
+            for (a, b) in self {
+                a.encode(encoder);
+                b.encode(encoder);
+            }
+        });
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for Vec<(A, B)> {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        decoder.list(|decoder| Some((A::decode(decoder)?, B::decode(decoder)?)))
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Some(value) => value.encode(encoder),
+            None => encoder.token("-"),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        if decoder.peek()? == "-" {
+            decoder.next();
+            return Some(None);
+        }
+        Some(Some(T::decode(decoder)?))
+    }
+}
+
+impl<T: Encode> Encode for Result<T, Status> {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Ok(value) => value.encode(encoder),
+            Err(status) => encoder.token(&format!("!{}", *status as u32)),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Result<T, Status> {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        if let Some(status) = decoder.peek()?.strip_prefix('!') {
+            decoder.next();
+            return Some(Err(decode_status(status.parse().ok()?)?));
+        }
+        Some(Ok(T::decode(decoder)?))
+    }
+}
+
+impl Encode for () {
+    fn encode(&self, _: &mut Encoder) {}
+}
+
+impl Decode for () {
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        decoder.expect("()")
+    }
+}
+
+macro_rules! tuple {
+    ($($t:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($t: Encode),*> Encode for ($($t,)*) {
+            fn encode(&self, encoder: &mut Encoder) {
+                let ($($t,)*) = self;
+                $($t.encode(encoder);)*
+            }
+        }
+
+        impl<$($t: Decode),*> Decode for ($($t,)*) {
+            fn decode(decoder: &mut Decoder) -> Option<Self> {
+                Some(($($t::decode(decoder)?,)*))
+            }
+        }
+    };
+}
+
+tuple!(A, B);
+tuple!(A, B, C);
+tuple!(A, B, C, D);
+tuple!(A, B, C, D, E);
+tuple!(A, B, C, D, E, F);
+
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+struct Record {
+    line: usize,
+    callback: bool,
+    name: String,
+    args: String,
+    result: String,
+}
+
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+fn parse(trace: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    for (index, line) in trace.lines().enumerate() {
+        let line_number = index + 1;
+        let line = match line.find(LOG_PREFIX) {
+            Some(start) => &line[start + LOG_PREFIX.len()..],
+            None => line,
+        };
+        let (callback, record) = match line.trim().split_at_checked(2) {
+            Some(("> ", record)) => (true, record),
+            Some(("< ", record)) => (false, record),
+            _ => continue,
+        };
+        let (record, result) = match callback {
+            true => (record, ""),
+            false => record
+                .split_once(" = ")
+                .unwrap_or_else(|| panic!("malformed trace at line {line_number}: {line}")),
+        };
+        let (name, args) = record.split_once(' ').unwrap_or((record, ""));
+        records.push(Record {
+            line: line_number,
+            callback,
+            name: name.to_string(),
+            args: args.to_string(),
+            result: result.to_string(),
+        });
+    }
+    records
+}
+
+/// Replays a trace made by [`start`], driving the plugin registered in the
+/// current thread with the recorded callbacks, and answering its hostcalls
+/// with the recorded results instead of calling the host.
+///
+/// The trace can be a host log containing lines written by [`flush`].
+/// Hostcalls recorded before the first callback (e.g. from `main!`) are
+/// skipped, since the plugin is expected to be set up already.
+///
+/// # Panics
+///
+/// Panics if the plugin diverges from the trace, i.e. it makes a hostcall
+/// with different arguments than the recorded one, or more or fewer
+/// hostcalls than were recorded for a callback.
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub fn replay(trace: &str) {
+    struct ReplayGuard;
+
+    impl Drop for ReplayGuard {
+        fn drop(&mut self) {
+            TRACER.with_borrow_mut(|tracer| tracer.replay = None);
+        }
+    }
+
+    let mut records = parse(trace)
+        .into_iter()
+        .skip_while(|record| !record.callback);
+    let _guard = ReplayGuard;
+    let mut next = records.next();
+    while let Some(callback) = next {
+        let mut hostcalls = std::collections::VecDeque::new();
+        next = None;
+        for record in records.by_ref() {
+            if record.callback {
+                next = Some(record);
+                break;
+            }
+            hostcalls.push_back(record);
+        }
+        TRACER.with_borrow_mut(|tracer| tracer.replay = Some(hostcalls));
+        dispatch(&callback);
+        let hostcalls = TRACER.with_borrow_mut(|tracer| tracer.replay.take());
+        if let Some(record) = hostcalls.and_then(|mut hostcalls| hostcalls.pop_front()) {
+            panic!(
+                "replay diverged at line {}: plugin didn't make hostcall `{} {}`",
+                record.line, record.name, record.args
+            );
+        }
+    }
+}
+
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+fn replay_hostcall<A: Encode, R: Decode>(name: &str, args: A) -> R {
+    let mut encoded = Encoder(String::new());
+    args.encode(&mut encoded);
+    let encoded = encoded.0;
+    let record = TRACER.with_borrow_mut(|tracer| tracer.replay.as_mut()?.pop_front());
+    let Some(record) = record else {
+        panic!("replay diverged: plugin made unexpected hostcall `{name} {encoded}`");
+    };
+    if record.name != name || record.args != encoded {
+        panic!(
+            "replay diverged at line {}: expected hostcall `{} {}`, got `{name} {encoded}`",
+            record.line, record.name, record.args
+        );
+    }
+    let mut decoder = Decoder(&record.result);
+    match R::decode(&mut decoder) {
+        Some(result) if decoder.is_empty() => result,
+        _ => panic!(
+            "malformed trace at line {}: invalid result `{}`",
+            record.line, record.result
+        ),
+    }
+}
+
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+fn dispatch(record: &Record) {
+    let args: Vec<u64> = record
+        .args
+        .split_whitespace()
+        .map(|arg| arg.parse())
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|_| panic!("malformed trace at line {}", record.line));
+    let arg = |index: usize| -> u64 {
+        *args.get(index).unwrap_or_else(|| {
+            panic!(
+                "malformed trace at line {}: missing argument {index}",
+                record.line
+            )
+        })
+    };
+    let peer_type = |index: usize| match arg(index) {
+        1 => PeerType::Local,
+        2 => PeerType::Remote,
+        _ => PeerType::Unknown,
+    };
+    crate::dispatcher::with_dispatcher(|dispatcher| match record.name.as_str() {
+        "proxy_on_context_create" => dispatcher.on_create_context(arg(0) as u32, arg(1) as u32),
+        "proxy_on_done" => {
+            dispatcher.on_done(arg(0) as u32);
+        }
+        "proxy_on_log" => dispatcher.on_log(arg(0) as u32),
+        "proxy_on_delete" => dispatcher.on_delete(arg(0) as u32),
+        "proxy_on_vm_start" => {
+            dispatcher.on_vm_start(arg(0) as u32, arg(1) as usize);
+        }
+        "proxy_on_configure" => {
+            dispatcher.on_configure(arg(0) as u32, arg(1) as usize);
+        }
+        "proxy_on_tick" => dispatcher.on_tick(arg(0) as u32),
+        "proxy_on_queue_ready" => dispatcher.on_queue_ready(arg(0) as u32, arg(1) as u32),
+        "proxy_on_new_connection" => {
+            dispatcher.on_new_connection(arg(0) as u32);
+        }
+        "proxy_on_downstream_data" => {
+            dispatcher.on_downstream_data(arg(0) as u32, arg(1) as usize, arg(2) != 0);
+        }
+        "proxy_on_downstream_connection_close" => {
+            dispatcher.on_downstream_close(arg(0) as u32, peer_type(1))
+        }
+        "proxy_on_upstream_data" => {
+            dispatcher.on_upstream_data(arg(0) as u32, arg(1) as usize, arg(2) != 0);
+        }
+        "proxy_on_upstream_connection_close" => {
+            dispatcher.on_upstream_close(arg(0) as u32, peer_type(1))
+        }
+        "proxy_on_request_headers" => {
+            // ABI v0.1.0 doesn't pass end_of_stream.
+            let end_of_stream = args.get(2).is_some_and(|&arg| arg != 0);
+            dispatcher.on_http_request_headers(arg(0) as u32, arg(1) as usize, end_of_stream);
+        }
+        "proxy_on_request_body" => {
+            dispatcher.on_http_request_body(arg(0) as u32, arg(1) as usize, arg(2) != 0);
+        }
+        "proxy_on_request_trailers" => {
+            dispatcher.on_http_request_trailers(arg(0) as u32, arg(1) as usize);
+        }
+        #[cfg(proxy_abi = "vnext")]
+        "proxy_on_request_metadata" => {
+            dispatcher.on_http_request_metadata(arg(0) as u32, arg(1) as usize);
+        }
+        "proxy_on_response_headers" => {
+            let end_of_stream = args.get(2).is_some_and(|&arg| arg != 0);
+            dispatcher.on_http_response_headers(arg(0) as u32, arg(1) as usize, end_of_stream);
+        }
+        "proxy_on_response_body" => {
+            dispatcher.on_http_response_body(arg(0) as u32, arg(1) as usize, arg(2) != 0);
+        }
+        "proxy_on_response_trailers" => {
+            dispatcher.on_http_response_trailers(arg(0) as u32, arg(1) as usize);
+        }
+        #[cfg(proxy_abi = "vnext")]
+        "proxy_on_response_metadata" => {
+            dispatcher.on_http_response_metadata(arg(0) as u32, arg(1) as usize);
+        }
+        "proxy_on_http_call_response" => dispatcher.on_http_call_response(
+            arg(1) as u32,
+            arg(2) as usize,
+            arg(3) as usize,
+            arg(4) as usize,
+        ),
+        "proxy_on_grpc_receive_initial_metadata" => {
+            dispatcher.on_grpc_receive_initial_metadata(arg(1) as u32, arg(2) as u32)
+        }
+        "proxy_on_grpc_receive" => dispatcher.on_grpc_receive(arg(1) as u32, arg(2) as usize),
+        "proxy_on_grpc_receive_trailing_metadata" => {
+            dispatcher.on_grpc_receive_trailing_metadata(arg(1) as u32, arg(2) as u32)
+        }
+        "proxy_on_grpc_close" => dispatcher.on_grpc_close(arg(1) as u32, arg(2) as u32),
+        "proxy_on_foreign_function" => {
+            dispatcher.on_foreign_function(arg(0) as u32, arg(1) as u32, arg(2) as usize)
+        }
+        name => panic!(
+            "malformed trace at line {}: unknown callback `{name}`",
+            record.line
+        ),
+    });
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::dispatcher::*;
+    use crate::testing;
+    use crate::traits::*;
+
+    struct Root;
+
+    impl Context for Root {}
+
+    impl RootContext for Root {
+        fn create_http_context(&self, _: u32) -> Option<Box<dyn HttpContext>> {
+            Some(Box::new(Filter))
+        }
+
+        fn get_type(&self) -> Option<ContextType> {
+            Some(ContextType::HttpContext)
+        }
+    }
+
+    struct Filter;
+
+    impl Context for Filter {}
+
+    impl HttpContext for Filter {
+        fn on_http_request_headers(&mut self, _: usize, _: bool) -> Action {
+            let path = self.get_http_request_header(":path");
+            self.set_http_request_header("x-path", path.as_deref());
+            self.get_shared_data("missing");
+            Action::Continue
+        }
+    }
+
+    fn record() -> Vec<(LogLevel, String)> {
+        std::thread::spawn(|| {
+            crate::set_root_context(|_| Box::new(Root));
+            testing::set_map(MapType::HttpRequestHeaders, vec![(":path", "/hello world")]);
+            start();
+            proxy_on_context_create(1, 0);
+            proxy_on_context_create(2, 1);
+            #[cfg(not(proxy_abi = "0.1.0"))]
+            proxy_on_request_headers(2, 1, true);
+            #[cfg(proxy_abi = "0.1.0")]
+            proxy_on_request_headers(2, 1);
+            proxy_on_delete(2);
+            flush(LogLevel::Trace);
+            testing::get_logs()
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_record() {
+        let logs = record();
+        let trace: Vec<&str> = logs.iter().map(|(_, line)| line.as_str()).collect();
+        assert_eq!(
+            trace,
+            [
+                "proxy-wasm-trace: > proxy_on_context_create 1 0",
+                "proxy-wasm-trace: > proxy_on_context_create 2 1",
+                #[cfg(not(proxy_abi = "0.1.0"))]
+                "proxy-wasm-trace: > proxy_on_request_headers 2 1 1",
+                #[cfg(proxy_abi = "0.1.0")]
+                "proxy-wasm-trace: > proxy_on_request_headers 2 1",
+                "proxy-wasm-trace: < get_map_value 0 \":path\" = \"/hello%20world\"",
+                "proxy-wasm-trace: < set_map_value 0 \"x-path\" \"/hello%20world\" = ()",
+                "proxy-wasm-trace: < get_shared_data \"missing\" = - -",
+                "proxy-wasm-trace: > proxy_on_delete 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let log = record()
+            .into_iter()
+            .map(|(_, line)| format!("[info] wasm log: {line}\n"))
+            .collect::<String>();
+        crate::set_root_context(|_| Box::new(Root));
+        replay(&log);
+        // Hostcalls were answered from the trace, not by the host.
+        assert_eq!(testing::get_map(MapType::HttpRequestHeaders), vec![]);
+    }
+
+    #[test]
+    fn test_decode_status() {
+        for code in 0..=12 {
+            assert_eq!(decode_status(code).map(|status| status as u32), Some(code));
+        }
+        assert_eq!(decode_status(13), None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected hostcall `set_map_value 0 \"x-path\" \"/hello%20world\"`, got `set_map_value 0 \"x-path\" \"/other\"`"
+    )]
+    fn test_replay_diverged() {
+        let log = record()
+            .into_iter()
+            .map(|(_, line)| line.replace("= \"/hello%20world\"", "= \"/other\"") + "\n")
+            .collect::<String>();
+        crate::set_root_context(|_| Box::new(Root));
+        replay(&log);
+    }
+}
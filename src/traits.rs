@@ -12,10 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::dispatcher;
+use crate::headers::HeaderMap;
 use crate::hostcalls;
+use crate::metrics::{Counter, Family, Gauge, Histogram, Metric};
+use crate::protobuf;
 use crate::types::*;
+use std::any::Any;
 use std::time::{Duration, SystemTime};
 
+macro_rules! try_hostcall {
+    ($hostcall:ident($($arg:expr),*)) => {
+        hostcalls::$hostcall($($arg),*).map_err(|status| {
+            HostcallError::new(status, stringify!($hostcall), &[$(&$arg),*])
+        })
+    };
+}
+
 pub trait Context {
     fn get_current_time(&self) -> SystemTime {
         hostcalls::get_current_time().unwrap()
@@ -25,6 +38,17 @@ pub trait Context {
         hostcalls::get_property(path).unwrap()
     }
 
+    /// Returns a property serialized as `google.protobuf.Struct`, e.g. `node.metadata`.
+    fn get_struct_property(&self, path: Vec<&str>) -> Option<protobuf::Value> {
+        hostcalls::get_property(path)
+            .and_then(|bytes| {
+                bytes
+                    .map(|bytes| protobuf::decode_struct(&bytes))
+                    .transpose()
+            })
+            .unwrap()
+    }
+
     fn set_property(&self, path: Vec<&str>, value: Option<&[u8]>) {
         hostcalls::set_property(path, value).unwrap()
     }
@@ -157,6 +181,22 @@ pub trait Context {
         hostcalls::open_grpc_stream(cluster_name, service_name, method_name, initial_metadata)
     }
 
+    /// Like [`Context::open_grpc_stream`], but the stream's events are delivered to `handler`
+    /// instead of the `on_grpc_stream_*` callbacks.
+    fn open_grpc_stream_with_handler(
+        &self,
+        cluster_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        handler: Box<dyn GrpcStreamHandler>,
+    ) -> Result<u32, Status> {
+        let token_id =
+            hostcalls::open_grpc_stream(cluster_name, service_name, method_name, initial_metadata)?;
+        dispatcher::register_grpc_stream_handler(token_id, handler);
+        Ok(token_id)
+    }
+
     fn on_grpc_stream_initial_metadata(&mut self, _token_id: u32, _num_elements: u32) {}
 
     fn get_grpc_stream_initial_metadata(&self) -> Vec<(String, Bytes)> {
@@ -220,7 +260,54 @@ pub trait Context {
     }
 }
 
-pub trait RootContext: Context {
+/// Converts any context to `dyn Context`, since trait objects can't be upcast on all
+/// supported Rust versions.
+pub trait AsContext {
+    fn as_context(&mut self) -> &mut dyn Context;
+}
+
+impl<T: Context> AsContext for T {
+    fn as_context(&mut self) -> &mut dyn Context {
+        self
+    }
+}
+
+/// Converts a root context to `dyn Any`, so that its streams can downcast it with
+/// [`HttpContext::with_root`] or [`StreamContext::with_root`].
+pub trait AsAny {
+    fn as_any(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Receives the events of a gRPC stream opened with [`Context::open_grpc_stream_with_handler`].
+pub trait GrpcStreamHandler {
+    fn on_initial_metadata(
+        &mut self,
+        _context: &mut dyn Context,
+        _token_id: u32,
+        _metadata: Vec<(String, Bytes)>,
+    ) {
+    }
+
+    fn on_message(&mut self, _context: &mut dyn Context, _token_id: u32, _message: Bytes) {}
+
+    fn on_trailing_metadata(
+        &mut self,
+        _context: &mut dyn Context,
+        _token_id: u32,
+        _metadata: Vec<(String, Bytes)>,
+    ) {
+    }
+
+    fn on_close(&mut self, _context: &mut dyn Context, _token_id: u32, _status_code: u32) {}
+}
+
+pub trait RootContext: Context + AsContext + AsAny {
     fn on_vm_start(&mut self, _vm_configuration_size: usize) -> bool {
         true
     }
@@ -238,15 +325,90 @@ pub trait RootContext: Context {
     }
 
     fn set_tick_period(&self, period: Duration) {
-        hostcalls::set_tick_period(period).unwrap()
+        dispatcher::set_tick_period(period).unwrap()
     }
 
     fn on_tick(&mut self) {}
 
+    /// Calls `callback` on the first tick after `delay` elapses.
+    ///
+    /// Timers share the host's tick period with [`RootContext::on_tick`], which is
+    /// reprogrammed to the earliest deadline, so the tick period must only be set
+    /// with [`RootContext::set_tick_period`].
+    fn set_timeout(&self, delay: Duration, callback: TimerCallback) -> Timer {
+        dispatcher::set_timer(delay, None, callback).unwrap()
+    }
+
+    /// Like [`RootContext::set_timeout`], but `callback` is called every `interval`
+    /// until the timer is cancelled.
+    fn set_interval(&self, interval: Duration, callback: TimerCallback) -> Timer {
+        dispatcher::set_timer(interval, Some(interval), callback).unwrap()
+    }
+
+    /// Like [`Context::dispatch_http_call`], but the response is delivered to `callback`
+    /// instead of [`Context::on_http_call_response`].
+    fn dispatch_http_call_with_callback(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+        callback: RootHttpCallCallback,
+    ) -> Result<u32, Status> {
+        let token_id = hostcalls::dispatch_http_call(upstream, headers, body, trailers, timeout)?;
+        dispatcher::register_http_callback(token_id, dispatcher::HttpCallHandler::Root(callback));
+        Ok(token_id)
+    }
+
+    /// Like [`Context::dispatch_grpc_call`], but the response is delivered to `callback`
+    /// instead of [`Context::on_grpc_call_response`].
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch_grpc_call_with_callback(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+        callback: RootGrpcCallCallback,
+    ) -> Result<u32, Status> {
+        let token_id = hostcalls::dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata,
+            message,
+            timeout,
+        )?;
+        dispatcher::register_grpc_callback(token_id, dispatcher::GrpcCallHandler::Root(callback));
+        Ok(token_id)
+    }
+
     fn on_queue_ready(&mut self, _queue_id: u32) {}
 
     fn on_log(&mut self) {}
 
+    fn define_counter(&self, name: &str) -> Counter {
+        Counter::new(name)
+    }
+
+    fn define_gauge(&self, name: &str) -> Gauge {
+        Gauge::new(name)
+    }
+
+    fn define_histogram(&self, name: &str) -> Histogram {
+        Histogram::new(name)
+    }
+
+    fn define_metric_family<M: Metric>(&self, name: &str, labels: &[&str]) -> Family<M>
+    where
+        Self: Sized,
+    {
+        Family::new(name, labels)
+    }
+
     fn create_http_context(&self, _context_id: u32) -> Option<Box<dyn HttpContext>> {
         None
     }
@@ -260,7 +422,7 @@ pub trait RootContext: Context {
     }
 }
 
-pub trait StreamContext: Context {
+pub trait StreamContext: Context + AsContext {
     fn on_new_connection(&mut self) -> Action {
         Action::Continue
     }
@@ -277,10 +439,12 @@ pub trait StreamContext: Context {
         hostcalls::set_buffer(BufferType::DownstreamData, start, size, value).unwrap()
     }
 
+    #[cfg(not(proxy_abi = "0.1.0"))]
     fn resume_downstream(&self) {
         hostcalls::resume_downstream().unwrap()
     }
 
+    #[cfg(not(proxy_abi = "0.1.0"))]
     fn close_downstream(&self) {
         hostcalls::close_downstream().unwrap()
     }
@@ -299,10 +463,12 @@ pub trait StreamContext: Context {
         hostcalls::set_buffer(BufferType::UpstreamData, start, size, value).unwrap()
     }
 
+    #[cfg(not(proxy_abi = "0.1.0"))]
     fn resume_upstream(&self) {
         hostcalls::resume_upstream().unwrap()
     }
 
+    #[cfg(not(proxy_abi = "0.1.0"))]
     fn close_upstream(&self) {
         hostcalls::close_upstream().unwrap()
     }
@@ -310,9 +476,61 @@ pub trait StreamContext: Context {
     fn on_upstream_close(&mut self, _peer_type: PeerType) {}
 
     fn on_log(&mut self) {}
+
+    /// Like [`Context::dispatch_http_call`], but the response is delivered to `callback`
+    /// instead of [`Context::on_http_call_response`].
+    fn dispatch_http_call_with_callback(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+        callback: StreamHttpCallCallback,
+    ) -> Result<u32, Status> {
+        let token_id = hostcalls::dispatch_http_call(upstream, headers, body, trailers, timeout)?;
+        dispatcher::register_http_callback(token_id, dispatcher::HttpCallHandler::Stream(callback));
+        Ok(token_id)
+    }
+
+    /// Like [`Context::dispatch_grpc_call`], but the response is delivered to `callback`
+    /// instead of [`Context::on_grpc_call_response`].
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch_grpc_call_with_callback(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+        callback: StreamGrpcCallCallback,
+    ) -> Result<u32, Status> {
+        let token_id = hostcalls::dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata,
+            message,
+            timeout,
+        )?;
+        dispatcher::register_grpc_callback(token_id, dispatcher::GrpcCallHandler::Stream(callback));
+        Ok(token_id)
+    }
+
+    /// Calls `f` with the root context that created this stream, if it's an `R`.
+    ///
+    /// Returns `None` if the root context is in use, e.g. while it's creating
+    /// this stream.
+    fn with_root<R: RootContext + 'static, T>(&self, f: impl FnOnce(&mut R) -> T) -> Option<T>
+    where
+        Self: Sized,
+    {
+        dispatcher::with_root(f)
+    }
 }
 
-pub trait HttpContext: Context {
+pub trait HttpContext: Context + AsContext {
     fn on_http_request_headers(&mut self, _num_headers: usize, _end_of_stream: bool) -> Action {
         Action::Continue
     }
@@ -333,6 +551,14 @@ pub trait HttpContext: Context {
         hostcalls::set_map_bytes(MapType::HttpRequestHeaders, headers).unwrap()
     }
 
+    fn get_http_request_header_map(&self) -> HeaderMap {
+        HeaderMap::from(hostcalls::get_map_bytes(MapType::HttpRequestHeaders).unwrap())
+    }
+
+    fn set_http_request_header_map(&self, headers: &HeaderMap) {
+        hostcalls::set_map_bytes(MapType::HttpRequestHeaders, headers.to_vec()).unwrap()
+    }
+
     fn get_http_request_header(&self, name: &str) -> Option<String> {
         hostcalls::get_map_value(MapType::HttpRequestHeaders, name).unwrap()
     }
@@ -421,10 +647,31 @@ pub trait HttpContext: Context {
         hostcalls::remove_map_value(MapType::HttpRequestTrailers, name).unwrap()
     }
 
+    #[cfg(proxy_abi = "vnext")]
+    fn on_http_request_metadata(&mut self, _num_elements: usize) -> Action {
+        Action::Continue
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn get_http_request_metadata(&self) -> Vec<(String, Bytes)> {
+        hostcalls::get_map_bytes(MapType::HttpRequestMetadata).unwrap()
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn get_http_request_metadata_value(&self, name: &str) -> Option<Bytes> {
+        hostcalls::get_map_value_bytes(MapType::HttpRequestMetadata, name).unwrap()
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn set_http_request_metadata_value(&self, name: &str, value: Option<&[u8]>) {
+        hostcalls::set_map_value_bytes(MapType::HttpRequestMetadata, name, value).unwrap()
+    }
+
     fn resume_http_request(&self) {
         hostcalls::resume_http_request().unwrap()
     }
 
+    #[cfg(not(proxy_abi = "0.1.0"))]
     fn reset_http_request(&self) {
         hostcalls::reset_http_request().unwrap()
     }
@@ -449,6 +696,14 @@ pub trait HttpContext: Context {
         hostcalls::set_map_bytes(MapType::HttpResponseHeaders, headers).unwrap()
     }
 
+    fn get_http_response_header_map(&self) -> HeaderMap {
+        HeaderMap::from(hostcalls::get_map_bytes(MapType::HttpResponseHeaders).unwrap())
+    }
+
+    fn set_http_response_header_map(&self, headers: &HeaderMap) {
+        hostcalls::set_map_bytes(MapType::HttpResponseHeaders, headers.to_vec()).unwrap()
+    }
+
     fn get_http_response_header(&self, name: &str) -> Option<String> {
         hostcalls::get_map_value(MapType::HttpResponseHeaders, name).unwrap()
     }
@@ -537,10 +792,31 @@ pub trait HttpContext: Context {
         hostcalls::remove_map_value(MapType::HttpResponseTrailers, name).unwrap()
     }
 
+    #[cfg(proxy_abi = "vnext")]
+    fn on_http_response_metadata(&mut self, _num_elements: usize) -> Action {
+        Action::Continue
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn get_http_response_metadata(&self) -> Vec<(String, Bytes)> {
+        hostcalls::get_map_bytes(MapType::HttpResponseMetadata).unwrap()
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn get_http_response_metadata_value(&self, name: &str) -> Option<Bytes> {
+        hostcalls::get_map_value_bytes(MapType::HttpResponseMetadata, name).unwrap()
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn set_http_response_metadata_value(&self, name: &str, value: Option<&[u8]>) {
+        hostcalls::set_map_value_bytes(MapType::HttpResponseMetadata, name, value).unwrap()
+    }
+
     fn resume_http_response(&self) {
         hostcalls::resume_http_response().unwrap()
     }
 
+    #[cfg(not(proxy_abi = "0.1.0"))]
     fn reset_http_response(&self) {
         hostcalls::reset_http_response().unwrap()
     }
@@ -564,4 +840,955 @@ pub trait HttpContext: Context {
     }
 
     fn on_log(&mut self) {}
+
+    /// Like [`Context::dispatch_http_call`], but the response is delivered to `callback`
+    /// instead of [`Context::on_http_call_response`].
+    fn dispatch_http_call_with_callback(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+        callback: HttpCallCallback,
+    ) -> Result<u32, Status> {
+        let token_id = hostcalls::dispatch_http_call(upstream, headers, body, trailers, timeout)?;
+        dispatcher::register_http_callback(token_id, dispatcher::HttpCallHandler::Http(callback));
+        Ok(token_id)
+    }
+
+    /// Like [`Context::dispatch_grpc_call`], but the response is delivered to `callback`
+    /// instead of [`Context::on_grpc_call_response`].
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch_grpc_call_with_callback(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+        callback: HttpGrpcCallCallback,
+    ) -> Result<u32, Status> {
+        let token_id = hostcalls::dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata,
+            message,
+            timeout,
+        )?;
+        dispatcher::register_grpc_callback(token_id, dispatcher::GrpcCallHandler::Http(callback));
+        Ok(token_id)
+    }
+
+    /// Calls `f` with the root context that created this stream, if it's an `R`.
+    ///
+    /// Returns `None` if the root context is in use, e.g. while it's creating
+    /// this stream.
+    fn with_root<R: RootContext + 'static, T>(&self, f: impl FnOnce(&mut R) -> T) -> Option<T>
+    where
+        Self: Sized,
+    {
+        dispatcher::with_root(f)
+    }
+}
+
+/// Variants of [`Context`] helpers that return a [`HostcallError`] instead of panicking.
+pub trait TryContext: Context {
+    fn try_get_current_time(&self) -> Result<SystemTime, HostcallError> {
+        try_hostcall!(get_current_time())
+    }
+
+    fn try_get_property(&self, path: Vec<&str>) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_property(path.clone()))
+    }
+
+    fn try_get_struct_property(
+        &self,
+        path: Vec<&str>,
+    ) -> Result<Option<protobuf::Value>, HostcallError> {
+        self.try_get_property(path.clone())?
+            .map(|bytes| protobuf::decode_struct(&bytes))
+            .transpose()
+            .map_err(|status| HostcallError::new(status, "get_property", &[&path]))
+    }
+
+    fn try_set_property(&self, path: Vec<&str>, value: Option<&[u8]>) -> Result<(), HostcallError> {
+        try_hostcall!(set_property(path.clone(), value))
+    }
+
+    fn try_get_shared_data(
+        &self,
+        key: &str,
+    ) -> Result<(Option<Bytes>, Option<u32>), HostcallError> {
+        try_hostcall!(get_shared_data(key))
+    }
+
+    fn try_set_shared_data(
+        &self,
+        key: &str,
+        value: Option<&[u8]>,
+        cas: Option<u32>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_shared_data(key, value, cas))
+    }
+
+    fn try_remove_shared_data(&self, key: &str, cas: Option<u32>) -> Result<(), HostcallError> {
+        self.try_set_shared_data(key, None, cas)
+    }
+
+    fn try_register_shared_queue(&self, name: &str) -> Result<u32, HostcallError> {
+        try_hostcall!(register_shared_queue(name))
+    }
+
+    fn try_resolve_shared_queue(
+        &self,
+        vm_id: &str,
+        name: &str,
+    ) -> Result<Option<u32>, HostcallError> {
+        try_hostcall!(resolve_shared_queue(vm_id, name))
+    }
+
+    fn try_dequeue_shared_queue(&self, queue_id: u32) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(dequeue_shared_queue(queue_id))
+    }
+
+    fn try_enqueue_shared_queue(
+        &self,
+        queue_id: u32,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(enqueue_shared_queue(queue_id, value))
+    }
+
+    fn try_dispatch_http_call(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+    ) -> Result<u32, HostcallError> {
+        try_hostcall!(dispatch_http_call(
+            upstream,
+            headers.clone(),
+            body,
+            trailers.clone(),
+            timeout
+        ))
+    }
+
+    fn try_get_http_call_response_headers(&self) -> Result<Vec<(String, String)>, HostcallError> {
+        try_hostcall!(get_map(MapType::HttpCallResponseHeaders))
+    }
+
+    fn try_get_http_call_response_headers_bytes(
+        &self,
+    ) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpCallResponseHeaders))
+    }
+
+    fn try_get_http_call_response_header(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, HostcallError> {
+        try_hostcall!(get_map_value(MapType::HttpCallResponseHeaders, name))
+    }
+
+    fn try_get_http_call_response_header_bytes(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpCallResponseHeaders, name))
+    }
+
+    fn try_get_http_call_response_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(
+            BufferType::HttpCallResponseBody,
+            start,
+            max_size
+        ))
+    }
+
+    fn try_get_http_call_response_trailers(&self) -> Result<Vec<(String, String)>, HostcallError> {
+        try_hostcall!(get_map(MapType::HttpCallResponseTrailers))
+    }
+
+    fn try_get_http_call_response_trailers_bytes(
+        &self,
+    ) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpCallResponseTrailers))
+    }
+
+    fn try_get_http_call_response_trailer(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, HostcallError> {
+        try_hostcall!(get_map_value(MapType::HttpCallResponseTrailers, name))
+    }
+
+    fn try_get_http_call_response_trailer_bytes(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpCallResponseTrailers, name))
+    }
+
+    fn try_dispatch_grpc_call(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+    ) -> Result<u32, HostcallError> {
+        try_hostcall!(dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata.clone(),
+            message,
+            timeout
+        ))
+    }
+
+    fn try_get_grpc_call_response_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::GrpcReceiveBuffer, start, max_size))
+    }
+
+    fn try_cancel_grpc_call(&self, token_id: u32) -> Result<(), HostcallError> {
+        try_hostcall!(cancel_grpc_call(token_id))
+    }
+
+    fn try_open_grpc_stream(
+        &self,
+        cluster_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+    ) -> Result<u32, HostcallError> {
+        try_hostcall!(open_grpc_stream(
+            cluster_name,
+            service_name,
+            method_name,
+            initial_metadata.clone()
+        ))
+    }
+
+    fn try_open_grpc_stream_with_handler(
+        &self,
+        cluster_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        handler: Box<dyn GrpcStreamHandler>,
+    ) -> Result<u32, HostcallError> {
+        let token_id =
+            self.try_open_grpc_stream(cluster_name, service_name, method_name, initial_metadata)?;
+        dispatcher::register_grpc_stream_handler(token_id, handler);
+        Ok(token_id)
+    }
+
+    fn try_get_grpc_stream_initial_metadata(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::GrpcReceiveInitialMetadata))
+    }
+
+    fn try_get_grpc_stream_initial_metadata_value(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(
+            MapType::GrpcReceiveInitialMetadata,
+            name
+        ))
+    }
+
+    fn try_send_grpc_stream_message(
+        &self,
+        token_id: u32,
+        message: Option<&[u8]>,
+        end_stream: bool,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(send_grpc_stream_message(token_id, message, end_stream))
+    }
+
+    fn try_get_grpc_stream_message(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::GrpcReceiveBuffer, start, max_size))
+    }
+
+    fn try_get_grpc_stream_trailing_metadata(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::GrpcReceiveTrailingMetadata))
+    }
+
+    fn try_get_grpc_stream_trailing_metadata_value(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(
+            MapType::GrpcReceiveTrailingMetadata,
+            name
+        ))
+    }
+
+    fn try_cancel_grpc_stream(&self, token_id: u32) -> Result<(), HostcallError> {
+        try_hostcall!(cancel_grpc_stream(token_id))
+    }
+
+    fn try_close_grpc_stream(&self, token_id: u32) -> Result<(), HostcallError> {
+        try_hostcall!(close_grpc_stream(token_id))
+    }
+
+    fn try_get_grpc_status(&self) -> Result<(u32, Option<String>), HostcallError> {
+        try_hostcall!(get_grpc_status())
+    }
+
+    fn try_call_foreign_function(
+        &self,
+        function_name: &str,
+        arguments: Option<&[u8]>,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(call_foreign_function(function_name, arguments))
+    }
+
+    fn try_done(&self) -> Result<(), HostcallError> {
+        try_hostcall!(done())
+    }
+}
+
+impl<T: Context + ?Sized> TryContext for T {}
+
+/// Variants of [`RootContext`] helpers that return a [`HostcallError`] instead of panicking.
+pub trait TryRootContext: RootContext {
+    fn try_get_vm_configuration(&self) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::VmConfiguration, 0, usize::MAX))
+    }
+
+    fn try_get_plugin_configuration(&self) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::PluginConfiguration, 0, usize::MAX))
+    }
+
+    fn try_set_tick_period(&self, period: Duration) -> Result<(), HostcallError> {
+        dispatcher::set_tick_period(period)
+            .map_err(|status| HostcallError::new(status, "set_tick_period", &[&period]))
+    }
+
+    fn try_set_timeout(
+        &self,
+        delay: Duration,
+        callback: TimerCallback,
+    ) -> Result<Timer, HostcallError> {
+        dispatcher::set_timer(delay, None, callback)
+            .map_err(|status| HostcallError::new(status, "set_timeout", &[&delay]))
+    }
+
+    fn try_set_interval(
+        &self,
+        interval: Duration,
+        callback: TimerCallback,
+    ) -> Result<Timer, HostcallError> {
+        dispatcher::set_timer(interval, Some(interval), callback)
+            .map_err(|status| HostcallError::new(status, "set_interval", &[&interval]))
+    }
+
+    fn try_dispatch_http_call_with_callback(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+        callback: RootHttpCallCallback,
+    ) -> Result<u32, HostcallError> {
+        let token_id = self.try_dispatch_http_call(upstream, headers, body, trailers, timeout)?;
+        dispatcher::register_http_callback(token_id, dispatcher::HttpCallHandler::Root(callback));
+        Ok(token_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn try_dispatch_grpc_call_with_callback(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+        callback: RootGrpcCallCallback,
+    ) -> Result<u32, HostcallError> {
+        let token_id = self.try_dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata,
+            message,
+            timeout,
+        )?;
+        dispatcher::register_grpc_callback(token_id, dispatcher::GrpcCallHandler::Root(callback));
+        Ok(token_id)
+    }
+}
+
+impl<T: RootContext + ?Sized> TryRootContext for T {}
+
+/// Variants of [`StreamContext`] helpers that return a [`HostcallError`] instead of panicking.
+pub trait TryStreamContext: StreamContext {
+    fn try_get_downstream_data(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::DownstreamData, start, max_size))
+    }
+
+    fn try_set_downstream_data(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_buffer(BufferType::DownstreamData, start, size, value))
+    }
+
+    #[cfg(not(proxy_abi = "0.1.0"))]
+    fn try_resume_downstream(&self) -> Result<(), HostcallError> {
+        try_hostcall!(resume_downstream())
+    }
+
+    #[cfg(not(proxy_abi = "0.1.0"))]
+    fn try_close_downstream(&self) -> Result<(), HostcallError> {
+        try_hostcall!(close_downstream())
+    }
+
+    fn try_get_upstream_data(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::UpstreamData, start, max_size))
+    }
+
+    fn try_set_upstream_data(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_buffer(BufferType::UpstreamData, start, size, value))
+    }
+
+    #[cfg(not(proxy_abi = "0.1.0"))]
+    fn try_resume_upstream(&self) -> Result<(), HostcallError> {
+        try_hostcall!(resume_upstream())
+    }
+
+    #[cfg(not(proxy_abi = "0.1.0"))]
+    fn try_close_upstream(&self) -> Result<(), HostcallError> {
+        try_hostcall!(close_upstream())
+    }
+
+    fn try_dispatch_http_call_with_callback(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+        callback: StreamHttpCallCallback,
+    ) -> Result<u32, HostcallError> {
+        let token_id = self.try_dispatch_http_call(upstream, headers, body, trailers, timeout)?;
+        dispatcher::register_http_callback(token_id, dispatcher::HttpCallHandler::Stream(callback));
+        Ok(token_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn try_dispatch_grpc_call_with_callback(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+        callback: StreamGrpcCallCallback,
+    ) -> Result<u32, HostcallError> {
+        let token_id = self.try_dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata,
+            message,
+            timeout,
+        )?;
+        dispatcher::register_grpc_callback(token_id, dispatcher::GrpcCallHandler::Stream(callback));
+        Ok(token_id)
+    }
+}
+
+impl<T: StreamContext + ?Sized> TryStreamContext for T {}
+
+/// Variants of [`HttpContext`] helpers that return a [`HostcallError`] instead of panicking.
+pub trait TryHttpContext: HttpContext {
+    fn try_get_http_request_headers(&self) -> Result<Vec<(String, String)>, HostcallError> {
+        try_hostcall!(get_map(MapType::HttpRequestHeaders))
+    }
+
+    fn try_get_http_request_headers_bytes(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpRequestHeaders))
+    }
+
+    fn try_get_http_request_header_map(&self) -> Result<HeaderMap, HostcallError> {
+        self.try_get_http_request_headers_bytes()
+            .map(HeaderMap::from)
+    }
+
+    fn try_get_http_request_header(&self, name: &str) -> Result<Option<String>, HostcallError> {
+        try_hostcall!(get_map_value(MapType::HttpRequestHeaders, name))
+    }
+
+    fn try_get_http_request_header_bytes(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpRequestHeaders, name))
+    }
+
+    fn try_set_http_request_header(
+        &self,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value(MapType::HttpRequestHeaders, name, value))
+    }
+
+    fn try_set_http_request_header_bytes(
+        &self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value_bytes(
+            MapType::HttpRequestHeaders,
+            name,
+            value
+        ))
+    }
+
+    fn try_add_http_request_header(&self, name: &str, value: &str) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value(MapType::HttpRequestHeaders, name, value))
+    }
+
+    fn try_add_http_request_header_bytes(
+        &self,
+        name: &str,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value_bytes(
+            MapType::HttpRequestHeaders,
+            name,
+            value
+        ))
+    }
+
+    fn try_remove_http_request_header(&self, name: &str) -> Result<(), HostcallError> {
+        try_hostcall!(remove_map_value(MapType::HttpRequestHeaders, name))
+    }
+
+    fn try_get_http_request_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::HttpRequestBody, start, max_size))
+    }
+
+    fn try_set_http_request_body(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_buffer(BufferType::HttpRequestBody, start, size, value))
+    }
+
+    fn try_get_http_request_trailers(&self) -> Result<Vec<(String, String)>, HostcallError> {
+        try_hostcall!(get_map(MapType::HttpRequestTrailers))
+    }
+
+    fn try_get_http_request_trailers_bytes(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpRequestTrailers))
+    }
+
+    fn try_get_http_request_trailer(&self, name: &str) -> Result<Option<String>, HostcallError> {
+        try_hostcall!(get_map_value(MapType::HttpRequestTrailers, name))
+    }
+
+    fn try_get_http_request_trailer_bytes(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpRequestTrailers, name))
+    }
+
+    fn try_set_http_request_trailer(
+        &self,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value(MapType::HttpRequestTrailers, name, value))
+    }
+
+    fn try_set_http_request_trailer_bytes(
+        &self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value_bytes(
+            MapType::HttpRequestTrailers,
+            name,
+            value
+        ))
+    }
+
+    fn try_add_http_request_trailer(&self, name: &str, value: &str) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value(MapType::HttpRequestTrailers, name, value))
+    }
+
+    fn try_add_http_request_trailer_bytes(
+        &self,
+        name: &str,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value_bytes(
+            MapType::HttpRequestTrailers,
+            name,
+            value
+        ))
+    }
+
+    fn try_remove_http_request_trailer(&self, name: &str) -> Result<(), HostcallError> {
+        try_hostcall!(remove_map_value(MapType::HttpRequestTrailers, name))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn try_get_http_request_metadata(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpRequestMetadata))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn try_get_http_request_metadata_value(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpRequestMetadata, name))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn try_set_http_request_metadata_value(
+        &self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value_bytes(
+            MapType::HttpRequestMetadata,
+            name,
+            value
+        ))
+    }
+
+    fn try_resume_http_request(&self) -> Result<(), HostcallError> {
+        try_hostcall!(resume_http_request())
+    }
+
+    #[cfg(not(proxy_abi = "0.1.0"))]
+    fn try_reset_http_request(&self) -> Result<(), HostcallError> {
+        try_hostcall!(reset_http_request())
+    }
+
+    fn try_get_http_response_headers(&self) -> Result<Vec<(String, String)>, HostcallError> {
+        try_hostcall!(get_map(MapType::HttpResponseHeaders))
+    }
+
+    fn try_get_http_response_headers_bytes(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpResponseHeaders))
+    }
+
+    fn try_get_http_response_header_map(&self) -> Result<HeaderMap, HostcallError> {
+        self.try_get_http_response_headers_bytes()
+            .map(HeaderMap::from)
+    }
+
+    fn try_get_http_response_header(&self, name: &str) -> Result<Option<String>, HostcallError> {
+        try_hostcall!(get_map_value(MapType::HttpResponseHeaders, name))
+    }
+
+    fn try_get_http_response_header_bytes(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpResponseHeaders, name))
+    }
+
+    fn try_set_http_response_header(
+        &self,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value(MapType::HttpResponseHeaders, name, value))
+    }
+
+    fn try_set_http_response_header_bytes(
+        &self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value_bytes(
+            MapType::HttpResponseHeaders,
+            name,
+            value
+        ))
+    }
+
+    fn try_add_http_response_header(&self, name: &str, value: &str) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value(MapType::HttpResponseHeaders, name, value))
+    }
+
+    fn try_add_http_response_header_bytes(
+        &self,
+        name: &str,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value_bytes(
+            MapType::HttpResponseHeaders,
+            name,
+            value
+        ))
+    }
+
+    fn try_remove_http_response_header(&self, name: &str) -> Result<(), HostcallError> {
+        try_hostcall!(remove_map_value(MapType::HttpResponseHeaders, name))
+    }
+
+    fn try_get_http_response_body(
+        &self,
+        start: usize,
+        max_size: usize,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_buffer(BufferType::HttpResponseBody, start, max_size))
+    }
+
+    fn try_set_http_response_body(
+        &self,
+        start: usize,
+        size: usize,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_buffer(BufferType::HttpResponseBody, start, size, value))
+    }
+
+    fn try_get_http_response_trailers(&self) -> Result<Vec<(String, String)>, HostcallError> {
+        try_hostcall!(get_map(MapType::HttpResponseTrailers))
+    }
+
+    fn try_get_http_response_trailers_bytes(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpResponseTrailers))
+    }
+
+    fn try_get_http_response_trailer(&self, name: &str) -> Result<Option<String>, HostcallError> {
+        try_hostcall!(get_map_value(MapType::HttpResponseTrailers, name))
+    }
+
+    fn try_get_http_response_trailer_bytes(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpResponseTrailers, name))
+    }
+
+    fn try_set_http_response_trailer(
+        &self,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value(MapType::HttpResponseTrailers, name, value))
+    }
+
+    fn try_set_http_response_trailer_bytes(
+        &self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value_bytes(
+            MapType::HttpResponseTrailers,
+            name,
+            value
+        ))
+    }
+
+    fn try_add_http_response_trailer(&self, name: &str, value: &str) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value(MapType::HttpResponseTrailers, name, value))
+    }
+
+    fn try_add_http_response_trailer_bytes(
+        &self,
+        name: &str,
+        value: &[u8],
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(add_map_value_bytes(
+            MapType::HttpResponseTrailers,
+            name,
+            value
+        ))
+    }
+
+    fn try_remove_http_response_trailer(&self, name: &str) -> Result<(), HostcallError> {
+        try_hostcall!(remove_map_value(MapType::HttpResponseTrailers, name))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn try_get_http_response_metadata(&self) -> Result<Vec<(String, Bytes)>, HostcallError> {
+        try_hostcall!(get_map_bytes(MapType::HttpResponseMetadata))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn try_get_http_response_metadata_value(
+        &self,
+        name: &str,
+    ) -> Result<Option<Bytes>, HostcallError> {
+        try_hostcall!(get_map_value_bytes(MapType::HttpResponseMetadata, name))
+    }
+
+    #[cfg(proxy_abi = "vnext")]
+    fn try_set_http_response_metadata_value(
+        &self,
+        name: &str,
+        value: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(set_map_value_bytes(
+            MapType::HttpResponseMetadata,
+            name,
+            value
+        ))
+    }
+
+    fn try_resume_http_response(&self) -> Result<(), HostcallError> {
+        try_hostcall!(resume_http_response())
+    }
+
+    #[cfg(not(proxy_abi = "0.1.0"))]
+    fn try_reset_http_response(&self) -> Result<(), HostcallError> {
+        try_hostcall!(reset_http_response())
+    }
+
+    fn try_send_http_response(
+        &self,
+        status_code: u32,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(send_http_response(status_code, headers.clone(), body))
+    }
+
+    fn try_send_grpc_response(
+        &self,
+        grpc_status: GrpcStatusCode,
+        grpc_status_message: Option<&str>,
+        custom_metadata: Vec<(&str, &[u8])>,
+    ) -> Result<(), HostcallError> {
+        try_hostcall!(send_grpc_response(
+            grpc_status,
+            grpc_status_message,
+            custom_metadata.clone()
+        ))
+    }
+
+    fn try_dispatch_http_call_with_callback(
+        &self,
+        upstream: &str,
+        headers: Vec<(&str, &str)>,
+        body: Option<&[u8]>,
+        trailers: Vec<(&str, &str)>,
+        timeout: Duration,
+        callback: HttpCallCallback,
+    ) -> Result<u32, HostcallError> {
+        let token_id = self.try_dispatch_http_call(upstream, headers, body, trailers, timeout)?;
+        dispatcher::register_http_callback(token_id, dispatcher::HttpCallHandler::Http(callback));
+        Ok(token_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn try_dispatch_grpc_call_with_callback(
+        &self,
+        upstream_name: &str,
+        service_name: &str,
+        method_name: &str,
+        initial_metadata: Vec<(&str, &[u8])>,
+        message: Option<&[u8]>,
+        timeout: Duration,
+        callback: HttpGrpcCallCallback,
+    ) -> Result<u32, HostcallError> {
+        let token_id = self.try_dispatch_grpc_call(
+            upstream_name,
+            service_name,
+            method_name,
+            initial_metadata,
+            message,
+            timeout,
+        )?;
+        dispatcher::register_grpc_callback(token_id, dispatcher::GrpcCallHandler::Http(callback));
+        Ok(token_id)
+    }
+}
+
+impl<T: HttpContext + ?Sized> TryHttpContext for T {}
+
+#[cfg(all(test, feature = "testing", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    struct Root;
+
+    impl Context for Root {}
+
+    impl StreamContext for Root {}
+
+    #[test]
+    fn test_try_hostcall() {
+        testing::reset();
+        let error = Root
+            .try_enqueue_shared_queue(7, Some(b"hello"))
+            .unwrap_err();
+        assert_eq!(error.status(), Status::NotFound);
+        assert_eq!(error.hostcall(), "enqueue_shared_queue");
+        assert_eq!(
+            error.to_string(),
+            "enqueue_shared_queue(7, Some([104, 101, 108, 108, 111])) failed: NotFound"
+        );
+
+        let body = vec![b'x'; 1024];
+        let error = HostcallError::new(Status::BadArgument, "set_buffer", &[&body]);
+        assert_eq!(error.arguments().len(), 64 + "...".len());
+    }
+
+    #[test]
+    fn test_try_hostcall_unexpected_status() {
+        testing::reset();
+        testing::set_buffer(BufferType::DownstreamData, Some(b"data"));
+        let error = Root.try_set_downstream_data(100, 0, b"x").unwrap_err();
+        assert_eq!(error.status(), Status::BadArgument);
+        assert_eq!(error.hostcall(), "set_buffer");
+    }
 }
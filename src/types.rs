@@ -13,10 +13,19 @@
 // limitations under the License.
 
 use crate::traits::*;
+use std::fmt;
 
 pub type NewRootContext = fn(context_id: u32) -> Box<dyn RootContext>;
 pub type NewStreamContext = fn(context_id: u32, root_context_id: u32) -> Box<dyn StreamContext>;
 pub type NewHttpContext = fn(context_id: u32, root_context_id: u32) -> Box<dyn HttpContext>;
+pub type ErrorHook = fn(error: &DispatchError);
+pub type RootHttpCallCallback = Box<dyn FnOnce(&mut dyn RootContext, HttpCallResponse)>;
+pub type StreamHttpCallCallback = Box<dyn FnOnce(&mut dyn StreamContext, HttpCallResponse)>;
+pub type HttpCallCallback = Box<dyn FnOnce(&mut dyn HttpContext, HttpCallResponse)>;
+pub type RootGrpcCallCallback = Box<dyn FnOnce(&mut dyn RootContext, GrpcCallResponse)>;
+pub type StreamGrpcCallCallback = Box<dyn FnOnce(&mut dyn StreamContext, GrpcCallResponse)>;
+pub type HttpGrpcCallCallback = Box<dyn FnOnce(&mut dyn HttpContext, GrpcCallResponse)>;
+pub type TimerCallback = Box<dyn FnMut(&mut dyn RootContext)>;
 
 #[repr(u32)]
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -46,9 +55,14 @@ pub enum Status {
     BadArgument = 2,
     SerializationFailure = 3,
     ParseFailure = 4,
+    BadExpression = 5,
+    InvalidMemoryAccess = 6,
     Empty = 7,
     CasMismatch = 8,
+    ResultMismatch = 9,
     InternalFailure = 10,
+    BrokenConnection = 11,
+    Unimplemented = 12,
 }
 
 #[repr(u32)]
@@ -96,6 +110,8 @@ pub enum MapType {
     GrpcReceiveTrailingMetadata = 5,
     HttpCallResponseHeaders = 6,
     HttpCallResponseTrailers = 7,
+    HttpRequestMetadata = 8,
+    HttpResponseMetadata = 9,
 }
 
 #[repr(u32)]
@@ -139,4 +155,208 @@ pub enum GrpcStatusCode {
     Unauthenticated = 16,
 }
 
+/// What the dispatcher does when the host calls into the plugin in a way that
+/// violates one of its invariants, see [`DispatchError`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub enum ErrorPolicy {
+    /// Log the error and ignore the callback.
+    Ignore,
+    /// Log the error, ignore the callback, and reset the affected stream.
+    ///
+    /// ABI v0.1.0 can't reset streams, so HTTP streams get a local 500 response instead.
+    ResetStream,
+    /// Panic, which aborts the VM.
+    #[default]
+    Abort,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum DispatchError {
+    /// The host created a context with an ID that is already in use.
+    DuplicateContextId(u32),
+    /// The host returned a token ID that is already in use for a callout.
+    DuplicateTokenId { token_id: u32, context_id: u32 },
+    /// The host invoked a callback on a context that doesn't exist,
+    /// or that isn't of the right type.
+    InvalidContextId(u32),
+    /// The host created a context under a root context that doesn't exist.
+    InvalidRootContextId {
+        context_id: u32,
+        root_context_id: u32,
+    },
+    /// The host delivered a response for a callout that doesn't exist.
+    InvalidTokenId(u32),
+    /// The root context didn't say which type of context to create.
+    MissingContextType {
+        context_id: u32,
+        root_context_id: u32,
+    },
+    /// The root context didn't create a new context.
+    ContextNotCreated {
+        context_id: u32,
+        root_context_id: u32,
+    },
+    /// The host failed to switch to the context that owns a callout.
+    EffectiveContextFailed { context_id: u32, status: Status },
+}
+
+impl DispatchError {
+    /// Returns the ID of the context affected by the error, if any.
+    pub fn context_id(&self) -> Option<u32> {
+        match *self {
+            DispatchError::DuplicateContextId(context_id)
+            | DispatchError::DuplicateTokenId { context_id, .. }
+            | DispatchError::InvalidContextId(context_id)
+            | DispatchError::InvalidRootContextId { context_id, .. }
+            | DispatchError::MissingContextType { context_id, .. }
+            | DispatchError::ContextNotCreated { context_id, .. }
+            | DispatchError::EffectiveContextFailed { context_id, .. } => Some(context_id),
+            DispatchError::InvalidTokenId(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DispatchError::DuplicateContextId(context_id) => {
+                write!(f, "duplicate context_id: {context_id}")
+            }
+            DispatchError::DuplicateTokenId { token_id, .. } => {
+                write!(f, "duplicate token_id: {token_id}")
+            }
+            DispatchError::InvalidContextId(context_id) => {
+                write!(f, "invalid context_id: {context_id}")
+            }
+            DispatchError::InvalidRootContextId {
+                root_context_id, ..
+            } => write!(f, "invalid root_context_id: {root_context_id}"),
+            DispatchError::InvalidTokenId(token_id) => write!(f, "invalid token_id: {token_id}"),
+            DispatchError::MissingContextType {
+                root_context_id, ..
+            } => write!(f, "missing ContextType on root_context: {root_context_id}"),
+            DispatchError::ContextNotCreated {
+                context_id,
+                root_context_id,
+            } => write!(
+                f,
+                "root_context {root_context_id} didn't create context {context_id}"
+            ),
+            DispatchError::EffectiveContextFailed { context_id, status } => write!(
+                f,
+                "failed to set effective context {context_id}: {status:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct HttpCallResponse {
+    pub headers: Vec<(String, Bytes)>,
+    pub body: Option<Bytes>,
+    pub trailers: Vec<(String, Bytes)>,
+}
+
+impl HttpCallResponse {
+    pub fn header(&self, name: &str) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct GrpcCallResponse {
+    pub status_code: u32,
+    pub status_message: Option<String>,
+    pub message: Option<Bytes>,
+}
+
+/// Handle to a timer set with [`RootContext::set_timeout`] or [`RootContext::set_interval`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Timer(u32);
+
+impl Timer {
+    pub(crate) fn new(id: u32) -> Timer {
+        Timer(id)
+    }
+
+    /// Cancels the timer, if it didn't fire yet or is an interval.
+    pub fn cancel(self) {
+        crate::dispatcher::cancel_timer(self.0)
+    }
+}
+
+/// Error returned by a hostcall, along with the call that failed.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct HostcallError {
+    status: Status,
+    hostcall: &'static str,
+    arguments: String,
+}
+
+impl HostcallError {
+    // Arguments longer than this (e.g. bodies) are truncated.
+    const MAX_ARGUMENT_SIZE: usize = 64;
+
+    pub fn new(status: Status, hostcall: &'static str, arguments: &[&dyn fmt::Debug]) -> Self {
+        let arguments = arguments
+            .iter()
+            .map(|argument| {
+                let mut argument = format!("{argument:?}");
+                if argument.len() > Self::MAX_ARGUMENT_SIZE {
+                    let mut end = Self::MAX_ARGUMENT_SIZE;
+                    while !argument.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    argument.truncate(end);
+                    argument.push_str("...");
+                }
+                argument
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        HostcallError {
+            status,
+            hostcall,
+            arguments,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn hostcall(&self) -> &'static str {
+        self.hostcall
+    }
+
+    pub fn arguments(&self) -> &str {
+        &self.arguments
+    }
+}
+
+impl fmt::Display for HostcallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) failed: {:?}",
+            self.hostcall, self.arguments, self.status
+        )
+    }
+}
+
+impl std::error::Error for HostcallError {}
+
+impl From<HostcallError> for Status {
+    fn from(error: HostcallError) -> Self {
+        error.status
+    }
+}
+
 pub type Bytes = Vec<u8>;